@@ -0,0 +1,203 @@
+//! Adapters exposing [Client] through interfaces familiar from other crates,
+//! to ease migrating code written against them.
+
+use anyhow::Result;
+use serde::de::DeserializeOwned;
+
+use crate::{Client, Row, Statement};
+
+/// A [Client] wrapper with the query methods of `sqlx`'s `Executor`, deserializing rows
+/// into any type implementing [`serde::de::DeserializeOwned`]
+///
+/// Rows are deserialized with [`Row::deserialize_into()`], so struct fields are matched
+/// to columns by name. Each method executes a single statement with [`Client::execute()`].
+///
+/// # Examples
+///
+/// ```
+/// # async fn f() -> anyhow::Result<()> {
+/// use libsql_client::adapters::Executor;
+/// use libsql_client::{args, Client, Statement};
+///
+/// #[derive(serde::Deserialize)]
+/// struct User {
+///     id: i64,
+///     name: String,
+/// }
+///
+/// let db = Executor::new(Client::in_memory()?);
+/// db.execute("CREATE TABLE users(id INTEGER, name TEXT)").await?;
+/// db.execute("INSERT INTO users VALUES (1, 'alice')").await?;
+/// let user: User = db
+///     .fetch_one(Statement::with_args("SELECT * FROM users WHERE id = ?", args!(1)))
+///     .await?;
+/// assert_eq!(user.name, "alice");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Executor {
+    inner: Client,
+}
+
+impl Executor {
+    /// Wraps `inner`
+    pub fn new(inner: Client) -> Self {
+        Self { inner }
+    }
+
+    /// Returns the wrapped client
+    pub fn inner(&self) -> &Client {
+        &self.inner
+    }
+
+    /// Executes a statement, returning the number of rows it changed
+    ///
+    /// # Arguments
+    /// * `stmt` - SQL statement
+    pub async fn execute(&self, stmt: impl Into<Statement> + Send) -> Result<u64> {
+        Ok(self.inner.execute(stmt).await?.rows_affected)
+    }
+
+    /// Executes a statement, deserializing all the rows it returned
+    ///
+    /// # Arguments
+    /// * `stmt` - SQL statement
+    pub async fn fetch_all<T: DeserializeOwned>(
+        &self,
+        stmt: impl Into<Statement> + Send,
+    ) -> Result<Vec<T>> {
+        self.inner
+            .execute(stmt)
+            .await?
+            .rows
+            .iter()
+            .map(Row::deserialize_into)
+            .collect()
+    }
+
+    /// Executes a statement, deserializing the first row it returned
+    ///
+    /// Will return an error if the statement returned no rows.
+    ///
+    /// # Arguments
+    /// * `stmt` - SQL statement
+    pub async fn fetch_one<T: DeserializeOwned>(
+        &self,
+        stmt: impl Into<Statement> + Send,
+    ) -> Result<T> {
+        let stmt: Statement = stmt.into();
+        let sql = stmt.sql.clone();
+        self.fetch_optional(stmt)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Statement {sql:?} returned no rows"))
+    }
+
+    /// Executes a statement, deserializing the first row it returned, if any
+    ///
+    /// # Arguments
+    /// * `stmt` - SQL statement
+    pub async fn fetch_optional<T: DeserializeOwned>(
+        &self,
+        stmt: impl Into<Statement> + Send,
+    ) -> Result<Option<T>> {
+        self.inner
+            .execute(stmt)
+            .await?
+            .rows
+            .first()
+            .map(Row::deserialize_into)
+            .transpose()
+    }
+}
+
+#[cfg(all(test, feature = "reqwest_backend"))]
+mod tests {
+    use super::*;
+    use crate::mock_server::{MockResponse, MockServer};
+    use crate::Config;
+
+    #[derive(Debug, PartialEq, serde::Deserialize)]
+    struct User {
+        id: i64,
+        name: String,
+    }
+
+    async fn executor() -> (MockServer, Executor) {
+        let server = MockServer::start(|req| {
+            if req.body.contains("WHERE 0") {
+                MockResponse::execute(
+                    r#"{"cols":[{"name":"id"},{"name":"name"}],"rows":[],"affected_row_count":0,"last_insert_rowid":null}"#,
+                )
+            } else if req.body.contains("SELECT") {
+                MockResponse::execute(
+                    r#"{"cols":[{"name":"id"},{"name":"name"}],"rows":[[{"type":"integer","value":"1"},{"type":"text","value":"alice"}],[{"type":"integer","value":"2"},{"type":"text","value":"bob"}]],"affected_row_count":0,"last_insert_rowid":null}"#,
+                )
+            } else {
+                MockResponse::execute(
+                    r#"{"cols":[],"rows":[],"affected_row_count":3,"last_insert_rowid":null}"#,
+                )
+            }
+        })
+        .await;
+        let client = Client::from_config(Config::new(server.url.as_str()).unwrap())
+            .await
+            .unwrap();
+        (server, Executor::new(client))
+    }
+
+    #[tokio::test]
+    async fn test_execute() {
+        let (server, db) = executor().await;
+        assert_eq!(db.execute("UPDATE users SET name = 'x'").await.unwrap(), 3);
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_all() {
+        let (_server, db) = executor().await;
+        let users: Vec<User> = db.fetch_all("SELECT * FROM users").await.unwrap();
+        assert_eq!(
+            users,
+            [
+                User {
+                    id: 1,
+                    name: "alice".into()
+                },
+                User {
+                    id: 2,
+                    name: "bob".into()
+                },
+            ]
+        );
+        let users: Vec<User> = db.fetch_all("SELECT * FROM users WHERE 0").await.unwrap();
+        assert!(users.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_fetch_one() {
+        let (_server, db) = executor().await;
+        let user: User = db.fetch_one("SELECT * FROM users").await.unwrap();
+        assert_eq!(user.name, "alice");
+        let err = db
+            .fetch_one::<User>("SELECT * FROM users WHERE 0")
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            r#"Statement "SELECT * FROM users WHERE 0" returned no rows"#
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fetch_optional() {
+        let (_server, db) = executor().await;
+        let user: Option<User> = db.fetch_optional("SELECT * FROM users").await.unwrap();
+        assert_eq!(user.map(|user| user.id), Some(1));
+        let user: Option<User> = db
+            .fetch_optional("SELECT * FROM users WHERE 0")
+            .await
+            .unwrap();
+        assert!(user.is_none());
+    }
+}
@@ -0,0 +1,67 @@
+//! `Affinity` is the type preference SQLite assigns to a column.
+//!
+//! See <https://www.sqlite.org/datatype3.html#determination_of_column_affinity> for details.
+
+/// Column affinity, derived from its declared type
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Affinity {
+    Integer,
+    Text,
+    Blob,
+    Real,
+    Numeric,
+}
+
+impl Affinity {
+    /// Computes the affinity of a column declared with type `decl_type`,
+    /// following the rules SQLite applies in `CREATE TABLE`.
+    ///
+    /// The rules are checked in order, so e.g. `CHARINT` has integer affinity.
+    /// An empty declared type (as for expressions and computed columns) has blob affinity.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsql_client::Affinity;
+    /// assert_eq!(Affinity::from_decl_type("VARCHAR(10)"), Affinity::Text);
+    /// assert_eq!(Affinity::from_decl_type("BIGINT"), Affinity::Integer);
+    /// ```
+    pub fn from_decl_type(decl_type: &str) -> Affinity {
+        let decl_type = decl_type.to_ascii_uppercase();
+        let contains_any = |patterns: &[&str]| patterns.iter().any(|p| decl_type.contains(p));
+        if decl_type.contains("INT") {
+            Affinity::Integer
+        } else if contains_any(&["CHAR", "CLOB", "TEXT"]) {
+            Affinity::Text
+        } else if decl_type.trim().is_empty() || decl_type.contains("BLOB") {
+            Affinity::Blob
+        } else if contains_any(&["REAL", "FLOA", "DOUB"]) {
+            Affinity::Real
+        } else {
+            Affinity::Numeric
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_affinity_from_decl_type() {
+        assert_eq!(Affinity::from_decl_type("VARCHAR(10)"), Affinity::Text);
+        assert_eq!(Affinity::from_decl_type("INT"), Affinity::Integer);
+        assert_eq!(
+            Affinity::from_decl_type("unsigned big int"),
+            Affinity::Integer
+        );
+        assert_eq!(Affinity::from_decl_type("DOUBLE"), Affinity::Real);
+        assert_eq!(Affinity::from_decl_type("FLOAT"), Affinity::Real);
+        assert_eq!(Affinity::from_decl_type("BLOB"), Affinity::Blob);
+        assert_eq!(Affinity::from_decl_type(""), Affinity::Blob);
+        assert_eq!(Affinity::from_decl_type("DECIMAL(10,5)"), Affinity::Numeric);
+        assert_eq!(Affinity::from_decl_type("BOOLEAN"), Affinity::Numeric);
+        // "INT" takes precedence, even as a part of another word
+        assert_eq!(Affinity::from_decl_type("POINT"), Affinity::Integer);
+    }
+}
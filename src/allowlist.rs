@@ -0,0 +1,203 @@
+//! `AllowlistClient` only runs statements matching preregistered templates, for restricted query APIs.
+
+use std::collections::HashSet;
+
+use anyhow::{Context, Result};
+use sqlite3_parser::dialect::TokenType;
+use sqlite3_parser::lexer::sql::Tokenizer;
+use sqlite3_parser::lexer::Scanner;
+
+use crate::{Client, ResultSet, Statement};
+
+/// A [Client] wrapper which only lets statements matching one of its templates through
+///
+/// Templates are compared token by token, so differences in whitespace, comments and the case
+/// of keywords and identifiers are ignored, while string and blob literals must match exactly.
+/// Parameters are how statements vary: `SELECT * FROM users WHERE id = ?` allows looking up
+/// any user, but not `SELECT * FROM users WHERE id = 1 OR 1 = 1`.
+///
+/// Statements which match no template fail with
+/// [`Error::StatementNotAllowed`](crate::Error::StatementNotAllowed) without being sent.
+/// This is a safety layer for semi-trusted callers, on top of server-side permissions.
+///
+/// # Examples
+///
+/// ```
+/// # async fn f() -> anyhow::Result<()> {
+/// use libsql_client::allowlist::AllowlistClient;
+/// use libsql_client::{args, Client, Statement};
+///
+/// let inner = Client::in_memory()?;
+/// inner.execute("CREATE TABLE users(id INTEGER, name TEXT)").await?;
+/// let db = AllowlistClient::new(inner, ["SELECT name FROM users WHERE id = ?"])?;
+/// db.execute(Statement::with_args("select name from users where id = ?", args!(1))).await?;
+/// assert!(db.execute("DELETE FROM users").await.is_err());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct AllowlistClient {
+    inner: Client,
+    templates: HashSet<String>,
+}
+
+impl AllowlistClient {
+    /// Wraps `inner`, rejecting statements which do not match any of `templates`
+    ///
+    /// Will return an error if one of the templates cannot be tokenized.
+    pub fn new(
+        inner: Client,
+        templates: impl IntoIterator<Item = impl AsRef<str>>,
+    ) -> Result<Self> {
+        let templates = templates
+            .into_iter()
+            .map(|template| {
+                let template = Statement::new(template.as_ref()).sql;
+                normalize(&template).with_context(|| format!("Invalid template `{template}`"))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { inner, templates })
+    }
+
+    /// Returns the wrapped client, which does not reject any statement
+    pub fn inner(&self) -> &Client {
+        &self.inner
+    }
+
+    /// Executes a single SQL statement if it matches one of the templates
+    ///
+    /// # Arguments
+    /// * `stmt` - SQL statement
+    pub async fn execute(&self, stmt: impl Into<Statement> + Send) -> Result<ResultSet> {
+        let stmt: Statement = stmt.into();
+        self.check(&stmt)?;
+        self.inner.execute(stmt).await
+    }
+
+    /// Executes a batch of SQL statements in a transaction, if all of them match templates.
+    /// Otherwise, none of them are sent.
+    ///
+    /// # Arguments
+    /// * `stmts` - SQL statements
+    pub async fn batch<I: IntoIterator<Item = impl Into<Statement> + Send> + Send>(
+        &self,
+        stmts: I,
+    ) -> Result<Vec<ResultSet>>
+    where
+        <I as IntoIterator>::IntoIter: Send,
+    {
+        let stmts: Vec<Statement> = stmts.into_iter().map(|s| s.into()).collect();
+        for stmt in &stmts {
+            self.check(stmt)?;
+        }
+        self.inner.batch(stmts).await
+    }
+
+    fn check(&self, stmt: &Statement) -> Result<()> {
+        match normalize(&stmt.sql) {
+            Ok(normalized) if self.templates.contains(&normalized) => Ok(()),
+            _ => Err(crate::Error::StatementNotAllowed {
+                sql: stmt.sql.clone(),
+            }
+            .into()),
+        }
+    }
+}
+
+/// Joins the tokens of `sql` with single spaces, lowercasing all but string and blob literals.
+/// Whitespace and comments are not tokens, so they are dropped.
+fn normalize(sql: &str) -> Result<String> {
+    let input = sql.as_bytes();
+    let mut scanner = Scanner::new(Tokenizer::new());
+    let mut tokens = Vec::new();
+    while let (start, Some((_, token_type)), end) = scanner.scan(input)? {
+        let token = std::str::from_utf8(&input[start..end])?;
+        tokens.push(match token_type {
+            TokenType::TK_STRING | TokenType::TK_BLOB => token.to_string(),
+            _ => token.to_lowercase(),
+        });
+    }
+    Ok(tokens.join(" "))
+}
+
+#[cfg(all(test, feature = "local_backend"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(
+            normalize("SELECT  name\n FROM Users -- comment\n WHERE id = ?1 AND role = 'Admin'")
+                .unwrap(),
+            "select name from users where id = ?1 and role = 'Admin'"
+        );
+        assert_ne!(
+            normalize("SELECT 1").unwrap(),
+            normalize("SELECT 1; SELECT 2").unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_allowlist() {
+        let inner = Client::in_memory().unwrap();
+        inner
+            .batch([
+                "CREATE TABLE users(id INTEGER, name TEXT)",
+                "INSERT INTO users VALUES (1, 'alice'), (2, 'bob')",
+            ])
+            .await
+            .unwrap();
+        let db = AllowlistClient::new(
+            inner,
+            [
+                "SELECT name FROM users WHERE id = :id",
+                "UPDATE users SET name = ? WHERE id = ?",
+            ],
+        )
+        .unwrap();
+
+        let rs = db
+            .execute(Statement::with_args(
+                "select name\n  from USERS\n  where id = :id;",
+                &[2],
+            ))
+            .await
+            .unwrap();
+        assert_eq!(rs.rows[0].get_str(0), Some("bob"));
+
+        for sql in [
+            "SELECT name FROM users",
+            "SELECT name FROM users WHERE id = 1 OR 1 = 1",
+            "SELECT name FROM users WHERE id = :id; DROP TABLE users",
+            "DROP TABLE users",
+        ] {
+            let err = db.execute(sql).await.unwrap_err();
+            assert_eq!(
+                err.downcast_ref::<crate::Error>(),
+                Some(&crate::Error::StatementNotAllowed {
+                    sql: Statement::new(sql).sql
+                }),
+                "{sql}"
+            );
+        }
+
+        let err = db
+            .batch([
+                Statement::with_args("UPDATE users SET name = ? WHERE id = ?", &["carol", "1"]),
+                Statement::new("DELETE FROM users"),
+            ])
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<crate::Error>(),
+            Some(crate::Error::StatementNotAllowed { .. })
+        ));
+        // Nothing was modified
+        let rs = db
+            .inner()
+            .execute("SELECT COUNT(*) FROM users")
+            .await
+            .unwrap();
+        assert_eq!(rs.rows[0].try_get::<i64>(0).unwrap(), 2);
+    }
+}
@@ -0,0 +1,129 @@
+//! `BulkLoader` inserts a stream of rows in chunks, see [`Client::bulk_loader()`].
+
+use anyhow::Result;
+
+use crate::{proto, Client, Statement};
+
+/// Inserts rows fed one by one, buffering them into multi-row `INSERT` statements
+///
+/// Each chunk is flushed as a single statement once it holds as many rows as fit
+/// below SQLite's limit on the number of parameters, so only one chunk is kept in memory.
+/// Chunks are not wrapped in a common transaction: if one fails, the chunks flushed before
+/// it stay inserted. Rows still buffered are only inserted by [`BulkLoader::finish()`],
+/// dropping the loader discards them.
+#[derive(Debug)]
+pub struct BulkLoader<'a> {
+    client: &'a Client,
+    sql_prefix: String,
+    placeholders: String,
+    columns: usize,
+    chunk_rows: usize,
+    buffer: Vec<proto::Value>,
+    inserted: u64,
+}
+
+impl<'a> BulkLoader<'a> {
+    pub(crate) fn new(client: &'a Client, table: &str, columns: &[&str]) -> Result<Self> {
+        if columns.is_empty() {
+            anyhow::bail!("Cannot bulk load rows without any columns into `{table}`");
+        }
+        let chunk_rows = (crate::client::MAX_PARAMS / columns.len()).max(1);
+        Ok(Self {
+            client,
+            sql_prefix: format!("INSERT INTO {table} ({}) VALUES ", columns.join(", ")),
+            placeholders: format!("({})", vec!["?"; columns.len()].join(", ")),
+            columns: columns.len(),
+            chunk_rows,
+            buffer: Vec::with_capacity(chunk_rows * columns.len()),
+            inserted: 0,
+        })
+    }
+
+    /// Buffers a row, flushing the buffer if it is full
+    ///
+    /// # Arguments
+    /// * `row` - values of the row, in the order of the columns of the loader
+    pub async fn feed(&mut self, row: Vec<proto::Value>) -> Result<()> {
+        if row.len() != self.columns {
+            anyhow::bail!(
+                "Expected a row of {} values, got {}",
+                self.columns,
+                row.len()
+            );
+        }
+        self.buffer.extend(row);
+        if self.buffer.len() / self.columns >= self.chunk_rows {
+            self.flush().await?;
+        }
+        Ok(())
+    }
+
+    /// Inserts the rows buffered so far, if any
+    pub async fn flush(&mut self) -> Result<()> {
+        let rows = self.buffer.len() / self.columns;
+        if rows == 0 {
+            return Ok(());
+        }
+        let sql = format!(
+            "{}{}",
+            self.sql_prefix,
+            vec![self.placeholders.as_str(); rows].join(", ")
+        );
+        let args = std::mem::take(&mut self.buffer);
+        let rs = self
+            .client
+            .execute(Statement::with_args(sql, &args))
+            .await?;
+        self.inserted += rs.rows_affected;
+        Ok(())
+    }
+
+    /// Inserts the remaining buffered rows, returning the number of rows inserted in total
+    pub async fn finish(mut self) -> Result<u64> {
+        self.flush().await?;
+        Ok(self.inserted)
+    }
+}
+
+#[cfg(all(test, feature = "local_backend"))]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_bulk_loader() {
+        let statements = Arc::new(AtomicUsize::new(0));
+        let counter = statements.clone();
+        let db = Client::in_memory()
+            .unwrap()
+            .with_query_logger(Arc::new(move |_| {
+                counter.fetch_add(1, Ordering::SeqCst);
+            }));
+        db.execute("CREATE TABLE t(id INTEGER, name TEXT)")
+            .await
+            .unwrap();
+        statements.store(0, Ordering::SeqCst);
+
+        let mut loader = db.bulk_loader("t", &["id", "name"]).unwrap();
+        for i in 0..3000 {
+            let row = vec![
+                proto::Value::from(i),
+                proto::Value::from(format!("row {i}")),
+            ];
+            loader.feed(row).await.unwrap();
+        }
+        // 999 parameters fit 499 rows of 2 columns, so 6 chunks were flushed while feeding
+        assert_eq!(statements.load(Ordering::SeqCst), 6);
+        assert_eq!(loader.finish().await.unwrap(), 3000);
+        assert_eq!(statements.load(Ordering::SeqCst), 7);
+
+        let rs = db.execute("SELECT count(*), sum(id) FROM t").await.unwrap();
+        assert_eq!(rs.rows[0].try_get::<i64>(0).unwrap(), 3000);
+        assert_eq!(rs.rows[0].try_get::<i64>(1).unwrap(), 2999 * 3000 / 2);
+
+        let mut loader = db.bulk_loader("t", &["id", "name"]).unwrap();
+        assert!(loader.feed(vec![proto::Value::from(1)]).await.is_err());
+        assert!(db.bulk_loader("t", &[]).is_err());
+    }
+}
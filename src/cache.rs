@@ -0,0 +1,236 @@
+//! `CachingClient` caches the results of reads, for read-mostly data like reference tables.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::Result;
+
+use crate::clock::{Clock, SystemClock};
+use crate::statement::{classify, StatementKind};
+use crate::{Client, ResultSet, Statement};
+
+/// A [Client] wrapper serving repeated identical reads from memory
+///
+/// Results of [read](StatementKind::Read) statements are cached for a fixed time to live,
+/// keyed by their SQL and parameters, including the types of the parameters.
+/// Expired results are dropped whenever a new one is cached. Other statements always go to the database and,
+/// unless disabled with [`CachingClient::with_invalidate_on_write()`], clear the whole cache,
+/// since their effects on cached results cannot be determined. Changes made through other
+/// clients, or through [`CachingClient::inner()`], are not noticed until cached results expire.
+///
+/// # Examples
+///
+/// ```
+/// # async fn f() -> anyhow::Result<()> {
+/// use libsql_client::cache::CachingClient;
+/// use std::time::Duration;
+///
+/// let db = CachingClient::new(libsql_client::Client::in_memory()?, Duration::from_secs(60));
+/// db.execute("SELECT 1").await?;
+/// // Served from the cache
+/// db.execute("SELECT 1").await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct CachingClient {
+    inner: Client,
+    ttl: Duration,
+    invalidate_on_write: bool,
+    clock: Arc<dyn Clock>,
+    entries: Mutex<Entries>,
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    results: HashMap<Statement, (Duration, ResultSet)>,
+    /// Incremented when the cache is cleared, so that results of reads which were
+    /// in flight at that time are not cached
+    generation: u64,
+}
+
+impl CachingClient {
+    /// Wraps `inner`, caching results of reads for `ttl`
+    pub fn new(inner: Client, ttl: Duration) -> Self {
+        Self {
+            inner,
+            ttl,
+            invalidate_on_write: true,
+            clock: Arc::new(SystemClock),
+            entries: Mutex::new(Entries::default()),
+        }
+    }
+
+    /// Sets whether statements other than reads clear the cache, which they do by default
+    pub fn with_invalidate_on_write(mut self, invalidate_on_write: bool) -> Self {
+        self.invalidate_on_write = invalidate_on_write;
+        self
+    }
+
+    /// Uses `clock` to expire cached results, instead of the real time
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Returns the wrapped client, which bypasses the cache
+    pub fn inner(&self) -> &Client {
+        &self.inner
+    }
+
+    /// Removes all cached results
+    pub fn clear(&self) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.results.clear();
+        entries.generation += 1;
+    }
+
+    /// Executes a single SQL statement, or returns its cached result if it is a read
+    /// executed less than the time to live ago
+    ///
+    /// # Arguments
+    /// * `stmt` - SQL statement
+    pub async fn execute(&self, stmt: impl Into<Statement> + Send) -> Result<ResultSet> {
+        let stmt: Statement = stmt.into();
+        if classify(&stmt.sql) != StatementKind::Read {
+            let result = self.inner.execute(stmt).await;
+            if self.invalidate_on_write {
+                self.clear();
+            }
+            return result;
+        }
+
+        let generation = match self.cached(&stmt) {
+            Ok(rs) => return Ok(rs),
+            Err(generation) => generation,
+        };
+        let rs = self.inner.execute(stmt.clone()).await?;
+        self.store(stmt, generation, &rs);
+        Ok(rs)
+    }
+
+    /// Returns the cached result of `stmt`, or the current generation of the cache if none
+    fn cached(&self, stmt: &Statement) -> std::result::Result<ResultSet, u64> {
+        let now = self.clock.now();
+        let entries = self.entries.lock().unwrap();
+        match entries.results.get(stmt) {
+            Some((cached_at, rs)) if now.saturating_sub(*cached_at) < self.ttl => Ok(rs.clone()),
+            _ => Err(entries.generation),
+        }
+    }
+
+    /// Caches the result of a read started at `generation`, unless the cache was cleared since
+    fn store(&self, stmt: Statement, generation: u64, rs: &ResultSet) {
+        let now = self.clock.now();
+        let mut entries = self.entries.lock().unwrap();
+        if entries.generation != generation {
+            return;
+        }
+        let ttl = self.ttl;
+        entries
+            .results
+            .retain(|_, (cached_at, _)| now.saturating_sub(*cached_at) < ttl);
+        entries.results.insert(stmt, (now, rs.clone()));
+    }
+}
+
+#[cfg(all(test, feature = "local_backend"))]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    fn counting_client(ttl: Duration) -> (CachingClient, Arc<AtomicUsize>) {
+        let sent = Arc::new(AtomicUsize::new(0));
+        let counter = sent.clone();
+        let inner = Client::in_memory()
+            .unwrap()
+            .with_query_logger(Arc::new(move |_| {
+                counter.fetch_add(1, Ordering::SeqCst);
+            }));
+        (CachingClient::new(inner, ttl), sent)
+    }
+
+    #[tokio::test]
+    async fn test_cached_reads() {
+        let (db, sent) = counting_client(Duration::from_secs(60));
+        db.execute("CREATE TABLE t(x)").await.unwrap();
+        db.execute("INSERT INTO t VALUES (1)").await.unwrap();
+        assert_eq!(sent.load(Ordering::SeqCst), 2);
+
+        let first = db.execute("SELECT x FROM t").await.unwrap();
+        let second = db.execute("SELECT x FROM t").await.unwrap();
+        assert_eq!(sent.load(Ordering::SeqCst), 3);
+        assert_eq!(first.rows.len(), second.rows.len());
+
+        // Parameters are part of the key
+        db.execute(Statement::with_args("SELECT x FROM t WHERE x = ?", &[1]))
+            .await
+            .unwrap();
+        db.execute(Statement::with_args("SELECT x FROM t WHERE x = ?", &[2]))
+            .await
+            .unwrap();
+        assert_eq!(sent.load(Ordering::SeqCst), 5);
+
+        // So are their types
+        let rs = db
+            .execute(Statement::with_args("SELECT typeof(?)", &[1]))
+            .await
+            .unwrap();
+        assert_eq!(rs.rows[0].try_get::<&str>(0).unwrap(), "integer");
+        let rs = db
+            .execute(Statement::with_args("SELECT typeof(?)", &["1"]))
+            .await
+            .unwrap();
+        assert_eq!(rs.rows[0].try_get::<&str>(0).unwrap(), "text");
+    }
+
+    #[tokio::test]
+    async fn test_write_invalidates() {
+        let (db, sent) = counting_client(Duration::from_secs(60));
+        db.execute("CREATE TABLE t(x)").await.unwrap();
+        assert_eq!(db.execute("SELECT x FROM t").await.unwrap().rows.len(), 0);
+        db.execute("INSERT INTO t VALUES (1)").await.unwrap();
+        assert_eq!(db.execute("SELECT x FROM t").await.unwrap().rows.len(), 1);
+        assert_eq!(sent.load(Ordering::SeqCst), 4);
+
+        let db = db.with_invalidate_on_write(false);
+        db.execute("INSERT INTO t VALUES (2)").await.unwrap();
+        assert_eq!(db.execute("SELECT x FROM t").await.unwrap().rows.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_expiry() {
+        let clock = Arc::new(crate::clock::ManualClock::new());
+        let (db, sent) = counting_client(Duration::from_secs(20));
+        let db = db.with_clock(clock.clone());
+        db.execute("SELECT 1").await.unwrap();
+        clock.advance(Duration::from_secs(19));
+        db.execute("SELECT 1").await.unwrap();
+        assert_eq!(sent.load(Ordering::SeqCst), 1);
+        clock.advance(Duration::from_secs(1));
+        db.execute("SELECT 1").await.unwrap();
+        assert_eq!(sent.load(Ordering::SeqCst), 2);
+
+        // Expired results are dropped when caching new ones
+        db.execute("SELECT 2").await.unwrap();
+        clock.advance(Duration::from_secs(30));
+        db.execute("SELECT 3").await.unwrap();
+        let entries = db.entries.lock().unwrap();
+        assert_eq!(entries.results.len(), 1);
+        assert!(entries.results.contains_key(&Statement::new("SELECT 3")));
+    }
+
+    #[tokio::test]
+    async fn test_clear_during_read() {
+        let (db, _) = counting_client(Duration::from_secs(60));
+        let stmt = Statement::new("SELECT 1");
+        let generation = db.cached(&stmt).unwrap_err();
+        let rs = db.inner().execute(stmt.clone()).await.unwrap();
+        // A write clears the cache while the read is in flight
+        db.clear();
+        db.store(stmt.clone(), generation, &rs);
+        assert!(db.cached(&stmt).is_err());
+    }
+}
@@ -0,0 +1,149 @@
+//! `CircuitBreaker` stops sending requests to a server which keeps failing.
+//!
+//! After `failure_threshold` consecutive failures within `window`, the breaker opens
+//! and requests fail immediately with [`Error::CircuitOpen`] for `cooldown`.
+//! Once the cooldown passes, a single trial request is let through: if it succeeds
+//! the breaker closes again, otherwise it reopens for another cooldown.
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::Result;
+
+use crate::clock::{Clock, SystemClock};
+use crate::Error;
+
+/// Configuration of a [CircuitBreaker]
+#[derive(Clone, Debug)]
+pub struct CircuitBreakerConfig {
+    /// How many consecutive failures open the breaker
+    pub failure_threshold: usize,
+    /// Failures further apart than this are not counted as consecutive
+    pub window: Duration,
+    /// How long the breaker stays open before letting a trial request through
+    pub cooldown: Duration,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            window: Duration::from_secs(30),
+            cooldown: Duration::from_secs(10),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum State {
+    Closed {
+        failures: usize,
+        first_failure: Option<Duration>,
+    },
+    Open {
+        until: Duration,
+    },
+    HalfOpen {
+        trial_started: Duration,
+    },
+}
+
+/// Circuit breaker state shared by all clones of a client
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    config: CircuitBreakerConfig,
+    state: Mutex<State>,
+    clock: Arc<dyn Clock>,
+}
+
+impl CircuitBreaker {
+    pub fn new(config: CircuitBreakerConfig) -> Self {
+        Self::with_clock(config, Arc::new(SystemClock))
+    }
+
+    /// Creates a breaker measuring windows and cooldowns with `clock`
+    pub fn with_clock(config: CircuitBreakerConfig, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            config,
+            state: Mutex::new(State::Closed {
+                failures: 0,
+                first_failure: None,
+            }),
+            clock,
+        }
+    }
+
+    pub(crate) fn config(&self) -> &CircuitBreakerConfig {
+        &self.config
+    }
+
+    /// Checks whether a request may be sent now, failing with [`Error::CircuitOpen`] if not
+    pub fn acquire(&self) -> Result<()> {
+        let now = self.clock.now();
+        let mut state = self.state.lock().unwrap();
+        match *state {
+            State::Closed { .. } => Ok(()),
+            State::Open { until } if now >= until => {
+                tracing::debug!("Circuit breaker half-open, sending a trial request");
+                *state = State::HalfOpen { trial_started: now };
+                Ok(())
+            }
+            State::Open { until } => Err(Error::CircuitOpen {
+                retry_after: until.saturating_sub(now),
+            }
+            .into()),
+            // A trial that takes longer than the cooldown was most likely cancelled,
+            // so let another one through
+            State::HalfOpen { trial_started }
+                if now.saturating_sub(trial_started) >= self.config.cooldown =>
+            {
+                *state = State::HalfOpen { trial_started: now };
+                Ok(())
+            }
+            State::HalfOpen { trial_started } => Err(Error::CircuitOpen {
+                retry_after: self.config.cooldown - now.saturating_sub(trial_started),
+            }
+            .into()),
+        }
+    }
+
+    /// Records the outcome of a request let through by [`CircuitBreaker::acquire()`]
+    pub fn record(&self, success: bool) {
+        let now = self.clock.now();
+        let mut state = self.state.lock().unwrap();
+        *state = match (*state, success) {
+            (_, true) => State::Closed {
+                failures: 0,
+                first_failure: None,
+            },
+            (State::HalfOpen { .. }, false) | (State::Open { .. }, false) => State::Open {
+                until: now + self.config.cooldown,
+            },
+            (
+                State::Closed {
+                    failures,
+                    first_failure,
+                },
+                false,
+            ) => {
+                let (failures, first_failure) = match first_failure {
+                    Some(first) if now.saturating_sub(first) <= self.config.window => {
+                        (failures + 1, first)
+                    }
+                    _ => (1, now),
+                };
+                if failures >= self.config.failure_threshold {
+                    tracing::warn!("Circuit breaker open after {failures} consecutive failures");
+                    State::Open {
+                        until: now + self.config.cooldown,
+                    }
+                } else {
+                    State::Closed {
+                        failures,
+                        first_failure: Some(first_failure),
+                    }
+                }
+            }
+        };
+    }
+}
@@ -1,21 +1,81 @@
 //! [Client] is the main structure to interact with the database.
 use anyhow::Result;
 
-use crate::{proto, BatchResult, ResultSet, Statement, SyncTransaction, Transaction};
+use fallible_iterator::FallibleIterator;
+use futures::Stream;
+use sqlite3_parser::ast::{Cmd, Stmt};
+use sqlite3_parser::lexer::sql::Parser;
+use std::sync::Arc;
+use tracing::Instrument;
+
+use crate::statement::label_error;
+use crate::{
+    proto, BatchResult, ResultSet, Row, Statement, SyncTransaction, Transaction, TxMode, ValueType,
+};
+
+#[cfg(feature = "local_backend")]
+impl From<crate::local::Client> for Client {
+    fn from(client: crate::local::Client) -> Self {
+        Client::Local(client)
+    }
+}
+
+#[cfg(any(
+    feature = "reqwest_backend",
+    feature = "workers_backend",
+    feature = "spin_backend",
+    feature = "tower"
+))]
+impl From<crate::http::Client> for Client {
+    fn from(client: crate::http::Client) -> Self {
+        Client::Http(client)
+    }
+}
+
+#[cfg(feature = "hrana_backend")]
+impl From<crate::hrana::Client> for Client {
+    fn from(client: crate::hrana::Client) -> Self {
+        Client::Hrana(client)
+    }
+}
 
 static TRANSACTION_IDS: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(1);
 
 /// A generic client struct, wrapping possible backends.
 /// It's a convenience struct which allows implementing connect()
 /// with backends being passed as env parameters.
+///
+/// Backends are dispatched statically, so `Client` also serves to pick a backend
+/// at runtime without boxing: each backend's client converts into it with `into()`.
+///
+/// Methods configuring the HTTP transport, like `with_request_id()`, only apply to remote
+/// HTTP clients, and leave clients of other backends unchanged.
+///
+/// ```
+/// # fn f(remote_url: Option<&str>) -> anyhow::Result<()> {
+/// use libsql_client::{http, reqwest, Client};
+///
+/// let db: Client = match remote_url {
+///     Some(url) => {
+///         let inner = http::InnerClient::Reqwest(reqwest::HttpClient::new());
+///         http::Client::new(inner, url, "token").into()
+///     }
+///     None => libsql_client::local::Client::in_memory()?.into(),
+/// };
+/// # Ok(())
+/// # }
+/// ```
 #[derive(Debug)]
+// Backend clients are stored inline, a client is rarely moved around
+#[allow(clippy::large_enum_variant)]
 pub enum Client {
     #[cfg(feature = "local_backend")]
     Local(crate::local::Client),
     #[cfg(any(
         feature = "reqwest_backend",
         feature = "workers_backend",
-        feature = "spin_backend"
+        feature = "spin_backend",
+        feature = "tower"
     ))]
     Http(crate::http::Client),
     #[cfg(feature = "hrana_backend")]
@@ -23,6 +83,164 @@ pub enum Client {
     Default,
 }
 
+/// Callback receiving each statement just before it is sent, see [`Client::with_query_logger()`]
+pub type QueryLogger = Arc<dyn Fn(&Statement) + Send + Sync>;
+
+/// Callback receiving each statement after it completes, see [`Client::with_sql_trace_hook()`]
+pub type SqlTraceHook = Arc<dyn Fn(TraceEvent) + Send + Sync>;
+
+/// A statement which completed, as passed to a [SqlTraceHook]
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct TraceEvent {
+    /// SQL of the statement
+    pub sql: String,
+    /// Parameters bound to the statement
+    pub params: Vec<proto::Value>,
+    /// Time between sending the statement and receiving its result, including network latency.
+    /// Always zero on `wasm32-unknown-unknown`, where time cannot be measured.
+    pub duration: std::time::Duration,
+    /// Summary of the result, or the error message if the statement failed
+    pub result: std::result::Result<TraceSummary, String>,
+}
+
+/// Summary of the result of a traced statement, see [TraceEvent]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct TraceSummary {
+    /// Number of rows returned
+    pub rows: usize,
+    /// Number of rows changed
+    pub rows_affected: u64,
+}
+
+/// Maximum number of parameters of a statement, SQLite's default limit before 3.32.0
+pub(crate) const MAX_PARAMS: usize = 999;
+
+/// Returns the type of the first non-NULL value of each column of `rs`, see [`Client::batch_merge()`]
+fn column_types(rs: &ResultSet) -> Vec<Option<ValueType>> {
+    (0..rs.columns.len())
+        .map(|c| {
+            rs.rows
+                .iter()
+                .filter_map(|row| row.values.get(c))
+                .map(ValueType::of)
+                .find(|t| *t != ValueType::Null)
+        })
+        .collect()
+}
+
+/// Statement setting the busy timeout, see [`Client::with_busy_timeout()`]
+#[cfg(any(
+    feature = "local_backend",
+    feature = "reqwest_backend",
+    feature = "workers_backend",
+    feature = "spin_backend",
+    feature = "tower",
+    feature = "hrana_backend"
+))]
+pub(crate) fn busy_timeout_pragma(ms: u64) -> Statement {
+    Statement::new(format!("PRAGMA busy_timeout = {ms}"))
+}
+
+/// Settings applying to a [Client] regardless of its backend.
+/// Each backend stores them, since [Client] itself is just a dispatcher.
+#[derive(Clone, Default)]
+pub(crate) struct Options {
+    query_logger: Option<QueryLogger>,
+    redact_params: bool,
+    trace_hook: Option<SqlTraceHook>,
+    name: Option<String>,
+    default_params: Vec<(String, proto::Value)>,
+}
+
+/// A statement being traced, see [`Options::trace()`]
+struct Trace {
+    hook: SqlTraceHook,
+    sql: String,
+    params: Vec<proto::Value>,
+    started: Option<std::time::Instant>,
+}
+
+impl Trace {
+    fn finish(self, result: &Result<ResultSet>) {
+        let result = match result {
+            Ok(rs) => Ok(TraceSummary {
+                rows: rs.rows.len(),
+                rows_affected: rs.rows_affected,
+            }),
+            Err(e) => Err(format!("{e:#}")),
+        };
+        (self.hook)(TraceEvent {
+            sql: self.sql,
+            params: self.params,
+            duration: self.started.map(|t| t.elapsed()).unwrap_or_default(),
+            result,
+        })
+    }
+}
+
+impl Options {
+    /// Attaches the name of the client to `error`, if it has one
+    fn name_error(&self, error: anyhow::Error) -> anyhow::Error {
+        match &self.name {
+            Some(name) => error.context(format!("Query on client `{name}` failed")),
+            None => error,
+        }
+    }
+
+    /// Binds the default parameters to the named parameters of `stmt` left unbound,
+    /// see [`Client::with_default_params()`]
+    fn bind_defaults(&self, mut stmt: Statement) -> Statement {
+        if self.default_params.is_empty() {
+            return stmt;
+        }
+        // Invalid SQL is left for the database to report
+        let Ok(named) = crate::statement::named_parameters(&stmt.sql) else {
+            return stmt;
+        };
+        for (name, index) in named {
+            if index <= stmt.args.len() {
+                continue;
+            }
+            // Parameters are bound by position, so a gap cannot be skipped over
+            let default = self.default_params.iter().find(|(n, _)| *n == name);
+            match default {
+                Some((_, value)) if index == stmt.args.len() + 1 => stmt.args.push(value.clone()),
+                _ => break,
+            }
+        }
+        stmt
+    }
+
+    fn log(&self, stmt: &Statement) {
+        let Some(logger) = &self.query_logger else {
+            return;
+        };
+        if self.redact_params {
+            logger(&Statement {
+                sql: stmt.sql.clone(),
+                args: vec![],
+                label: stmt.label.clone(),
+            })
+        } else {
+            logger(stmt)
+        }
+    }
+
+    /// Starts tracing `stmt`, if a trace hook is registered
+    fn trace(&self, stmt: &Statement) -> Option<Trace> {
+        let hook = self.trace_hook.clone()?;
+        Some(Trace {
+            hook,
+            sql: stmt.sql.clone(),
+            params: stmt.args.clone(),
+            // `Instant` is not available on wasm32-unknown-unknown
+            started: (!cfg!(target_arch = "wasm32")).then(std::time::Instant::now),
+        })
+    }
+}
+
 /// A synchronous flavor of [Client]. All its public methods are synchronous,
 /// to make it usable in environments that don't support async/await.
 pub struct SyncClient {
@@ -51,28 +269,82 @@ impl Client {
     /// ]).await.unwrap();
     /// # }
     /// ```
+    #[cfg_attr(
+        not(any(
+            feature = "local_backend",
+            feature = "reqwest_backend",
+            feature = "workers_backend",
+            feature = "spin_backend",
+            feature = "tower",
+            feature = "hrana_backend"
+        )),
+        allow(unused_variables, unreachable_code)
+    )]
     pub async fn raw_batch(
         &self,
         stmts: impl IntoIterator<Item = impl Into<Statement> + Send> + Send,
     ) -> Result<BatchResult> {
-        match self {
+        let options = self.options();
+        let stmts = stmts.into_iter().map(|stmt| {
+            let stmt = options.bind_defaults(stmt.into());
+            options.log(&stmt);
+            stmt
+        });
+        let result: Result<BatchResult> = match self {
             #[cfg(feature = "local_backend")]
             Self::Local(l) => l.raw_batch(stmts),
             #[cfg(any(
                 feature = "reqwest_backend",
                 feature = "workers_backend",
-                feature = "spin_backend"
+                feature = "spin_backend",
+                feature = "tower"
             ))]
             Self::Http(r) => r.raw_batch(stmts).await,
             #[cfg(feature = "hrana_backend")]
             Self::Hrana(h) => h.raw_batch(stmts).await,
             _ => panic!("Must enable at least one feature"),
-        }
+        };
+        result.map_err(|e| options.name_error(e))
+    }
+
+    /// Executes a batch of SQL statements atomically like [`Client::batch_transaction()`],
+    /// only checking whether all of them succeeded.
+    ///
+    /// Returns the error of the statement which failed, as an [`Error::Sql`](crate::Error::Sql)
+    /// with the label of the statement attached as context, if any. The whole batch
+    /// is then rolled back, leaving none of its changes committed.
+    ///
+    /// # Arguments
+    /// * `stmts` - SQL statements
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn run() {
+    /// let db = libsql_client::Client::in_memory().unwrap();
+    /// db.batch_ok(["create table foo(bar text)", "insert into foo(bar) values ('bar')"])
+    ///     .await
+    ///     .unwrap();
+    /// # }
+    /// ```
+    pub async fn batch_ok(
+        &self,
+        stmts: impl IntoIterator<Item = impl Into<Statement> + Send> + Send,
+    ) -> Result<()> {
+        self.batch_transaction(stmts).await.map(|_| ())
     }
 
     /// Transactionally executes a batch of SQL statements.
     ///
     /// For a version in which statements can fail or succeed independently, see [`Client::raw_batch()`]
+    ///
+    /// # Cancellation
+    ///
+    /// Dropping the returned future, e.g. when it loses the race in a `select!`, cancels the
+    /// request: remote backends close its connection instead of waiting for the response.
+    /// The server may still have received the batch, and whether it committed it is unknown.
+    /// With the local backend, the batch runs to completion when the future is first polled.
+    ///
     /// # Arguments
     /// * `stmts` - SQL statements
     ///
@@ -97,21 +369,25 @@ impl Client {
     where
         <I as IntoIterator>::IntoIter: Send,
     {
+        let stmts: Vec<Statement> = stmts.into_iter().map(|s| s.into()).collect();
+        let labels: Vec<Option<String>> = stmts.iter().map(|s| s.label.clone()).collect();
         let batch_results = self
             .raw_batch(
                 std::iter::once(Statement::new("BEGIN"))
-                    .chain(stmts.into_iter().map(|s| s.into()))
+                    .chain(stmts)
                     .chain(std::iter::once(Statement::new("END"))),
             )
             .await?;
-        let step_error: Option<proto::Error> = batch_results
+        let step_error: Option<(usize, proto::Error)> = batch_results
             .step_errors
             .into_iter()
             .skip(1)
-            .find(|e| e.is_some())
-            .flatten();
-        if let Some(error) = step_error {
-            return Err(anyhow::anyhow!(error.message));
+            .enumerate()
+            .find_map(|(i, e)| e.map(|e| (i, e)));
+        if let Some((i, error)) = step_error {
+            let label = labels.get(i).and_then(Option::as_deref);
+            let error = label_error(anyhow::anyhow!(error.message), label);
+            return Err(self.options().name_error(error));
         }
         let mut step_results: Vec<Result<ResultSet>> = batch_results
             .step_results
@@ -128,383 +404,697 @@ impl Client {
         step_results.into_iter().collect::<Result<Vec<ResultSet>>>()
     }
 
-    /// Transactionally executes a batch of SQL statements, in synchronous contexts.
+    /// Checks the health of the server without running a query.
     ///
-    /// This method calls [block_on](`futures::executor::block_on()`) internally.
+    /// Only remote HTTP clients support this, other backends return an error.
+    /// See [`http::Client::server_health()`](crate::http::Client::server_health) for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn run(db: libsql_client::Client) -> anyhow::Result<()> {
+    /// let health = db.server_health().await?;
+    /// if !health.ok {
+    ///     println!("server is down, status: {:?}", health.status);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(any(
+        feature = "reqwest_backend",
+        feature = "workers_backend",
+        feature = "spin_backend",
+        feature = "tower"
+    ))]
+    pub async fn server_health(&self) -> Result<crate::http::HealthStatus> {
+        match self {
+            Self::Http(h) => h.server_health().await,
+            _ => anyhow::bail!("Health checks are only supported by remote HTTP clients"),
+        }
+    }
+
+    /// Transactionally executes a batch of SQL statements like [`Client::batch()`],
+    /// authenticating with `auth` instead of the credentials of this client.
+    ///
+    /// Useful for gateways issuing queries on behalf of their users, e.g. with a token of the
+    /// end user, without building a client per request. The credentials only apply to this call,
+    /// and the connection pool of this client is reused.
+    ///
+    /// Only remote HTTP clients support this, other backends return an error.
     ///
-    /// For the async version of this method, see [`Client::batch()`]
     /// # Arguments
     /// * `stmts` - SQL statements
+    /// * `auth` - credentials for this call only
     ///
     /// # Examples
     ///
     /// ```
-    /// # async fn run() {
-    /// # use libsql_client::Config;
-    /// # std::env::set_var("LIBSQL_CLIENT_URL", "file:////tmp/example.db");
-    /// let db = libsql_client::Client::in_memory().unwrap();
-    /// # db.execute("create table foo(bar text)").await.unwrap();
-    /// let res = db.batch_sync([
-    ///   "select * from foo",
-    ///   "insert into foo(bar) values ('bar')"
-    /// ]).unwrap();
-    /// assert_eq!(res.len(), 2)
+    /// # async fn run(db: libsql_client::Client, user_token: String) -> anyhow::Result<()> {
+    /// use libsql_client::http::Auth;
+    ///
+    /// let res = db.batch_with_auth(["select 1"], Auth::Token(user_token)).await?;
+    /// # Ok(())
     /// # }
     /// ```
-    pub fn batch_sync<I: IntoIterator<Item = impl Into<Statement> + Send> + Send>(
+    #[cfg(any(
+        feature = "reqwest_backend",
+        feature = "workers_backend",
+        feature = "spin_backend",
+        feature = "tower"
+    ))]
+    pub async fn batch_with_auth<I: IntoIterator<Item = impl Into<Statement> + Send> + Send>(
         &self,
         stmts: I,
+        auth: crate::http::Auth,
     ) -> Result<Vec<ResultSet>>
     where
-        <I as std::iter::IntoIterator>::IntoIter: std::marker::Send,
+        <I as IntoIterator>::IntoIter: Send,
     {
-        futures::executor::block_on(self.batch(stmts))
+        match self {
+            Self::Http(h) => {
+                Client::Http(h.clone_with_auth(auth.header()))
+                    .batch(stmts)
+                    .await
+            }
+            _ => anyhow::bail!("Per-call credentials are only supported by remote HTTP clients"),
+        }
     }
 
-    /// Executes a single SQL statement
+    /// Transactionally executes a batch of SQL statements like [`Client::batch()`],
+    /// attaching `extensions` to the HTTP requests it sends.
+    ///
+    /// Extensions are never sent to the server. With the tower backend, they are inserted
+    /// into the extensions of each `http::Request`, so that middleware can correlate
+    /// requests with application context, e.g. to tag logs or metrics with a tenant.
+    /// Other backends, including local ones, ignore them.
     ///
     /// # Arguments
-    /// * `stmt` - SQL statements
+    /// * `stmts` - SQL statements
+    /// * `extensions` - values for middleware, for this call only
     ///
     /// # Examples
     ///
     /// ```
-    /// # async fn run() {
-    /// # use libsql_client::Config;
-    /// let db = libsql_client::Client::in_memory().unwrap();
-    /// # db.execute("create table foo(bar text)").await.unwrap();
-    /// db.execute("select * from foo").await.unwrap();
+    /// # async fn run(db: libsql_client::Client) -> anyhow::Result<()> {
+    /// use libsql_client::http::Extensions;
+    ///
+    /// struct TenantId(u64);
+    ///
+    /// let mut ext = Extensions::new();
+    /// ext.insert(TenantId(7));
+    /// let res = db.batch_with_extensions(["select 1"], ext).await?;
+    /// # Ok(())
     /// # }
     /// ```
-    pub async fn execute(&self, stmt: impl Into<Statement> + Send) -> Result<ResultSet> {
+    #[cfg(any(
+        feature = "reqwest_backend",
+        feature = "workers_backend",
+        feature = "spin_backend",
+        feature = "tower"
+    ))]
+    pub async fn batch_with_extensions<I: IntoIterator<Item = impl Into<Statement> + Send> + Send>(
+        &self,
+        stmts: I,
+        extensions: crate::http::Extensions,
+    ) -> Result<Vec<ResultSet>>
+    where
+        <I as IntoIterator>::IntoIter: Send,
+    {
         match self {
-            #[cfg(feature = "local_backend")]
-            Self::Local(l) => l.execute(stmt),
-            #[cfg(any(
-                feature = "reqwest_backend",
-                feature = "workers_backend",
-                feature = "spin_backend"
-            ))]
-            Self::Http(r) => r.execute(stmt).await,
-            #[cfg(feature = "hrana_backend")]
-            Self::Hrana(h) => h.execute(stmt).await,
-            _ => panic!("Must enable at least one feature"),
+            Self::Http(h) => {
+                Client::Http(h.clone_with_extensions(extensions))
+                    .batch(stmts)
+                    .await
+            }
+            _ => self.batch(stmts).await,
         }
     }
 
-    /// Creates an interactive transaction
+    /// Transactionally executes a batch of SQL statements, failing with
+    /// [`Error::Timeout`](crate::Error::Timeout) if it does not complete before `deadline`.
+    ///
+    /// Useful for propagating a time budget, e.g. one of an incoming request.
+    /// The deadline is checked before sending anything, and for remote HTTP clients
+    /// the remaining time is also applied as the timeout of the request itself.
+    /// Other backends do not support timeouts, so once started, their batches run to completion.
+    ///
+    /// # Arguments
+    /// * `stmts` - SQL statements
+    /// * `deadline` - point in time by which the batch must complete
     ///
     /// # Examples
     ///
     /// ```
     /// # async fn run() {
-    /// # use libsql_client::Config;
+    /// # use std::time::{Duration, Instant};
     /// let db = libsql_client::Client::in_memory().unwrap();
-    /// # db.execute("create table foo(bar text)").await.unwrap();
-    /// let tx = db.transaction().await.unwrap();
-    /// tx.execute("select * from foo").await.unwrap();
-    /// tx.commit();
+    /// let deadline = Instant::now() + Duration::from_secs(1);
+    /// let res = db.batch_until(["select 1"], deadline).await.unwrap();
     /// # }
     /// ```
-    pub async fn transaction(&self) -> Result<Transaction> {
-        let id = TRANSACTION_IDS.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
-        Transaction::new(self, id).await
-    }
-
-    pub(crate) async fn execute_in_transaction(
+    pub async fn batch_until<I: IntoIterator<Item = impl Into<Statement> + Send> + Send>(
         &self,
-        tx_id: u64,
-        stmt: Statement,
-    ) -> Result<ResultSet> {
-        match self {
-            #[cfg(feature = "local_backend")]
-            Self::Local(l) => l.execute_in_transaction(tx_id, stmt),
-            #[cfg(any(
-                feature = "reqwest_backend",
-                feature = "workers_backend",
-                feature = "spin_backend"
-            ))]
-            Self::Http(r) => r.execute_in_transaction(tx_id, stmt).await,
-            #[cfg(feature = "hrana_backend")]
-            Self::Hrana(h) => h.execute_in_transaction(tx_id, stmt).await,
-
-            _ => panic!("Must enable at least one feature"),
-        }
-    }
-
-    pub(crate) async fn commit_transaction(&self, tx_id: u64) -> Result<()> {
-        match self {
-            #[cfg(feature = "local_backend")]
-            Self::Local(l) => l.commit_transaction(tx_id),
-            #[cfg(any(
-                feature = "reqwest_backend",
-                feature = "workers_backend",
-                feature = "spin_backend"
-            ))]
-            Self::Http(r) => r.commit_transaction(tx_id).await,
-            #[cfg(feature = "hrana_backend")]
-            Self::Hrana(h) => h.commit_transaction(tx_id).await,
-
-            _ => panic!("Must enable at least one feature"),
-        }
-    }
-
-    pub(crate) async fn rollback_transaction(&self, tx_id: u64) -> Result<()> {
+        stmts: I,
+        deadline: std::time::Instant,
+    ) -> Result<Vec<ResultSet>>
+    where
+        <I as IntoIterator>::IntoIter: Send,
+    {
+        let remaining = deadline
+            .checked_duration_since(std::time::Instant::now())
+            .filter(|remaining| !remaining.is_zero())
+            .ok_or(crate::Error::Timeout)?;
         match self {
-            #[cfg(feature = "local_backend")]
-            Self::Local(l) => l.rollback_transaction(tx_id),
             #[cfg(any(
                 feature = "reqwest_backend",
                 feature = "workers_backend",
-                feature = "spin_backend"
+                feature = "spin_backend",
+                feature = "tower"
             ))]
-            Self::Http(r) => r.rollback_transaction(tx_id).await,
-            #[cfg(feature = "hrana_backend")]
-            Self::Hrana(h) => h.rollback_transaction(tx_id).await,
-
-            _ => panic!("Must enable at least one feature"),
+            Self::Http(h) => {
+                Client::Http(h.clone().with_timeout(remaining))
+                    .batch(stmts)
+                    .await
+            }
+            _ => {
+                // Other backends cannot time out requests, the deadline was only checked upfront
+                let _ = remaining;
+                self.batch(stmts).await
+            }
         }
     }
-}
 
-impl Client {
-    /// Creates an in-memory database
+    /// Executes a batch of SQL statements atomically, in a single transaction.
+    ///
+    /// Unlike [`Client::batch()`], a failing statement rolls back the whole batch,
+    /// leaving none of its changes committed, and its error is returned.
+    /// Statements controlling transactions (`BEGIN`, `COMMIT`, `ROLLBACK`, savepoints)
+    /// are rejected upfront, since they would interfere with the enclosing transaction.
+    ///
+    /// Remote HTTP clients send the whole transaction in a single request, in which each
+    /// statement only runs if the previous ones succeeded. Other backends execute the
+    /// statements one by one in an interactive transaction.
+    ///
+    /// # Arguments
+    /// * `stmts` - SQL statements
     ///
     /// # Examples
     ///
     /// ```
-    /// # async fn f() {
-    /// # use libsql_client::Config;
+    /// # async fn run() {
     /// let db = libsql_client::Client::in_memory().unwrap();
+    /// # db.execute("create table foo(bar text)").await.unwrap();
+    /// let res = db.batch_transaction([
+    ///   "insert into foo(bar) values ('bar')",
+    ///   "select * from foo",
+    /// ]).await.unwrap();
+    /// assert_eq!(res.len(), 2)
     /// # }
     /// ```
-    #[cfg(feature = "local_backend")]
-    pub fn in_memory() -> anyhow::Result<Client> {
-        Ok(Client::Local(crate::local::Client::in_memory()?))
+    pub async fn batch_transaction(
+        &self,
+        stmts: impl IntoIterator<Item = impl Into<Statement>>,
+    ) -> Result<Vec<ResultSet>> {
+        self.batch_transaction_with(stmts, TxMode::Deferred).await
     }
 
-    /// Establishes a database client based on [Config] struct
+    /// Executes a batch of SQL statements atomically like [`Client::batch_transaction()`],
+    /// in a transaction started in the given mode.
+    ///
+    /// Batches which write should use [`TxMode::Immediate`]: the write lock is then taken
+    /// upfront, instead of being upgraded from a read lock midway, which fails with
+    /// `SQLITE_BUSY` if another connection wrote in the meantime. See [`TxMode`] for details.
+    ///
+    /// # Arguments
+    /// * `stmts` - SQL statements
+    /// * `mode` - locking behavior of the transaction
     ///
     /// # Examples
     ///
     /// ```
-    /// # async fn f() {
-    /// # use libsql_client::Config;
-    /// let config = Config {
-    ///   url: url::Url::parse("file:////tmp/example.db").unwrap(),
-    ///   auth_token: None
-    /// };
-    /// let db = libsql_client::Client::from_config(config).await.unwrap();
+    /// # async fn run() {
+    /// use libsql_client::TxMode;
+    ///
+    /// let db = libsql_client::Client::in_memory().unwrap();
+    /// # db.execute("create table foo(bar text)").await.unwrap();
+    /// let res = db.batch_transaction_with([
+    ///   "select count(*) from foo",
+    ///   "insert into foo(bar) values ('bar')",
+    /// ], TxMode::Immediate).await.unwrap();
+    /// assert_eq!(res.len(), 2)
     /// # }
     /// ```
-    #[allow(unreachable_patterns)]
-    pub async fn from_config<'a>(mut config: Config) -> anyhow::Result<Client> {
-        config.url = if config.url.scheme() == "libsql" {
-            // We cannot use url::Url::set_scheme() because it prevents changing the scheme to http...
-            // Safe to unwrap, because we know that the scheme is libsql
-            url::Url::parse(&config.url.as_str().replace("libsql://", "https://")).unwrap()
-        } else {
-            config.url
-        };
-        let scheme = config.url.scheme();
-        Ok(match scheme {
-            #[cfg(feature = "local_backend")]
-            "file" => {
-                Client::Local(crate::local::Client::new(config.url.to_string())?)
-            },
-            #[cfg(feature = "hrana_backend")]
-            "ws" | "wss" => {
-                Client::Hrana(crate::hrana::Client::from_config(config).await?)
-            },
-            #[cfg(feature = "reqwest_backend")]
-            "http" | "https" => {
-                let inner = crate::http::InnerClient::Reqwest(crate::reqwest::HttpClient::new());
-                Client::Http(crate::http::Client::from_config(inner, config)?)
-            },
-            #[cfg(feature = "workers_backend")]
-            "workers" | "http" | "https" => {
-                let inner = crate::http::InnerClient::Workers(crate::workers::HttpClient::new());
-                Client::Http(crate::http::Client::from_config(inner, config)?)
-            },
-            #[cfg(feature = "spin_backend")]
-            "spin" | "http" | "https" => {
-                let inner = crate::http::InnerClient::Spin(crate::spin::HttpClient::new());
-                Client::Http(crate::http::Client::from_config(inner, config)?)
-            },
-            _ => anyhow::bail!("Unknown scheme: {scheme}. Make sure your backend exists and is enabled with its feature flag"),
-        })
+    pub async fn batch_transaction_with(
+        &self,
+        stmts: impl IntoIterator<Item = impl Into<Statement>>,
+        mode: TxMode,
+    ) -> Result<Vec<ResultSet>> {
+        let stmts: Vec<Statement> = stmts.into_iter().map(Into::into).collect();
+        if let Some(stmt) = stmts.iter().find(|stmt| controls_transaction(stmt)) {
+            anyhow::bail!(
+                "Statement {:?} cannot be used in batch_transaction(), which manages the transaction itself",
+                stmt.sql
+            );
+        }
+        #[cfg(any(
+            feature = "reqwest_backend",
+            feature = "workers_backend",
+            feature = "spin_backend",
+            feature = "tower"
+        ))]
+        if let Self::Http(h) = self {
+            return self.batch_transaction_http(h, stmts, mode).await;
+        }
+        let id = TRANSACTION_IDS.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        let tx = Transaction::with_mode(self, id, mode).await?;
+        let mut results = Vec::with_capacity(stmts.len());
+        for stmt in stmts {
+            match tx.execute(stmt).await {
+                Ok(rs) => results.push(rs),
+                Err(e) => {
+                    // The original error is more useful than a failure to roll back
+                    let _ = tx.rollback().await;
+                    return Err(e);
+                }
+            }
+        }
+        tx.commit().await?;
+        Ok(results)
     }
 
-    /// Establishes a database client based on environment variables
+    /// Runs [`Client::batch_transaction_with()`] over HTTP, sending the whole transaction
+    /// in a single request instead of one per statement
+    #[cfg(any(
+        feature = "reqwest_backend",
+        feature = "workers_backend",
+        feature = "spin_backend",
+        feature = "tower"
+    ))]
+    async fn batch_transaction_http(
+        &self,
+        client: &crate::http::Client,
+        stmts: Vec<Statement>,
+        mode: TxMode,
+    ) -> Result<Vec<ResultSet>> {
+        let options = self.options();
+        let begin = mode.begin();
+        options.log(&begin);
+        let stmts: Vec<Statement> = stmts
+            .into_iter()
+            .map(|stmt| {
+                let stmt = options.bind_defaults(stmt);
+                options.log(&stmt);
+                stmt
+            })
+            .collect();
+        let labels: Vec<Option<String>> = stmts.iter().map(|s| s.label.clone()).collect();
+        let count = stmts.len();
+        let (batch_results, code) = client
+            .batch_transaction(begin, stmts)
+            .await
+            .map_err(|e| options.name_error(e))?;
+        // The steps are BEGIN, the statements, COMMIT, and ROLLBACK whose outcome is irrelevant
+        let step_error = batch_results
+            .step_errors
+            .into_iter()
+            .take(count + 2)
+            .enumerate()
+            .find_map(|(i, e)| e.map(|e| (i, e)));
+        if let Some((i, error)) = step_error {
+            let label = i
+                .checked_sub(1)
+                .and_then(|i| labels.get(i))
+                .and_then(Option::as_deref);
+            let error = crate::Error::Sql {
+                message: error.message,
+                code,
+            };
+            return Err(options.name_error(label_error(error.into(), label)));
+        }
+        let results: Vec<ResultSet> = batch_results
+            .step_results
+            .into_iter()
+            .skip(1)
+            .take(count)
+            .map(|maybe_rs| {
+                maybe_rs
+                    .map(ResultSet::from)
+                    .ok_or_else(|| anyhow::anyhow!("Unexpected missing result set"))
+            })
+            .collect::<Result<_>>()?;
+        if results.len() != count {
+            anyhow::bail!(
+                "Expected {count} results from server, got {}",
+                results.len()
+            );
+        }
+        Ok(results)
+    }
+
+    /// Executes a batch of SQL statements atomically like [`Client::batch_transaction()`],
+    /// returning the total number of rows they changed, see [`crate::total_rows_affected()`].
+    ///
+    /// # Arguments
+    /// * `stmts` - SQL statements
     ///
-    /// # Env
-    /// * `LIBSQL_CLIENT_URL` - URL of the database endpoint - e.g. a https:// endpoint for remote connections
-    ///   (with specified credentials) or local file:/// path for a local database
-    /// * (optional) `LIBSQL_CLIENT_TOKEN` - authentication token for the database. Skip if your database
-    ///   does not require authentication
-    /// *
     /// # Examples
     ///
     /// ```
-    /// # async fn run() {
-    /// # use libsql_client::Config;
-    /// # std::env::set_var("LIBSQL_CLIENT_URL", "file:////tmp/example.db");
-    /// let db = libsql_client::Client::from_env().await.unwrap();
+    /// # async fn run() -> anyhow::Result<()> {
+    /// let db = libsql_client::Client::in_memory()?;
+    /// # db.execute("create table foo(bar text)").await?;
+    /// let changed = db
+    ///     .batch_transaction_counts([
+    ///         "insert into foo(bar) values ('a'), ('b')",
+    ///         "update foo set bar = 'c' where bar = 'a'",
+    ///     ])
+    ///     .await?;
+    /// assert_eq!(changed, 3);
+    /// # Ok(())
     /// # }
     /// ```
-    pub async fn from_env() -> anyhow::Result<Client> {
-        let url = std::env::var("LIBSQL_CLIENT_URL").map_err(|_| {
-            anyhow::anyhow!("LIBSQL_CLIENT_URL variable should point to your libSQL/sqld database")
-        })?;
-        let auth_token = std::env::var("LIBSQL_CLIENT_TOKEN").ok();
-        Self::from_config(Config {
-            url: url::Url::parse(&url)?,
-            auth_token,
-        })
-        .await
+    pub async fn batch_transaction_counts(
+        &self,
+        stmts: impl IntoIterator<Item = impl Into<Statement>>,
+    ) -> Result<u64> {
+        let results = self.batch_transaction(stmts).await?;
+        Ok(crate::total_rows_affected(&results))
     }
 
-    #[cfg(feature = "workers_backend")]
-    pub fn from_workers_env(env: &worker::Env) -> anyhow::Result<Client> {
-        let url = env
-            .secret("LIBSQL_CLIENT_URL")
-            .map_err(|e| anyhow::anyhow!("{e}"))?
-            .to_string();
-        let token = env
-            .secret("LIBSQL_CLIENT_TOKEN")
-            .map_err(|e| anyhow::anyhow!("{e}"))?
-            .to_string();
-        let config = Config {
-            url: url::Url::parse(&url)?,
-            auth_token: Some(token),
-        };
-        let inner = crate::http::InnerClient::Workers(crate::workers::HttpClient::new());
-        Ok(Client::Http(crate::http::Client::from_config(
-            inner, config,
-        )?))
+    /// Executes statements produced lazily by a stream, in transactional chunks,
+    /// returning the total number of rows changed.
+    ///
+    /// Up to `chunk_size` statements are buffered and executed together with
+    /// [`Client::batch_transaction()`], so statements controlling transactions are rejected.
+    /// The stream is only polled for the next chunk once the previous one completed, so at most
+    /// `chunk_size` statements are held in memory, and a slow server slows down the producer.
+    /// Each chunk is atomic, but the stream as a whole is not: if a chunk fails, its error is
+    /// returned, the chunks sent before it stay committed, and the rest of the stream is not consumed.
+    /// Results of queries are discarded, use [`Client::batch_transaction()`] to read them.
+    ///
+    /// # Arguments
+    /// * `stmts` - stream of SQL statements
+    /// * `chunk_size` - maximum number of statements sent in a single batch
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn run() {
+    /// use futures::StreamExt;
+    ///
+    /// let db = libsql_client::Client::in_memory().unwrap();
+    /// # db.execute("create table foo(bar integer)").await.unwrap();
+    /// let stmts = futures::stream::iter(0..100).map(|i| {
+    ///     libsql_client::Statement::with_args("insert into foo(bar) values (?)", &[i])
+    /// });
+    /// let changed = db.batch_stream(stmts, 10).await.unwrap();
+    /// assert_eq!(changed, 100);
+    /// # }
+    /// ```
+    pub async fn batch_stream(
+        &self,
+        stmts: impl Stream<Item = impl Into<Statement> + Send> + Send,
+        chunk_size: usize,
+    ) -> Result<u64> {
+        use futures::StreamExt;
+
+        if chunk_size == 0 {
+            anyhow::bail!("Chunk size must be positive");
+        }
+        let mut chunks = std::pin::pin!(stmts.chunks(chunk_size));
+        let mut rows_affected = 0;
+        while let Some(chunk) = chunks.next().await {
+            rows_affected += self.batch_transaction_counts(chunk).await?;
+        }
+        Ok(rows_affected)
     }
-}
 
-pub mod sync {}
-impl SyncClient {
-    /// Creates an in-memory database
+    /// Transactionally executes a batch of queries and merges their results into one [ResultSet],
+    /// as with a client-side `UNION ALL`.
+    ///
+    /// Useful for fanning out the same query over several shards or attached databases.
+    /// All results must have identical column names, in the same order, and the same
+    /// [ValueType] for each column, or an error is returned. The type of a column in a result
+    /// is that of its first non-NULL value, so NULLs and empty results match any type.
+    /// Rows are concatenated in the order of the statements and `rows_affected` is summed.
+    ///
+    /// # Arguments
+    /// * `stmts` - SQL statements, at least one
     ///
     /// # Examples
     ///
     /// ```
-    /// # async fn f() {
-    /// # use libsql_client::Config;
-    /// let db = libsql_client::SyncClient::in_memory().unwrap();
+    /// # async fn run() {
+    /// let db = libsql_client::Client::in_memory().unwrap();
+    /// let rs = db.batch_merge([
+    ///   "select 1 as shard",
+    ///   "select 2 as shard",
+    /// ]).await.unwrap();
+    /// assert_eq!(rs.rows.len(), 2)
     /// # }
     /// ```
-    #[cfg(feature = "local_backend")]
-    pub fn in_memory() -> anyhow::Result<Self> {
-        Ok(Self {
-            inner: Client::in_memory()?,
-        })
+    pub async fn batch_merge<I: IntoIterator<Item = impl Into<Statement> + Send> + Send>(
+        &self,
+        stmts: I,
+    ) -> Result<ResultSet>
+    where
+        <I as IntoIterator>::IntoIter: Send,
+    {
+        let mut results = self.batch(stmts).await?.into_iter().enumerate();
+        let Some((_, mut merged)) = results.next() else {
+            anyhow::bail!("batch_merge() needs at least one statement");
+        };
+        // Type of each column and the statement it was first seen in
+        let mut types: Vec<Option<(ValueType, usize)>> = column_types(&merged)
+            .into_iter()
+            .map(|t| t.map(|t| (t, 0)))
+            .collect();
+        for (i, rs) in results {
+            if rs.columns != merged.columns {
+                anyhow::bail!(
+                    "Cannot merge results with different columns: statement 0 returned {:?}, statement {i} returned {:?}",
+                    merged.columns,
+                    rs.columns
+                );
+            }
+            for (c, t) in column_types(&rs).into_iter().enumerate() {
+                match (types[c], t) {
+                    (Some((expected, j)), Some(t)) if expected != t => anyhow::bail!(
+                        "Cannot merge results with different types in column `{}`: statement {j} returned {expected:?}, statement {i} returned {t:?}",
+                        rs.columns[c]
+                    ),
+                    (None, Some(t)) => types[c] = Some((t, i)),
+                    _ => {}
+                }
+            }
+            merged.rows.extend(rs.rows);
+            merged.rows_affected += rs.rows_affected;
+        }
+        merged.last_insert_rowid = None;
+        Ok(merged)
     }
 
-    /// Establishes a database client based on [Config] struct
+    /// Executes the same SQL statement once per set of parameters, transactionally,
+    /// returning one result per set.
+    ///
+    /// All statements are sent in a single batch, like with [`Client::batch()`]. This is meant
+    /// for statements which cannot be merged into one, like `UPDATE`s of different rows;
+    /// to insert many rows, [`Client::insert_all()`] is more efficient. Fails without sending
+    /// anything if a set does not have as many parameters as the SQL has placeholders.
+    ///
+    /// # Arguments
+    /// * `sql` - SQL statement with placeholders
+    /// * `param_sets` - parameters of each execution
     ///
     /// # Examples
     ///
     /// ```
-    /// # fn f() {
-    /// # use libsql_client::Config;
-    /// let config = Config { url: url::Url::parse("file:////tmp/example.db").unwrap(), auth_token: None };
-    /// let db = libsql_client::SyncClient::from_config(config).unwrap();
+    /// # async fn run() -> anyhow::Result<()> {
+    /// use libsql_client::args;
+    ///
+    /// let db = libsql_client::Client::in_memory()?;
+    /// # db.execute("create table users(id integer, name text)").await?;
+    /// db.execute_many(
+    ///     "update users set name = ? where id = ?",
+    ///     &[args!("alice", 1), args!("bob", 2)],
+    /// ).await?;
+    /// # Ok(())
     /// # }
     /// ```
-    pub fn from_config(config: Config) -> Result<Self> {
-        Ok(Self {
-            inner: futures::executor::block_on(Client::from_config(config))?,
-        })
+    pub async fn execute_many(
+        &self,
+        sql: &str,
+        param_sets: &[impl AsRef<[proto::Value]>],
+    ) -> Result<Vec<ResultSet>> {
+        let template = Statement::new(sql);
+        let expected = crate::statement::parameter_count(&template.sql)?;
+        let stmts = param_sets
+            .iter()
+            .enumerate()
+            .map(|(i, params)| {
+                let params = params.as_ref();
+                if params.len() != expected {
+                    anyhow::bail!(
+                        "Parameter set {i} has {} values, but the statement expects {expected}",
+                        params.len()
+                    );
+                }
+                Ok(Statement {
+                    args: params.to_vec(),
+                    ..template.clone()
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        self.batch(stmts).await
     }
 
-    /// Establishes a database client based on environment variables
+    /// Inserts `rows` into `table`, transactionally, returning the number of inserted rows.
     ///
-    /// # Env
-    /// * `LIBSQL_CLIENT_URL` - URL of the database endpoint - e.g. a https:// endpoint for remote connections
-    ///   (with specified credentials) or local file:/// path for a local database
-    /// * (optional) `LIBSQL_CLIENT_TOKEN` - authentication token for the database. Skip if your database
-    ///   does not require authentication
+    /// Each row is serialized with [`ser::to_named_values()`](crate::ser::to_named_values),
+    /// so its fields become columns. All rows must have the same fields.
+    /// Rows are sent as multi-row `INSERT` statements, in chunks small enough to stay
+    /// below SQLite's limit on the number of parameters. Table and column names are not escaped.
+    ///
+    /// # Arguments
+    /// * `table` - name of the table
+    /// * `rows` - rows to insert
     ///
     /// # Examples
     ///
     /// ```
-    /// # fn run() {
-    /// # use libsql_client::Config;
-    /// # std::env::set_var("LIBSQL_CLIENT_URL", "file:////tmp/example.db");
-    /// let db = libsql_client::SyncClient::from_env().unwrap();
+    /// # async fn run() {
+    /// #[derive(serde::Serialize)]
+    /// struct User {
+    ///     name: String,
+    ///     age: i64,
+    /// }
+    ///
+    /// let db = libsql_client::Client::in_memory().unwrap();
+    /// db.execute("create table users(name text, age integer)").await.unwrap();
+    /// let users = vec![
+    ///     User { name: "alice".into(), age: 42 },
+    ///     User { name: "bob".into(), age: 69 },
+    /// ];
+    /// let inserted = db.insert_all("users", users).await.unwrap();
+    /// assert_eq!(inserted, 2);
     /// # }
     /// ```
-    pub fn from_env() -> Result<Self> {
-        Ok(Self {
-            inner: futures::executor::block_on(Client::from_env())?,
-        })
-    }
+    pub async fn insert_all<T: serde::Serialize>(
+        &self,
+        table: &str,
+        rows: impl IntoIterator<Item = T>,
+    ) -> Result<u64> {
+        let mut rows = rows
+            .into_iter()
+            .map(|row| crate::ser::to_named_values(&row));
+        let Some(first) = rows.next().transpose()? else {
+            return Ok(0);
+        };
+        let columns: Vec<String> = first.iter().map(|(name, _)| name.clone()).collect();
+        if columns.is_empty() {
+            anyhow::bail!("Cannot insert rows without any fields into `{table}`");
+        }
+        let mut values: Vec<Vec<proto::Value>> =
+            vec![first.into_iter().map(|(_, value)| value).collect()];
+        for (i, row) in rows.enumerate() {
+            let mut row: std::collections::HashMap<String, proto::Value> =
+                row?.into_iter().collect();
+            let row_values = columns
+                .iter()
+                .map(|col| row.remove(col))
+                .collect::<Option<Vec<_>>>();
+            match row_values {
+                Some(row_values) if row.is_empty() => values.push(row_values),
+                _ => anyhow::bail!(
+                    "Row {} does not have the same fields as the first row: {columns:?}",
+                    i + 1
+                ),
+            }
+        }
 
-    #[cfg(feature = "workers_backend")]
-    pub fn from_workers_env(env: &worker::Env) -> Result<Self> {
-        Ok(Self {
-            inner: Client::from_workers_env(env)?,
-        })
+        let placeholders = format!("({})", vec!["?"; columns.len()].join(", "));
+        let stmts: Vec<Statement> = values
+            .chunks((MAX_PARAMS / columns.len()).max(1))
+            .map(|chunk| {
+                Statement::with_args(
+                    format!(
+                        "INSERT INTO {table} ({}) VALUES {}",
+                        columns.join(", "),
+                        vec![placeholders.as_str(); chunk.len()].join(", ")
+                    ),
+                    &chunk.concat(),
+                )
+            })
+            .collect();
+        let results = self.batch(stmts).await?;
+        Ok(crate::total_rows_affected(&results))
     }
 
-    /// Executes a batch of independent SQL statements.
+    /// Returns a [BulkLoader](crate::bulk::BulkLoader) inserting rows into `columns` of `table`
+    /// as they are fed, for loading large datasets without collecting them in memory first.
+    ///
+    /// Unlike [`Client::insert_all()`], rows are not inserted in a single transaction,
+    /// see [BulkLoader](crate::bulk::BulkLoader) for details. Table and column names are not escaped.
     ///
-    /// For a version in which statements execute transactionally, see [`SyncClient::batch()`]
     /// # Arguments
-    /// * `stmts` - SQL statements
+    /// * `table` - name of the table
+    /// * `columns` - names of the columns to insert values into
     ///
     /// # Examples
     ///
     /// ```
-    /// # fn run() {
-    /// # use libsql_client::Config;
-    /// let db = libsql_client::SyncClient::in_memory().unwrap();
-    /// # db.execute("create table foo(bar text)").unwrap();
-    /// let res = db.raw_batch([
-    ///   "select * from foo",
-    ///   "insert into foo(bar) values ('bar')"
-    /// ]).unwrap();
+    /// # async fn run() -> anyhow::Result<()> {
+    /// use libsql_client::args;
+    ///
+    /// let db = libsql_client::Client::in_memory()?;
+    /// db.execute("create table users(name text, age integer)").await?;
+    /// let mut loader = db.bulk_loader("users", &["name", "age"])?;
+    /// for (name, age) in [("alice", 42), ("bob", 69)] {
+    ///     loader.feed(args!(name, age).to_vec()).await?;
+    /// }
+    /// assert_eq!(loader.finish().await?, 2);
+    /// # Ok(())
     /// # }
     /// ```
-    pub fn raw_batch(
+    pub fn bulk_loader(
         &self,
-        stmts: impl IntoIterator<Item = impl Into<Statement> + Send> + Send,
-    ) -> Result<BatchResult> {
-        futures::executor::block_on(self.inner.raw_batch(stmts))
+        table: &str,
+        columns: &[&str],
+    ) -> Result<crate::bulk::BulkLoader<'_>> {
+        crate::bulk::BulkLoader::new(self, table, columns)
     }
 
-    /// Transactionally executes a batch of SQL statements.
+    /// Transactionally executes a batch of SQL statements, in synchronous contexts.
     ///
-    /// For a version in which statements can fail or succeed independently, see [`SyncClient::raw_batch()`]
+    /// This method calls [block_on](`futures::executor::block_on()`) internally.
+    ///
+    /// For the async version of this method, see [`Client::batch()`]
     /// # Arguments
     /// * `stmts` - SQL statements
     ///
     /// # Examples
     ///
     /// ```
-    /// # fn run() {
+    /// # async fn run() {
     /// # use libsql_client::Config;
-    /// let db = libsql_client::SyncClient::in_memory().unwrap();
-    /// # db.execute("create table foo(bar text)").unwrap();
-    /// let res = db.batch([
+    /// # std::env::set_var("LIBSQL_CLIENT_URL", "file:////tmp/example.db");
+    /// let db = libsql_client::Client::in_memory().unwrap();
+    /// # db.execute("create table foo(bar text)").await.unwrap();
+    /// let res = db.batch_sync([
     ///   "select * from foo",
     ///   "insert into foo(bar) values ('bar')"
     /// ]).unwrap();
     /// assert_eq!(res.len(), 2)
     /// # }
     /// ```
-    pub fn batch<I: IntoIterator<Item = impl Into<Statement> + Send> + Send>(
+    pub fn batch_sync<I: IntoIterator<Item = impl Into<Statement> + Send> + Send>(
         &self,
         stmts: I,
     ) -> Result<Vec<ResultSet>>
     where
         <I as std::iter::IntoIterator>::IntoIter: std::marker::Send,
     {
-        futures::executor::block_on(self.inner.batch(stmts))
+        futures::executor::block_on(self.batch(stmts))
     }
 
     /// Executes a single SQL statement
@@ -517,44 +1107,1596 @@ impl SyncClient {
     /// ```
     /// # async fn run() {
     /// # use libsql_client::Config;
-    /// let db = libsql_client::SyncClient::in_memory().unwrap();
-    /// # db.execute("create table foo(bar text)").unwrap();
-    /// db.execute("select * from foo").unwrap();
+    /// let db = libsql_client::Client::in_memory().unwrap();
+    /// # db.execute("create table foo(bar text)").await.unwrap();
+    /// db.execute("select * from foo").await.unwrap();
     /// # }
     /// ```
-    pub fn execute(&self, stmt: impl Into<Statement> + Send) -> Result<ResultSet> {
-        futures::executor::block_on(self.inner.execute(stmt))
+    pub async fn execute(&self, stmt: impl Into<Statement> + Send) -> Result<ResultSet> {
+        let stmt = self.options().bind_defaults(stmt.into());
+        self.options().log(&stmt);
+        let trace = self.options().trace(&stmt);
+        let label = stmt.label.clone();
+        let span = tracing::debug_span!(
+            "execute",
+            client = self.options().name.as_deref(),
+            label = label.as_deref()
+        );
+        let result: Result<ResultSet> = async {
+            match self {
+                #[cfg(feature = "local_backend")]
+                Self::Local(l) => l.execute(stmt),
+                #[cfg(any(
+                    feature = "reqwest_backend",
+                    feature = "workers_backend",
+                    feature = "spin_backend",
+                    feature = "tower"
+                ))]
+                Self::Http(r) => r.execute(stmt).await,
+                #[cfg(feature = "hrana_backend")]
+                Self::Hrana(h) => h.execute(stmt).await,
+                _ => panic!("Must enable at least one feature"),
+            }
+        }
+        .instrument(span)
+        .await;
+        let result =
+            result.map_err(|e| self.options().name_error(label_error(e, label.as_deref())));
+        if let Some(trace) = trace {
+            trace.finish(&result);
+        }
+        result
     }
 
-    /// Creates an interactive transaction
+    /// Executes an `INSERT` statement and returns the rowid of the inserted row
+    ///
+    /// Fails if no row was inserted, e.g. because of `INSERT OR IGNORE`.
+    /// If several rows are inserted, the rowid of the last one is returned.
+    ///
+    /// # Arguments
+    /// * `stmt` - the `INSERT` statement
     ///
     /// # Examples
     ///
     /// ```
     /// # async fn run() {
-    /// # use libsql_client::Config;
-    /// let db = libsql_client::SyncClient::in_memory().unwrap();
-    /// # db.execute("create table foo(bar text)").unwrap();
-    /// let tx = db.transaction().unwrap();
-    /// tx.execute("select * from foo").unwrap();
-    /// tx.commit();
+    /// let db = libsql_client::Client::in_memory().unwrap();
+    /// # db.execute("create table foo(bar text)").await.unwrap();
+    /// let id = db.execute_returning_id("insert into foo(bar) values ('bar')").await.unwrap();
+    /// assert_eq!(id, 1);
     /// # }
     /// ```
-    pub fn transaction(&self) -> Result<SyncTransaction> {
-        let id = TRANSACTION_IDS.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
-        SyncTransaction::new(self, id)
-    }
-
-    pub(crate) fn execute_in_transaction(&self, tx_id: u64, stmt: Statement) -> Result<ResultSet> {
-        futures::executor::block_on(self.inner.execute_in_transaction(tx_id, stmt))
+    pub async fn execute_returning_id(&self, stmt: impl Into<Statement> + Send) -> Result<i64> {
+        let stmt: Statement = stmt.into();
+        let sql = stmt.sql.clone();
+        let rs = self.execute(stmt).await?;
+        match rs.last_insert_rowid {
+            Some(id) if rs.rows_affected > 0 => Ok(id),
+            _ => anyhow::bail!("Statement {sql:?} did not insert any row"),
+        }
     }
 
-    pub(crate) fn commit_transaction(&self, tx_id: u64) -> Result<()> {
-        futures::executor::block_on(self.inner.commit_transaction(tx_id))
+    /// Executes an `INSERT`, `UPDATE` or `DELETE` statement with a `RETURNING` clause,
+    /// deserializing the returned rows into `T`
+    ///
+    /// Rows are deserialized with [`Row::deserialize_into()`], so struct fields are matched
+    /// to the columns of the `RETURNING` clause by name. Statements without a `RETURNING`
+    /// clause return no rows, and thus an empty `Vec`.
+    ///
+    /// # Arguments
+    /// * `stmt` - the statement with a `RETURNING` clause
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn run() -> anyhow::Result<()> {
+    /// #[derive(serde::Deserialize)]
+    /// struct User {
+    ///     id: i64,
+    ///     name: String,
+    /// }
+    ///
+    /// let db = libsql_client::Client::in_memory()?;
+    /// # db.execute("create table users(id integer primary key, name text)").await?;
+    /// let users: Vec<User> = db
+    ///     .execute_returning("insert into users(name) values ('alice') returning id, name")
+    ///     .await?;
+    /// assert_eq!(users[0].name, "alice");
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "mapping_names_to_values_in_rows")]
+    pub async fn execute_returning<T: serde::de::DeserializeOwned>(
+        &self,
+        stmt: impl Into<Statement> + Send,
+    ) -> Result<Vec<T>> {
+        self.execute(stmt)
+            .await?
+            .rows
+            .iter()
+            .map(Row::deserialize_into)
+            .collect()
     }
 
-    pub(crate) fn rollback_transaction(&self, tx_id: u64) -> Result<()> {
-        futures::executor::block_on(self.inner.rollback_transaction(tx_id))
+    /// Checks whether `table` has rows matching `where_clause`, without fetching them
+    ///
+    /// The clause is inserted verbatim after `WHERE`, see [`Statement::exists()`].
+    ///
+    /// # Arguments
+    /// * `table` - name of the table, not escaped
+    /// * `where_clause` - condition with `?` placeholders
+    /// * `params` - values bound to the placeholders
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn run() -> anyhow::Result<()> {
+    /// let db = libsql_client::Client::in_memory()?;
+    /// # db.execute("create table users(email text)").await?;
+    /// if !db.exists("users", "email = ?", &["a@b.c"]).await? {
+    ///     db.execute(libsql_client::Statement::with_args(
+    ///         "insert into users values (?)",
+    ///         &["a@b.c"],
+    ///     )).await?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn exists(
+        &self,
+        table: &str,
+        where_clause: &str,
+        params: &[impl Into<proto::Value> + Clone],
+    ) -> Result<bool> {
+        let rs = self
+            .execute(Statement::exists(table, where_clause, params)?)
+            .await?;
+        let row = rs
+            .rows
+            .first()
+            .ok_or_else(|| anyhow::anyhow!("EXISTS query returned no rows"))?;
+        Ok(row.try_get::<i64>(0)? != 0)
+    }
+
+    /// Counts the rows of `table`, or only those matching `where_clause` if given
+    ///
+    /// The clause is inserted verbatim after `WHERE`, see [`Statement::count()`].
+    ///
+    /// # Arguments
+    /// * `table` - name of the table, not escaped
+    /// * `where_clause` - condition with `?` placeholders, if any
+    /// * `params` - values bound to the placeholders
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn run() -> anyhow::Result<()> {
+    /// let db = libsql_client::Client::in_memory()?;
+    /// # db.execute("create table users(age integer)").await?;
+    /// let total = db.count("users", None, &[] as &[i64]).await?;
+    /// let adults = db.count("users", Some("age >= ?"), &[18]).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn count(
+        &self,
+        table: &str,
+        where_clause: Option<&str>,
+        params: &[impl Into<proto::Value> + Clone],
+    ) -> Result<u64> {
+        let rs = self
+            .execute(Statement::count(table, where_clause, params)?)
+            .await?;
+        let row = rs
+            .rows
+            .first()
+            .ok_or_else(|| anyhow::anyhow!("COUNT query returned no rows"))?;
+        row.try_get::<u64>(0)
+    }
+
+    /// Rebuilds the database file with `VACUUM`, reclaiming unused space
+    ///
+    /// `VACUUM` cannot run inside a transaction, so it is sent on its own rather than
+    /// in a batch, and fails if called on a connection with an open transaction.
+    /// It needs up to twice the size of the database in free disk space and blocks
+    /// writers while it runs, so schedule it when the database is idle.
+    pub async fn vacuum(&self) -> Result<()> {
+        self.execute("VACUUM").await.map(|_| ())
+    }
+
+    /// Gathers statistics about tables and indexes with `ANALYZE`,
+    /// which the query planner uses to pick better plans
+    ///
+    /// Like [`Client::vacuum()`], it is sent on its own rather than in a batch.
+    /// On large databases it can take a while, so schedule it when the database is idle.
+    pub async fn analyze(&self) -> Result<()> {
+        self.execute("ANALYZE").await.map(|_| ())
+    }
+
+    /// Reads `PRAGMA user_version`, an integer stored in the database header
+    /// which SQLite leaves to applications, e.g. to track applied migrations
+    ///
+    /// It is `0` in new databases. See [`Client::set_user_version()`] to change it.
+    pub async fn user_version(&self) -> Result<i32> {
+        let version = self.read_pragma_integer("user_version").await?;
+        Ok(i32::try_from(version)?)
+    }
+
+    /// Sets `PRAGMA user_version` to `version`, see [`Client::user_version()`]
+    ///
+    /// The user version is a 32-bit integer, which SQLite would silently truncate
+    /// larger values to, hence the type of `version`.
+    ///
+    /// To record a migration atomically, include the pragma in the batch applying it instead:
+    /// `PRAGMA user_version = N` takes effect as part of the surrounding transaction.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn run() -> anyhow::Result<()> {
+    /// let db = libsql_client::Client::in_memory()?;
+    /// if db.user_version().await? < 1 {
+    ///     db.batch(["create table users(name text)", "pragma user_version = 1"]).await?;
+    /// }
+    /// assert_eq!(db.user_version().await?, 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn set_user_version(&self, version: i32) -> Result<()> {
+        self.execute(format!("PRAGMA user_version = {version}"))
+            .await
+            .map(|_| ())
+    }
+
+    /// Reads `PRAGMA schema_version`, which SQLite increments itself whenever
+    /// the schema changes, e.g. when a table or index is created
+    ///
+    /// Unlike [`Client::user_version()`], it is not meant to be set by applications, so
+    /// there is no setter: it tells whether the schema changed, not which migrations ran.
+    pub async fn schema_version(&self) -> Result<i64> {
+        self.read_pragma_integer("schema_version").await
+    }
+
+    async fn read_pragma_integer(&self, pragma: &str) -> Result<i64> {
+        let rs = self.execute(format!("PRAGMA {pragma}")).await?;
+        let row = rs
+            .rows
+            .first()
+            .ok_or_else(|| anyhow::anyhow!("PRAGMA {pragma} returned no rows"))?;
+        row.try_get(0)
+    }
+
+    /// Fetches the rows of a `SELECT` statement page by page
+    ///
+    /// `LIMIT ? OFFSET ?` is appended to the statement, and pages are fetched lazily
+    /// as the stream is polled, until a page shorter than `page_size` is returned.
+    /// The statement must have an `ORDER BY` clause, because otherwise the order of rows,
+    /// and thus the contents of pages, is not guaranteed to be stable. It also must not
+    /// have its own `LIMIT` clause.
+    ///
+    /// # Arguments
+    /// * `stmt` - the `SELECT` statement
+    /// * `page_size` - maximum number of rows in a single page
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn run() -> anyhow::Result<()> {
+    /// use futures::StreamExt;
+    ///
+    /// let db = libsql_client::Client::in_memory()?;
+    /// # db.execute("create table foo(bar text)").await?;
+    /// let pages = db.paginate("select * from foo order by bar", 100)?;
+    /// let mut pages = std::pin::pin!(pages);
+    /// while let Some(page) = pages.next().await {
+    ///     println!("{} rows", page?.len());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn paginate(
+        &self,
+        stmt: impl Into<Statement>,
+        page_size: usize,
+    ) -> Result<impl Stream<Item = Result<Vec<Row>>> + '_> {
+        let stmt: Statement = stmt.into();
+        if page_size == 0 {
+            anyhow::bail!("Page size must be positive");
+        }
+        match Parser::new(stmt.sql.as_bytes()).next()? {
+            Some(Cmd::Stmt(Stmt::Select(select))) => {
+                if select.order_by.is_none() {
+                    anyhow::bail!(
+                        "Paginated queries need an ORDER BY clause for a stable order of rows: {}",
+                        stmt.sql
+                    );
+                }
+                if select.limit.is_some() {
+                    anyhow::bail!(
+                        "Paginated queries cannot have their own LIMIT clause: {}",
+                        stmt.sql
+                    );
+                }
+            }
+            _ => anyhow::bail!("Only SELECT statements can be paginated: {}", stmt.sql),
+        }
+        let sql = format!("{} LIMIT ? OFFSET ?", stmt.sql);
+        let args = stmt.args;
+        let label = stmt.label;
+        Ok(futures::stream::try_unfold(Some(0), move |offset| {
+            let sql = sql.clone();
+            let mut args = args.clone();
+            let label = label.clone();
+            async move {
+                let Some(offset) = offset else {
+                    return Ok(None);
+                };
+                args.push(page_size.into());
+                args.push(offset.into());
+                let rows = self
+                    .execute(Statement {
+                        sql,
+                        args,
+                        label: label.clone(),
+                    })
+                    .await?
+                    .rows;
+                let next_offset = if rows.len() < page_size {
+                    None
+                } else {
+                    Some(offset + page_size)
+                };
+                Ok(Some((rows, next_offset)))
+            }
+        }))
+    }
+
+    /// Writes all the rows of a table to `writer`, returning the number of rows written
+    ///
+    /// Rows are fetched in pages with [`Client::paginate()`], ordered by `rowid`, and written
+    /// as soon as each page arrives, so large tables are not held in memory. Tables declared
+    /// `WITHOUT ROWID` are not supported. See [ExportFormat](crate::export::ExportFormat)
+    /// for how values are written. The table name is not escaped.
+    ///
+    /// Pages are fetched by separate statements, so rows modified during the export
+    /// may be skipped or written twice. Run it on a quiescent table for a consistent copy.
+    ///
+    /// # Arguments
+    /// * `table` - name of the table
+    /// * `writer` - destination of the rows, which is flushed at the end
+    /// * `format` - format of the rows
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn run() -> anyhow::Result<()> {
+    /// use libsql_client::export::ExportFormat;
+    ///
+    /// let db = libsql_client::Client::in_memory()?;
+    /// # db.execute("create table users(name text)").await?;
+    /// let file = std::fs::File::create("/tmp/users.jsonl")?;
+    /// db.copy_table_to("users", std::io::BufWriter::new(file), ExportFormat::JsonLines)
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn copy_table_to<W: std::io::Write>(
+        &self,
+        table: &str,
+        writer: W,
+        format: crate::export::ExportFormat,
+    ) -> Result<u64> {
+        crate::export::copy_table(self, table, writer, format).await
+    }
+
+    /// Imports CSV into `table`, returning the number of rows imported.
+    ///
+    /// The first record is a header naming the columns the fields of the following records
+    /// are inserted into. Fields may be quoted as described in RFC 4180, and are inserted as text,
+    /// leaving it to the affinity of the columns to convert them. Rows are inserted in chunks
+    /// with a [BulkLoader](crate::bulk::BulkLoader), so they are not in a single transaction:
+    /// if a record fails, the chunks inserted before it stay in the table.
+    /// Column names from the header are quoted as identifiers, so they may contain any character,
+    /// but the table name is not escaped.
+    ///
+    /// The reader is a [`futures::io::AsyncBufRead`], which Tokio readers can be adapted to
+    /// with the `compat` module of `tokio-util`.
+    ///
+    /// # Arguments
+    /// * `table` - name of the table
+    /// * `reader` - source of the CSV
+    /// * `options` - delimiter and `NULL` sentinel of the CSV
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn run() -> anyhow::Result<()> {
+    /// use libsql_client::import::CsvOptions;
+    ///
+    /// let db = libsql_client::Client::in_memory()?;
+    /// db.execute("create table users(name text, age integer)").await?;
+    /// let csv = "name,age\nalice,42\n\"bob, jr\",69\n";
+    /// let imported = db.import_csv("users", csv.as_bytes(), &CsvOptions::default()).await?;
+    /// assert_eq!(imported, 2);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn import_csv(
+        &self,
+        table: &str,
+        reader: impl futures::io::AsyncBufRead + Unpin,
+        options: &crate::import::CsvOptions,
+    ) -> Result<u64> {
+        crate::import::import_csv(self, table, reader, options).await
+    }
+
+    /// Creates an interactive transaction
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn run() {
+    /// # use libsql_client::Config;
+    /// let db = libsql_client::Client::in_memory().unwrap();
+    /// # db.execute("create table foo(bar text)").await.unwrap();
+    /// let tx = db.transaction().await.unwrap();
+    /// tx.execute("select * from foo").await.unwrap();
+    /// tx.commit();
+    /// # }
+    /// ```
+    pub async fn transaction(&self) -> Result<Transaction<'_>> {
+        let id = TRANSACTION_IDS.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        Transaction::new(self, id).await
+    }
+
+    pub(crate) async fn execute_in_transaction(
+        &self,
+        tx_id: u64,
+        stmt: Statement,
+    ) -> Result<ResultSet> {
+        let stmt = self.options().bind_defaults(stmt);
+        self.options().log(&stmt);
+        let trace = self.options().trace(&stmt);
+        let label = stmt.label.clone();
+        let span = tracing::debug_span!(
+            "execute_in_transaction",
+            client = self.options().name.as_deref(),
+            tx_id,
+            label = label.as_deref()
+        );
+        let result: Result<ResultSet> = async {
+            match self {
+                #[cfg(feature = "local_backend")]
+                Self::Local(l) => l.execute_in_transaction(tx_id, stmt),
+                #[cfg(any(
+                    feature = "reqwest_backend",
+                    feature = "workers_backend",
+                    feature = "spin_backend",
+                    feature = "tower"
+                ))]
+                Self::Http(r) => r.execute_in_transaction(tx_id, stmt).await,
+                #[cfg(feature = "hrana_backend")]
+                Self::Hrana(h) => h.execute_in_transaction(tx_id, stmt).await,
+
+                _ => panic!("Must enable at least one feature"),
+            }
+        }
+        .instrument(span)
+        .await;
+        let result =
+            result.map_err(|e| self.options().name_error(label_error(e, label.as_deref())));
+        if let Some(trace) = trace {
+            trace.finish(&result);
+        }
+        result
+    }
+
+    pub(crate) async fn commit_transaction(&self, tx_id: u64) -> Result<()> {
+        match self {
+            #[cfg(feature = "local_backend")]
+            Self::Local(l) => l.commit_transaction(tx_id),
+            #[cfg(any(
+                feature = "reqwest_backend",
+                feature = "workers_backend",
+                feature = "spin_backend",
+                feature = "tower"
+            ))]
+            Self::Http(r) => r.commit_transaction(tx_id).await,
+            #[cfg(feature = "hrana_backend")]
+            Self::Hrana(h) => h.commit_transaction(tx_id).await,
+
+            _ => panic!("Must enable at least one feature"),
+        }
+    }
+
+    pub(crate) async fn rollback_transaction(&self, tx_id: u64) -> Result<()> {
+        match self {
+            #[cfg(feature = "local_backend")]
+            Self::Local(l) => l.rollback_transaction(tx_id),
+            #[cfg(any(
+                feature = "reqwest_backend",
+                feature = "workers_backend",
+                feature = "spin_backend",
+                feature = "tower"
+            ))]
+            Self::Http(r) => r.rollback_transaction(tx_id).await,
+            #[cfg(feature = "hrana_backend")]
+            Self::Hrana(h) => h.rollback_transaction(tx_id).await,
+
+            _ => panic!("Must enable at least one feature"),
+        }
+    }
+}
+
+impl Client {
+    /// Creates an in-memory database
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f() {
+    /// # use libsql_client::Config;
+    /// let db = libsql_client::Client::in_memory().unwrap();
+    /// # }
+    /// ```
+    #[cfg(feature = "local_backend")]
+    pub fn in_memory() -> anyhow::Result<Client> {
+        Ok(Client::Local(crate::local::Client::in_memory()?))
+    }
+
+    /// Establishes a database client based on [Config] struct
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f() {
+    /// # use libsql_client::Config;
+    /// let config = Config {
+    ///   url: url::Url::parse("file:////tmp/example.db").unwrap(),
+    ///   auth_token: None
+    /// };
+    /// let db = libsql_client::Client::from_config(config).await.unwrap();
+    /// # }
+    /// ```
+    #[allow(unreachable_patterns)]
+    pub async fn from_config(mut config: Config) -> anyhow::Result<Client> {
+        config.url = resolve_libsql_scheme(config.url);
+        let scheme = config.url.scheme();
+        Ok(match scheme {
+            #[cfg(feature = "local_backend")]
+            "file" => {
+                Client::Local(crate::local::Client::new(config.url.to_string())?)
+            },
+            #[cfg(feature = "hrana_backend")]
+            "ws" | "wss" => {
+                Client::Hrana(crate::hrana::Client::from_config(config).await?)
+            },
+            #[cfg(feature = "reqwest_backend")]
+            "http" | "https" => {
+                let inner = crate::http::InnerClient::Reqwest(crate::reqwest::HttpClient::new());
+                Client::Http(crate::http::Client::from_config(inner, config)?)
+            },
+            #[cfg(feature = "workers_backend")]
+            "workers" | "http" | "https" => {
+                let inner = crate::http::InnerClient::Workers(crate::workers::HttpClient::new());
+                Client::Http(crate::http::Client::from_config(inner, config)?)
+            },
+            #[cfg(feature = "spin_backend")]
+            "spin" | "http" | "https" => {
+                let inner = crate::http::InnerClient::Spin(crate::spin::HttpClient::new());
+                Client::Http(crate::http::Client::from_config(inner, config)?)
+            },
+            _ => anyhow::bail!("Unknown scheme: {scheme}. Make sure your backend exists and is enabled with its feature flag"),
+        })
+    }
+
+    /// Establishes a database client from a DSN, a URL carrying its options in the query string,
+    /// as is convenient in configuration files
+    ///
+    /// Credentials are given either as the `authToken` option, or as the user name and password
+    /// of the URL for basic authentication. Other recognized options are:
+    /// - `timeout` - timeout of each request in seconds, see
+    ///   [`http::Client::with_timeout()`](crate::http::Client::with_timeout),
+    /// - `tls_insecure` - `true` to accept invalid TLS certificates, see
+    ///   [`reqwest::HttpClient::with_accept_invalid_certs()`](crate::reqwest::HttpClient::with_accept_invalid_certs),
+    /// - `queries_path` - path of the endpoint receiving queries, see
+    ///   [`http::Client::with_queries_path()`](crate::http::Client::with_queries_path).
+    ///
+    /// These options, as well as basic authentication, are only supported by remote HTTP
+    /// clients, and `tls_insecure` only by the reqwest backend: passing them for other
+    /// backends is an error. Unknown options are an error too, see
+    /// [`Client::from_dsn_lenient()`] to ignore them instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f() -> anyhow::Result<()> {
+    /// let db = libsql_client::Client::from_dsn(
+    ///     "libsql://example.com?authToken=secret&timeout=30",
+    /// )
+    /// .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn from_dsn(dsn: &str) -> anyhow::Result<Client> {
+        Self::from_parsed_dsn(crate::dsn::Dsn::parse(dsn, true)?).await
+    }
+
+    /// Establishes a database client from a DSN like [`Client::from_dsn()`], but only logs
+    /// a warning for unknown options instead of failing, e.g. to accept DSNs meant for
+    /// other clients as well.
+    pub async fn from_dsn_lenient(dsn: &str) -> anyhow::Result<Client> {
+        Self::from_parsed_dsn(crate::dsn::Dsn::parse(dsn, false)?).await
+    }
+
+    async fn from_parsed_dsn(dsn: crate::dsn::Dsn) -> anyhow::Result<Client> {
+        let crate::dsn::Dsn {
+            config,
+            credentials,
+            timeout,
+            tls_insecure,
+            queries_path,
+        } = dsn;
+        let client = if tls_insecure {
+            Self::from_config_accepting_invalid_certs(config)?
+        } else {
+            Self::from_config(config).await?
+        };
+        match client {
+            #[cfg(any(
+                feature = "reqwest_backend",
+                feature = "workers_backend",
+                feature = "spin_backend",
+                feature = "tower"
+            ))]
+            Self::Http(mut h) => {
+                if let Some((username, password)) = credentials {
+                    h = h.clone_with_credentials(&username, &password);
+                }
+                if let Some(timeout) = timeout {
+                    h = h.with_timeout(timeout);
+                }
+                if let Some(path) = queries_path {
+                    h = h.with_queries_path(&path);
+                }
+                Ok(Self::Http(h))
+            }
+            other => {
+                if credentials.is_some() || timeout.is_some() || queries_path.is_some() {
+                    anyhow::bail!(
+                        "Credentials, timeout and queries_path in DSNs are only supported by remote HTTP clients"
+                    );
+                }
+                Ok(other)
+            }
+        }
+    }
+
+    #[cfg(feature = "reqwest_backend")]
+    fn from_config_accepting_invalid_certs(mut config: Config) -> anyhow::Result<Client> {
+        config.url = resolve_libsql_scheme(config.url);
+        if !matches!(config.url.scheme(), "http" | "https") {
+            anyhow::bail!("tls_insecure in DSNs is only supported by remote HTTP clients");
+        }
+        let inner = crate::http::InnerClient::Reqwest(
+            crate::reqwest::HttpClient::new().with_accept_invalid_certs(true),
+        );
+        Ok(Client::Http(crate::http::Client::from_config(
+            inner, config,
+        )?))
+    }
+
+    #[cfg(not(feature = "reqwest_backend"))]
+    fn from_config_accepting_invalid_certs(_config: Config) -> anyhow::Result<Client> {
+        anyhow::bail!("tls_insecure in DSNs is only supported by the reqwest backend")
+    }
+
+    /// Establishes a database client based on [Config] struct and checks that it can
+    /// run queries, by sending a cheap `SELECT 1` right away.
+    ///
+    /// [`Client::from_config()`] does not contact remote databases until the first query,
+    /// so invalid credentials only come to light later. This function fails with
+    /// [`Error::Auth`](crate::Error::Auth) instead if the server rejects the credentials.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f() -> anyhow::Result<()> {
+    /// # use libsql_client::Config;
+    /// let config = Config::new("https://example.com/db")?.with_auth_token("secret");
+    /// let db = libsql_client::Client::connect_and_verify(config).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn connect_and_verify(config: Config) -> anyhow::Result<Client> {
+        let client = Self::from_config(config).await?;
+        if let Err(e) = client.execute("SELECT 1").await {
+            return Err(match e.downcast_ref::<crate::Error>() {
+                Some(crate::Error::Http {
+                    status: 401 | 403,
+                    body,
+                }) => crate::Error::Auth {
+                    message: body.clone(),
+                }
+                .into(),
+                _ => e,
+            });
+        }
+        Ok(client)
+    }
+
+    /// Establishes a database client based on environment variables
+    ///
+    /// # Env
+    /// * `LIBSQL_CLIENT_URL` - URL of the database endpoint - e.g. a https:// endpoint for remote connections
+    ///   (with specified credentials) or local file:/// path for a local database
+    /// * (optional) `LIBSQL_CLIENT_TOKEN` - authentication token for the database. Skip if your database
+    ///   does not require authentication
+    /// *
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn run() {
+    /// # use libsql_client::Config;
+    /// # std::env::set_var("LIBSQL_CLIENT_URL", "file:////tmp/example.db");
+    /// let db = libsql_client::Client::from_env().await.unwrap();
+    /// # }
+    /// ```
+    pub async fn from_env() -> anyhow::Result<Client> {
+        let url = std::env::var("LIBSQL_CLIENT_URL").map_err(|_| {
+            anyhow::anyhow!("LIBSQL_CLIENT_URL variable should point to your libSQL/sqld database")
+        })?;
+        let auth_token = std::env::var("LIBSQL_CLIENT_TOKEN").ok();
+        Self::from_config(Config {
+            url: url::Url::parse(&url)?,
+            auth_token,
+        })
+        .await
+    }
+
+    /// Stops sending requests to a server after repeated failures,
+    /// see [`crate::circuit_breaker`] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f() -> anyhow::Result<()> {
+    /// # use libsql_client::Config;
+    /// use libsql_client::circuit_breaker::CircuitBreakerConfig;
+    ///
+    /// let config = Config::new("https://example.com/db")?;
+    /// let db = libsql_client::Client::from_config(config)
+    ///     .await?
+    ///     .with_circuit_breaker(CircuitBreakerConfig::default());
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(any(
+        feature = "reqwest_backend",
+        feature = "workers_backend",
+        feature = "spin_backend",
+        feature = "tower"
+    ))]
+    pub fn with_circuit_breaker(
+        self,
+        config: crate::circuit_breaker::CircuitBreakerConfig,
+    ) -> Client {
+        match self {
+            Self::Http(h) => Self::Http(h.with_circuit_breaker(config)),
+            other => other,
+        }
+    }
+
+    /// Fails requests whose response body is larger than `bytes`.
+    ///
+    /// See [`http::Client::with_max_response_size()`](crate::http::Client::with_max_response_size)
+    /// for details.
+    #[cfg(any(
+        feature = "reqwest_backend",
+        feature = "workers_backend",
+        feature = "spin_backend",
+        feature = "tower"
+    ))]
+    pub fn with_max_response_size(self, bytes: usize) -> Client {
+        match self {
+            Self::Http(h) => Self::Http(h.with_max_response_size(bytes)),
+            other => other,
+        }
+    }
+
+    /// Verifies that the results of batches come back in the order of their statements.
+    ///
+    /// See [`http::Client::with_strict_ordering()`](crate::http::Client::with_strict_ordering)
+    /// for details.
+    #[cfg(any(
+        feature = "reqwest_backend",
+        feature = "workers_backend",
+        feature = "spin_backend",
+        feature = "tower"
+    ))]
+    pub fn with_strict_ordering(self, enabled: bool) -> Client {
+        match self {
+            Self::Http(h) => Self::Http(h.with_strict_ordering(enabled)),
+            other => other,
+        }
+    }
+
+    /// Sends `id` as the `X-Request-Id` header of every request, for correlating
+    /// them with the logs of the server. By default, each request gets a fresh UUID.
+    ///
+    /// See [`http::Client::with_request_id()`](crate::http::Client::with_request_id) for details.
+    #[cfg(any(
+        feature = "reqwest_backend",
+        feature = "workers_backend",
+        feature = "spin_backend",
+        feature = "tower"
+    ))]
+    pub fn with_request_id(self, id: impl Into<String>) -> Client {
+        match self {
+            Self::Http(h) => Self::Http(h.with_request_id(id)),
+            other => other,
+        }
+    }
+
+    /// Fetches auth tokens from `provider`, refreshing them when they are about to expire
+    /// or rejected by the server.
+    ///
+    /// See [`http::Client::with_token_provider()`](crate::http::Client::with_token_provider) for details.
+    #[cfg(any(
+        feature = "reqwest_backend",
+        feature = "workers_backend",
+        feature = "spin_backend",
+        feature = "tower"
+    ))]
+    pub fn with_token_provider(self, provider: crate::http::TokenProvider) -> Client {
+        match self {
+            Self::Http(h) => Self::Http(h.with_token_provider(provider)),
+            other => other,
+        }
+    }
+
+    /// Sets an option in the `options` object of every request sent to sqld.
+    ///
+    /// See [`http::Client::with_sqld_option()`](crate::http::Client::with_sqld_option) for details.
+    #[cfg(any(
+        feature = "reqwest_backend",
+        feature = "workers_backend",
+        feature = "spin_backend",
+        feature = "tower"
+    ))]
+    pub fn with_sqld_option(
+        self,
+        key: impl Into<String>,
+        value: impl Into<serde_json::Value>,
+    ) -> Client {
+        match self {
+            Self::Http(h) => Self::Http(h.with_sqld_option(key, value)),
+            other => other,
+        }
+    }
+
+    /// Sets the busy timeout of SQLite, making statements wait up to `ms` milliseconds
+    /// for locks held by other connections instead of failing with `database is locked`.
+    ///
+    /// How the timeout is applied depends on the backend:
+    /// - local clients issue `PRAGMA busy_timeout` once, on their only connection,
+    /// - Hrana clients issue it on each stream they open,
+    /// - HTTP clients, which start a new session with each request, send it at the front
+    ///   of every request starting one, see
+    ///   [`http::Client::with_busy_timeout()`](crate::http::Client::with_busy_timeout).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f() -> anyhow::Result<()> {
+    /// let db = libsql_client::Client::in_memory()?.with_busy_timeout(5000).await?;
+    /// db.execute("SELECT 1").await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg_attr(
+        not(any(
+            feature = "local_backend",
+            feature = "reqwest_backend",
+            feature = "workers_backend",
+            feature = "spin_backend",
+            feature = "tower",
+            feature = "hrana_backend"
+        )),
+        allow(unused_variables)
+    )]
+    pub async fn with_busy_timeout(self, ms: u64) -> Result<Client> {
+        Ok(match self {
+            #[cfg(feature = "local_backend")]
+            Self::Local(l) => Self::Local(l.with_busy_timeout(ms)?),
+            #[cfg(any(
+                feature = "reqwest_backend",
+                feature = "workers_backend",
+                feature = "spin_backend",
+                feature = "tower"
+            ))]
+            Self::Http(h) => Self::Http(h.with_busy_timeout(ms)),
+            #[cfg(feature = "hrana_backend")]
+            Self::Hrana(h) => Self::Hrana(h.with_busy_timeout(ms)),
+            other => other,
+        })
+    }
+
+    /// Registers a logger called with every statement just before it is sent,
+    /// e.g. for audit trails. This includes the `BEGIN` and `END` wrapping [`Client::batch()`].
+    ///
+    /// The statement is passed with its parameters. To keep them out of logs, e.g. because
+    /// they contain personal data, see [`Client::with_redacted_query_logger()`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f() -> anyhow::Result<()> {
+    /// use std::sync::Arc;
+    ///
+    /// let db = libsql_client::Client::in_memory()?
+    ///     .with_query_logger(Arc::new(|stmt| println!("executing {stmt}")));
+    /// db.execute("SELECT 1").await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_query_logger(self, logger: QueryLogger) -> Client {
+        self.set_query_logger(logger, false)
+    }
+
+    /// Registers a logger like [`Client::with_query_logger()`], but only passes it
+    /// the SQL template of each statement, with all parameters removed.
+    pub fn with_redacted_query_logger(self, logger: QueryLogger) -> Client {
+        self.set_query_logger(logger, true)
+    }
+
+    /// Registers a hook called with every statement after it completes, along with
+    /// the time it took and a summary of its result, e.g. for logging slow queries.
+    ///
+    /// Unlike the [query logger](Client::with_query_logger()), which is called before
+    /// statements are sent, the hook sees their outcome. Only statements executed on their
+    /// own, including in interactive transactions, are traced: statements of batches are
+    /// not, since their individual durations are unknown. Parameters are passed as they are,
+    /// so take care not to log sensitive values.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f() -> anyhow::Result<()> {
+    /// use std::sync::Arc;
+    /// use std::time::Duration;
+    ///
+    /// let db = libsql_client::Client::in_memory()?.with_sql_trace_hook(Arc::new(|event| {
+    ///     if event.duration > Duration::from_millis(100) {
+    ///         println!("slow query: {} took {:?}", event.sql, event.duration);
+    ///     }
+    /// }));
+    /// db.execute("SELECT 1").await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_sql_trace_hook(mut self, hook: SqlTraceHook) -> Client {
+        if let Some(options) = self.options_mut() {
+            options.trace_hook = Some(hook);
+        }
+        self
+    }
+
+    /// Names this client, e.g. `primary` or `replica` for applications holding several of them.
+    ///
+    /// The name is attached as context to the errors of queries, and recorded as the `client`
+    /// field of the tracing spans of statements, to tell which client failed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f() -> anyhow::Result<()> {
+    /// let db = libsql_client::Client::in_memory()?.with_name("analytics");
+    /// let err = db.execute("SELECT * FROM missing").await.unwrap_err();
+    /// assert_eq!(err.to_string(), "Query on client `analytics` failed");
+    /// // The database error follows in the chain of causes
+    /// assert!(format!("{err:#}").ends_with("no such table: missing`"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_name(mut self, name: impl Into<String>) -> Client {
+        if let Some(options) = self.options_mut() {
+            options.name = Some(name.into());
+        }
+        self
+    }
+
+    /// Returns the name of this client, see [`Client::with_name()`]
+    pub fn name(&self) -> Option<&str> {
+        self.options().name.as_deref()
+    }
+
+    /// Sets values bound to named parameters which statements leave unbound,
+    /// e.g. a tenant id which all queries of a multi-tenant application filter on.
+    ///
+    /// Parameters are bound by position: SQLite numbers each distinct named parameter after
+    /// the parameters before it, see `sqlite3_bind_parameter_index()`. A default is therefore
+    /// bound to a parameter whose position is past the arguments of a statement, as long as all
+    /// parameters before it are bound too. Arguments passed explicitly take precedence.
+    /// Names include their prefix, so `:tenant` and `@tenant` are different parameters.
+    /// Calling this again replaces the previous defaults.
+    ///
+    /// # Arguments
+    /// * `params` - names of parameters, along with their default values
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f() -> anyhow::Result<()> {
+    /// use libsql_client::{args, Statement};
+    ///
+    /// let db = libsql_client::Client::in_memory()?.with_default_params(&[(":tenant", 1)]);
+    /// db.execute("create table orders(tenant integer, id integer)").await?;
+    /// db.execute(Statement::with_args(
+    ///     "select * from orders where id = ? and tenant = :tenant",
+    ///     args!(42),
+    /// ))
+    /// .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_default_params(
+        mut self,
+        params: &[(&str, impl Into<proto::Value> + Clone)],
+    ) -> Client {
+        if let Some(options) = self.options_mut() {
+            options.default_params = params
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.clone().into()))
+                .collect();
+        }
+        self
+    }
+
+    fn set_query_logger(mut self, logger: QueryLogger, redact_params: bool) -> Client {
+        if let Some(options) = self.options_mut() {
+            options.query_logger = Some(logger);
+            options.redact_params = redact_params;
+        }
+        self
+    }
+
+    fn options(&self) -> &Options {
+        static DEFAULT: Options = Options {
+            query_logger: None,
+            redact_params: false,
+            trace_hook: None,
+            name: None,
+            default_params: Vec::new(),
+        };
+        match self {
+            #[cfg(feature = "local_backend")]
+            Self::Local(l) => &l.options,
+            #[cfg(any(
+                feature = "reqwest_backend",
+                feature = "workers_backend",
+                feature = "spin_backend",
+                feature = "tower"
+            ))]
+            Self::Http(h) => &h.options,
+            #[cfg(feature = "hrana_backend")]
+            Self::Hrana(h) => &h.options,
+            _ => &DEFAULT,
+        }
+    }
+
+    fn options_mut(&mut self) -> Option<&mut Options> {
+        match self {
+            #[cfg(feature = "local_backend")]
+            Self::Local(l) => Some(&mut l.options),
+            #[cfg(any(
+                feature = "reqwest_backend",
+                feature = "workers_backend",
+                feature = "spin_backend",
+                feature = "tower"
+            ))]
+            Self::Http(h) => Some(&mut h.options),
+            #[cfg(feature = "hrana_backend")]
+            Self::Hrana(h) => Some(&mut h.options),
+            _ => None,
+        }
+    }
+
+    /// Establishes a database client from the secrets of a Cloudflare Workers environment
+    ///
+    /// Authentication is token-only: `LIBSQL_CLIENT_URL` and `LIBSQL_CLIENT_TOKEN` are both
+    /// required, and a missing token fails right away instead of falling back to credentials
+    /// or to an unauthenticated client.
+    #[cfg(feature = "workers_backend")]
+    pub fn from_workers_env(env: &worker::Env) -> anyhow::Result<Client> {
+        let url = env
+            .secret("LIBSQL_CLIENT_URL")
+            .map_err(|e| anyhow::anyhow!("Missing secret LIBSQL_CLIENT_URL: {e}"))?
+            .to_string();
+        let token = env
+            .secret("LIBSQL_CLIENT_TOKEN")
+            .map_err(|e| {
+                anyhow::anyhow!(
+                    "Missing secret LIBSQL_CLIENT_TOKEN, token authentication is required: {e}"
+                )
+            })?
+            .to_string();
+        let config = Config {
+            url: url::Url::parse(&url)?,
+            auth_token: Some(token),
+        };
+        let inner = crate::http::InnerClient::Workers(crate::workers::HttpClient::new());
+        Ok(Client::Http(crate::http::Client::from_config(
+            inner, config,
+        )?))
+    }
+}
+
+pub mod sync {}
+impl SyncClient {
+    /// Creates an in-memory database
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f() {
+    /// # use libsql_client::Config;
+    /// let db = libsql_client::SyncClient::in_memory().unwrap();
+    /// # }
+    /// ```
+    #[cfg(feature = "local_backend")]
+    pub fn in_memory() -> anyhow::Result<Self> {
+        Ok(Self {
+            inner: Client::in_memory()?,
+        })
+    }
+
+    /// Establishes a database client based on [Config] struct
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn f() {
+    /// # use libsql_client::Config;
+    /// let config = Config { url: url::Url::parse("file:////tmp/example.db").unwrap(), auth_token: None };
+    /// let db = libsql_client::SyncClient::from_config(config).unwrap();
+    /// # }
+    /// ```
+    pub fn from_config(config: Config) -> Result<Self> {
+        Ok(Self {
+            inner: futures::executor::block_on(Client::from_config(config))?,
+        })
+    }
+
+    /// Establishes a database client based on environment variables
+    ///
+    /// # Env
+    /// * `LIBSQL_CLIENT_URL` - URL of the database endpoint - e.g. a https:// endpoint for remote connections
+    ///   (with specified credentials) or local file:/// path for a local database
+    /// * (optional) `LIBSQL_CLIENT_TOKEN` - authentication token for the database. Skip if your database
+    ///   does not require authentication
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn run() {
+    /// # use libsql_client::Config;
+    /// # std::env::set_var("LIBSQL_CLIENT_URL", "file:////tmp/example.db");
+    /// let db = libsql_client::SyncClient::from_env().unwrap();
+    /// # }
+    /// ```
+    pub fn from_env() -> Result<Self> {
+        Ok(Self {
+            inner: futures::executor::block_on(Client::from_env())?,
+        })
+    }
+
+    /// Establishes a database client from a DSN.
+    ///
+    /// See [`Client::from_dsn()`] for details.
+    pub fn from_dsn(dsn: &str) -> Result<Self> {
+        Ok(Self {
+            inner: futures::executor::block_on(Client::from_dsn(dsn))?,
+        })
+    }
+
+    /// Establishes a database client from a DSN, ignoring unknown options.
+    ///
+    /// See [`Client::from_dsn_lenient()`] for details.
+    pub fn from_dsn_lenient(dsn: &str) -> Result<Self> {
+        Ok(Self {
+            inner: futures::executor::block_on(Client::from_dsn_lenient(dsn))?,
+        })
+    }
+
+    #[cfg(feature = "workers_backend")]
+    pub fn from_workers_env(env: &worker::Env) -> Result<Self> {
+        Ok(Self {
+            inner: Client::from_workers_env(env)?,
+        })
+    }
+
+    /// Executes a batch of independent SQL statements.
+    ///
+    /// For a version in which statements execute transactionally, see [`SyncClient::batch()`]
+    /// # Arguments
+    /// * `stmts` - SQL statements
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn run() {
+    /// # use libsql_client::Config;
+    /// let db = libsql_client::SyncClient::in_memory().unwrap();
+    /// # db.execute("create table foo(bar text)").unwrap();
+    /// let res = db.raw_batch([
+    ///   "select * from foo",
+    ///   "insert into foo(bar) values ('bar')"
+    /// ]).unwrap();
+    /// # }
+    /// ```
+    pub fn raw_batch(
+        &self,
+        stmts: impl IntoIterator<Item = impl Into<Statement> + Send> + Send,
+    ) -> Result<BatchResult> {
+        futures::executor::block_on(self.inner.raw_batch(stmts))
+    }
+
+    /// Executes a batch of independent SQL statements, only checking whether all of them succeeded.
+    ///
+    /// See [`Client::batch_ok()`] for details.
+    pub fn batch_ok(
+        &self,
+        stmts: impl IntoIterator<Item = impl Into<Statement> + Send> + Send,
+    ) -> Result<()> {
+        futures::executor::block_on(self.inner.batch_ok(stmts))
+    }
+
+    /// Transactionally executes a batch of SQL statements.
+    ///
+    /// For a version in which statements can fail or succeed independently, see [`SyncClient::raw_batch()`]
+    /// # Arguments
+    /// * `stmts` - SQL statements
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn run() {
+    /// # use libsql_client::Config;
+    /// let db = libsql_client::SyncClient::in_memory().unwrap();
+    /// # db.execute("create table foo(bar text)").unwrap();
+    /// let res = db.batch([
+    ///   "select * from foo",
+    ///   "insert into foo(bar) values ('bar')"
+    /// ]).unwrap();
+    /// assert_eq!(res.len(), 2)
+    /// # }
+    /// ```
+    pub fn batch<I: IntoIterator<Item = impl Into<Statement> + Send> + Send>(
+        &self,
+        stmts: I,
+    ) -> Result<Vec<ResultSet>>
+    where
+        <I as std::iter::IntoIterator>::IntoIter: std::marker::Send,
+    {
+        futures::executor::block_on(self.inner.batch(stmts))
+    }
+
+    /// Checks the health of the server without running a query.
+    ///
+    /// See [`Client::server_health()`] for details.
+    #[cfg(any(
+        feature = "reqwest_backend",
+        feature = "workers_backend",
+        feature = "spin_backend",
+        feature = "tower"
+    ))]
+    pub fn server_health(&self) -> Result<crate::http::HealthStatus> {
+        futures::executor::block_on(self.inner.server_health())
+    }
+
+    /// Transactionally executes a batch of SQL statements with credentials for this call only.
+    ///
+    /// See [`Client::batch_with_auth()`] for details.
+    #[cfg(any(
+        feature = "reqwest_backend",
+        feature = "workers_backend",
+        feature = "spin_backend",
+        feature = "tower"
+    ))]
+    pub fn batch_with_auth<I: IntoIterator<Item = impl Into<Statement> + Send> + Send>(
+        &self,
+        stmts: I,
+        auth: crate::http::Auth,
+    ) -> Result<Vec<ResultSet>>
+    where
+        <I as std::iter::IntoIterator>::IntoIter: std::marker::Send,
+    {
+        futures::executor::block_on(self.inner.batch_with_auth(stmts, auth))
+    }
+
+    /// Transactionally executes a batch of SQL statements with extensions for middleware.
+    ///
+    /// See [`Client::batch_with_extensions()`] for details.
+    #[cfg(any(
+        feature = "reqwest_backend",
+        feature = "workers_backend",
+        feature = "spin_backend",
+        feature = "tower"
+    ))]
+    pub fn batch_with_extensions<I: IntoIterator<Item = impl Into<Statement> + Send> + Send>(
+        &self,
+        stmts: I,
+        extensions: crate::http::Extensions,
+    ) -> Result<Vec<ResultSet>>
+    where
+        <I as std::iter::IntoIterator>::IntoIter: std::marker::Send,
+    {
+        futures::executor::block_on(self.inner.batch_with_extensions(stmts, extensions))
+    }
+
+    /// Executes a batch of SQL statements atomically, in a single transaction.
+    ///
+    /// See [`Client::batch_transaction()`] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn run() {
+    /// let db = libsql_client::SyncClient::in_memory().unwrap();
+    /// # db.execute("create table foo(bar text)").unwrap();
+    /// let res = db.batch_transaction([
+    ///   "insert into foo(bar) values ('bar')",
+    ///   "select * from foo",
+    /// ]).unwrap();
+    /// assert_eq!(res.len(), 2)
+    /// # }
+    /// ```
+    pub fn batch_transaction(
+        &self,
+        stmts: impl IntoIterator<Item = impl Into<Statement>>,
+    ) -> Result<Vec<ResultSet>> {
+        futures::executor::block_on(self.inner.batch_transaction(stmts))
+    }
+
+    /// Executes a batch of SQL statements atomically, in a transaction started in the given mode.
+    ///
+    /// See [`Client::batch_transaction_with()`] for details.
+    pub fn batch_transaction_with(
+        &self,
+        stmts: impl IntoIterator<Item = impl Into<Statement>>,
+        mode: TxMode,
+    ) -> Result<Vec<ResultSet>> {
+        futures::executor::block_on(self.inner.batch_transaction_with(stmts, mode))
+    }
+
+    /// Executes a batch of SQL statements atomically, returning the total number of rows changed.
+    ///
+    /// See [`Client::batch_transaction_counts()`] for details.
+    pub fn batch_transaction_counts(
+        &self,
+        stmts: impl IntoIterator<Item = impl Into<Statement>>,
+    ) -> Result<u64> {
+        futures::executor::block_on(self.inner.batch_transaction_counts(stmts))
+    }
+
+    /// Executes the same SQL statement once per set of parameters, transactionally.
+    ///
+    /// See [`Client::execute_many()`] for details.
+    pub fn execute_many(
+        &self,
+        sql: &str,
+        param_sets: &[impl AsRef<[proto::Value]>],
+    ) -> Result<Vec<ResultSet>> {
+        futures::executor::block_on(self.inner.execute_many(sql, param_sets))
+    }
+
+    /// Inserts `rows` into `table`, transactionally, returning the number of inserted rows.
+    ///
+    /// See [`Client::insert_all()`] for details.
+    pub fn insert_all<T: serde::Serialize>(
+        &self,
+        table: &str,
+        rows: impl IntoIterator<Item = T>,
+    ) -> Result<u64> {
+        futures::executor::block_on(self.inner.insert_all(table, rows))
+    }
+
+    /// Transactionally executes a batch of queries and merges their results into one [ResultSet].
+    ///
+    /// See [`Client::batch_merge()`] for details.
+    pub fn batch_merge<I: IntoIterator<Item = impl Into<Statement> + Send> + Send>(
+        &self,
+        stmts: I,
+    ) -> Result<ResultSet>
+    where
+        <I as IntoIterator>::IntoIter: Send,
+    {
+        futures::executor::block_on(self.inner.batch_merge(stmts))
+    }
+
+    /// Executes a single SQL statement
+    ///
+    /// # Arguments
+    /// * `stmt` - SQL statements
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn run() {
+    /// # use libsql_client::Config;
+    /// let db = libsql_client::SyncClient::in_memory().unwrap();
+    /// # db.execute("create table foo(bar text)").unwrap();
+    /// db.execute("select * from foo").unwrap();
+    /// # }
+    /// ```
+    pub fn execute(&self, stmt: impl Into<Statement> + Send) -> Result<ResultSet> {
+        futures::executor::block_on(self.inner.execute(stmt))
+    }
+
+    /// Executes an `INSERT` statement and returns the rowid of the inserted row
+    ///
+    /// See [`Client::execute_returning_id()`] for details.
+    pub fn execute_returning_id(&self, stmt: impl Into<Statement> + Send) -> Result<i64> {
+        futures::executor::block_on(self.inner.execute_returning_id(stmt))
+    }
+
+    /// Executes a statement with a `RETURNING` clause, deserializing the returned rows
+    ///
+    /// See [`Client::execute_returning()`] for details.
+    #[cfg(feature = "mapping_names_to_values_in_rows")]
+    pub fn execute_returning<T: serde::de::DeserializeOwned>(
+        &self,
+        stmt: impl Into<Statement> + Send,
+    ) -> Result<Vec<T>> {
+        futures::executor::block_on(self.inner.execute_returning(stmt))
+    }
+
+    /// Checks whether `table` has rows matching `where_clause`
+    ///
+    /// See [`Client::exists()`] for details.
+    pub fn exists(
+        &self,
+        table: &str,
+        where_clause: &str,
+        params: &[impl Into<proto::Value> + Clone],
+    ) -> Result<bool> {
+        futures::executor::block_on(self.inner.exists(table, where_clause, params))
+    }
+
+    /// Counts the rows of `table`, or only those matching `where_clause` if given
+    ///
+    /// See [`Client::count()`] for details.
+    pub fn count(
+        &self,
+        table: &str,
+        where_clause: Option<&str>,
+        params: &[impl Into<proto::Value> + Clone],
+    ) -> Result<u64> {
+        futures::executor::block_on(self.inner.count(table, where_clause, params))
+    }
+
+    /// Rebuilds the database file with `VACUUM`
+    ///
+    /// See [`Client::vacuum()`] for details.
+    pub fn vacuum(&self) -> Result<()> {
+        futures::executor::block_on(self.inner.vacuum())
+    }
+
+    /// Gathers statistics for the query planner with `ANALYZE`
+    ///
+    /// See [`Client::analyze()`] for details.
+    pub fn analyze(&self) -> Result<()> {
+        futures::executor::block_on(self.inner.analyze())
+    }
+
+    /// Reads `PRAGMA user_version`
+    ///
+    /// See [`Client::user_version()`] for details.
+    pub fn user_version(&self) -> Result<i32> {
+        futures::executor::block_on(self.inner.user_version())
+    }
+
+    /// Sets `PRAGMA user_version`
+    ///
+    /// See [`Client::set_user_version()`] for details.
+    pub fn set_user_version(&self, version: i32) -> Result<()> {
+        futures::executor::block_on(self.inner.set_user_version(version))
+    }
+
+    /// Reads `PRAGMA schema_version`
+    ///
+    /// See [`Client::schema_version()`] for details.
+    pub fn schema_version(&self) -> Result<i64> {
+        futures::executor::block_on(self.inner.schema_version())
+    }
+
+    /// Names this client, to tell which client failed in errors and traces.
+    ///
+    /// See [`Client::with_name()`] for details.
+    pub fn with_name(self, name: impl Into<String>) -> SyncClient {
+        Self {
+            inner: self.inner.with_name(name),
+        }
+    }
+
+    /// Sets values bound to named parameters which statements leave unbound.
+    ///
+    /// See [`Client::with_default_params()`] for details.
+    pub fn with_default_params(
+        self,
+        params: &[(&str, impl Into<proto::Value> + Clone)],
+    ) -> SyncClient {
+        Self {
+            inner: self.inner.with_default_params(params),
+        }
+    }
+
+    /// Sets the busy timeout of SQLite.
+    ///
+    /// See [`Client::with_busy_timeout()`] for details.
+    pub fn with_busy_timeout(self, ms: u64) -> Result<SyncClient> {
+        Ok(Self {
+            inner: futures::executor::block_on(self.inner.with_busy_timeout(ms))?,
+        })
+    }
+
+    /// Registers a logger called with every statement just before it is sent.
+    ///
+    /// See [`Client::with_query_logger()`] for details.
+    pub fn with_query_logger(self, logger: QueryLogger) -> SyncClient {
+        Self {
+            inner: self.inner.with_query_logger(logger),
+        }
+    }
+
+    /// Registers a logger which only receives the SQL template of each statement.
+    ///
+    /// See [`Client::with_redacted_query_logger()`] for details.
+    pub fn with_redacted_query_logger(self, logger: QueryLogger) -> SyncClient {
+        Self {
+            inner: self.inner.with_redacted_query_logger(logger),
+        }
+    }
+
+    /// Registers a hook called with every statement after it completes.
+    ///
+    /// See [`Client::with_sql_trace_hook()`] for details.
+    pub fn with_sql_trace_hook(self, hook: SqlTraceHook) -> SyncClient {
+        Self {
+            inner: self.inner.with_sql_trace_hook(hook),
+        }
+    }
+
+    /// Creates an interactive transaction
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn run() {
+    /// # use libsql_client::Config;
+    /// let db = libsql_client::SyncClient::in_memory().unwrap();
+    /// # db.execute("create table foo(bar text)").unwrap();
+    /// let tx = db.transaction().unwrap();
+    /// tx.execute("select * from foo").unwrap();
+    /// tx.commit();
+    /// # }
+    /// ```
+    pub fn transaction(&self) -> Result<SyncTransaction<'_>> {
+        let id = TRANSACTION_IDS.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        SyncTransaction::new(self, id)
+    }
+
+    pub(crate) fn execute_in_transaction(&self, tx_id: u64, stmt: Statement) -> Result<ResultSet> {
+        futures::executor::block_on(self.inner.execute_in_transaction(tx_id, stmt))
+    }
+
+    pub(crate) fn commit_transaction(&self, tx_id: u64) -> Result<()> {
+        futures::executor::block_on(self.inner.commit_transaction(tx_id))
+    }
+
+    pub(crate) fn rollback_transaction(&self, tx_id: u64) -> Result<()> {
+        futures::executor::block_on(self.inner.rollback_transaction(tx_id))
     }
 }
 
@@ -605,3 +2747,1131 @@ impl Config {
         self
     }
 }
+
+/// Translates Turso-style `libsql://` URLs to the transport of an enabled backend:
+/// `https://` if an HTTP backend is enabled, `wss://` for Hrana over WebSockets otherwise.
+/// URLs with other schemes are returned unchanged.
+fn resolve_libsql_scheme(url: url::Url) -> url::Url {
+    if url.scheme() != "libsql" {
+        return url;
+    }
+    let transport = if cfg!(any(
+        feature = "reqwest_backend",
+        feature = "workers_backend",
+        feature = "spin_backend"
+    )) || !cfg!(feature = "hrana_backend")
+    {
+        "https"
+    } else {
+        "wss"
+    };
+    // We cannot use url::Url::set_scheme() because it prevents changing the scheme to http...
+    // Safe to unwrap, because only the scheme changes
+    url::Url::parse(&url.as_str().replacen("libsql", transport, 1)).unwrap()
+}
+
+/// Returns true if `stmt` begins or ends a transaction or a savepoint
+fn controls_transaction(stmt: &Statement) -> bool {
+    let mut parser = Parser::new(stmt.sql.as_bytes());
+    while let Ok(Some(cmd)) = parser.next() {
+        if let Cmd::Stmt(
+            Stmt::Begin(..)
+            | Stmt::Commit(..)
+            | Stmt::Rollback { .. }
+            | Stmt::Savepoint(..)
+            | Stmt::Release(..),
+        ) = cmd
+        {
+            return true;
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "reqwest_backend")]
+    use crate::mock_server::{MockResponse, MockServer};
+
+    #[cfg(feature = "local_backend")]
+    #[tokio::test]
+    async fn test_paginate() {
+        use futures::TryStreamExt;
+
+        let db = Client::in_memory().unwrap();
+        db.execute("CREATE TABLE t(id INTEGER)").await.unwrap();
+        for id in 0..25 {
+            db.execute(Statement::with_args("INSERT INTO t VALUES (?)", &[id]))
+                .await
+                .unwrap();
+        }
+        let pages: Vec<Vec<Row>> = db
+            .paginate("SELECT id FROM t ORDER BY id", 10)
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(pages.iter().map(Vec::len).collect::<Vec<_>>(), [10, 10, 5]);
+        let ids: Vec<i64> = pages
+            .iter()
+            .flatten()
+            .map(|row| row.try_get(0).unwrap())
+            .collect();
+        assert_eq!(ids, (0..25).collect::<Vec<_>>());
+
+        let filtered: Vec<Vec<Row>> = db
+            .paginate(
+                Statement::with_args("SELECT id FROM t WHERE id >= ? ORDER BY id", &[20]),
+                10,
+            )
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(filtered[0].len(), 5);
+    }
+
+    #[cfg(feature = "local_backend")]
+    #[tokio::test]
+    async fn test_execute_many() {
+        use crate::args;
+
+        let db = Client::in_memory().unwrap();
+        db.execute("CREATE TABLE users(id INTEGER, name TEXT)")
+            .await
+            .unwrap();
+        db.execute("INSERT INTO users VALUES (1, 'a'), (2, 'b'), (3, 'c'), (4, 'd')")
+            .await
+            .unwrap();
+        let results = db
+            .execute_many(
+                "UPDATE users SET name = ? WHERE id = ?",
+                &[args!("alice", 1), args!("bob", 2), args!("nobody", 42)],
+            )
+            .await
+            .unwrap();
+        let affected: Vec<u64> = results.iter().map(|rs| rs.rows_affected).collect();
+        assert_eq!(affected, [1, 1, 0]);
+        let rs = db
+            .execute("SELECT name FROM users ORDER BY id")
+            .await
+            .unwrap();
+        let names: Vec<&str> = rs.rows.iter().map(|r| r.try_get(0).unwrap()).collect();
+        assert_eq!(names, ["alice", "bob", "c", "d"]);
+
+        // A mismatched set fails the whole call before anything is sent
+        let err = db
+            .execute_many(
+                "UPDATE users SET name = ? WHERE id = ?",
+                &[args!("carol", 3), args!("dave")],
+            )
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Parameter set 1"), "{err}");
+        let rs = db
+            .execute("SELECT name FROM users WHERE id = 3")
+            .await
+            .unwrap();
+        assert_eq!(rs.rows[0].try_get::<&str>(0).unwrap(), "c");
+    }
+
+    #[cfg(feature = "local_backend")]
+    #[tokio::test]
+    async fn test_exists() {
+        let db = Client::in_memory().unwrap();
+        db.execute("CREATE TABLE users(id INTEGER, email TEXT)")
+            .await
+            .unwrap();
+        db.execute("INSERT INTO users VALUES (1, 'a@b.c'), (2, 'd@e.f')")
+            .await
+            .unwrap();
+        assert!(db.exists("users", "email = ?", &["a@b.c"]).await.unwrap());
+        assert!(!db.exists("users", "email = ?", &["x@y.z"]).await.unwrap());
+        assert!(db.exists("users", "id > ?", &[1]).await.unwrap());
+        assert!(!db.exists("users", "id > ?", &[2]).await.unwrap());
+        // Placeholders and parameters must match
+        assert!(db.exists("users", "id = ?", &[1, 2]).await.is_err());
+        assert!(db.exists("users", " ", &[] as &[i64]).await.is_err());
+    }
+
+    #[cfg(feature = "local_backend")]
+    #[tokio::test]
+    async fn test_count() {
+        let db = Client::in_memory().unwrap();
+        db.execute("CREATE TABLE users(id INTEGER, age INTEGER)")
+            .await
+            .unwrap();
+        let none: &[i64] = &[];
+        assert_eq!(db.count("users", None, none).await.unwrap(), 0);
+        db.execute("INSERT INTO users VALUES (1, 12), (2, 30), (3, 45)")
+            .await
+            .unwrap();
+        assert_eq!(db.count("users", None, none).await.unwrap(), 3);
+        assert_eq!(db.count("users", Some("age >= ?"), &[18]).await.unwrap(), 2);
+        assert_eq!(
+            db.count("users", Some("age BETWEEN ? AND ?"), &[40, 50])
+                .await
+                .unwrap(),
+            1
+        );
+        // Placeholders and parameters must match
+        assert!(db.count("users", None, &[1]).await.is_err());
+        assert!(db.count("users", Some("age > ?"), none).await.is_err());
+    }
+
+    #[cfg(feature = "reqwest_backend")]
+    #[tokio::test]
+    async fn test_from_dsn() {
+        let server = MockServer::start(|_| MockResponse::empty_execute()).await;
+        let db = Client::from_dsn(&format!(
+            "{}?authToken=abc&timeout=5&queries_path=custom/pipeline",
+            server.url
+        ))
+        .await
+        .unwrap();
+        db.execute("SELECT 1").await.unwrap();
+        let request = &server.requests()[0];
+        assert_eq!(request.path, "/custom/pipeline");
+        assert_eq!(request.header("authorization"), Some("Bearer abc"));
+
+        let url = server.url.replace("http://", "http://user:pass@");
+        let db = Client::from_dsn(&format!("{url}?tls_insecure=true"))
+            .await
+            .unwrap();
+        assert!(format!("{db:?}").contains("accept_invalid_certs: true"));
+        db.execute("SELECT 1").await.unwrap();
+        let request = &server.requests()[1];
+        assert_eq!(request.path, "/v2/pipeline");
+        assert_eq!(request.header("authorization"), Some("Basic dXNlcjpwYXNz"));
+
+        let dsn = format!("{}?authToken=abc&pool_size=4", server.url);
+        assert!(Client::from_dsn(&dsn).await.is_err());
+        assert!(Client::from_dsn_lenient(&dsn).await.is_ok());
+    }
+
+    #[cfg(feature = "local_backend")]
+    #[tokio::test]
+    async fn test_from_dsn_local() {
+        let path = std::env::temp_dir().join(format!(
+            "libsql-client-dsn-{}-{}.db",
+            std::process::id(),
+            rand::random::<u32>()
+        ));
+        let dsn = url::Url::from_file_path(&path).unwrap();
+        let db = Client::from_dsn(dsn.as_str()).await.unwrap();
+        db.execute("SELECT 1").await.unwrap();
+        assert!(Client::from_dsn(&format!("{dsn}?timeout=5")).await.is_err());
+
+        drop(db);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(feature = "local_backend")]
+    #[tokio::test]
+    async fn test_with_name() {
+        let db = Client::in_memory().unwrap().with_name("replica");
+        assert_eq!(db.name(), Some("replica"));
+        let err = db
+            .execute(Statement::new("SELECT * FROM missing").with_label("load users"))
+            .await
+            .unwrap_err();
+        assert!(
+            format!("{err:#}")
+                .starts_with("Query on client `replica` failed: Statement `load users` failed: "),
+            "{err:#}"
+        );
+        assert!(
+            format!("{err:#}").contains("no such table: missing"),
+            "{err:#}"
+        );
+        assert!(matches!(
+            err.downcast_ref::<crate::Error>(),
+            Some(crate::Error::Sql { .. })
+        ));
+
+        let err = db.batch(["SELECT * FROM missing"]).await.unwrap_err();
+        assert!(format!("{err:#}").contains("replica"), "{err:#}");
+        assert_eq!(Client::in_memory().unwrap().name(), None);
+    }
+
+    #[cfg(feature = "local_backend")]
+    #[tokio::test]
+    async fn test_insert_or_ignore() {
+        use crate::Value;
+
+        let db = Client::in_memory().unwrap();
+        db.execute("CREATE TABLE t(id INTEGER PRIMARY KEY, name TEXT NOT NULL)")
+            .await
+            .unwrap();
+        let insert = |id: i64, name: Value| {
+            Statement::insert_or_ignore("t", &["id", "name"], &[Value::from(id), name]).unwrap()
+        };
+
+        let rs = db.execute(insert(1, "one".into())).await.unwrap();
+        assert_eq!(rs.rows_affected, 1);
+        // Duplicates are skipped, but other constraints are still enforced
+        let rs = db.execute(insert(1, "uno".into())).await.unwrap();
+        assert_eq!(rs.rows_affected, 0);
+        assert!(db.execute(insert(2, Value::Null)).await.is_err());
+    }
+
+    #[cfg(feature = "local_backend")]
+    #[tokio::test]
+    async fn test_batch_ok() {
+        let db = Client::in_memory().unwrap();
+        db.batch_ok([
+            "CREATE TABLE t(id INTEGER UNIQUE)",
+            "INSERT INTO t VALUES (1)",
+        ])
+        .await
+        .unwrap();
+
+        let err = db
+            .batch_ok([
+                Statement::new("INSERT INTO t VALUES (2)"),
+                Statement::new("INSERT INTO t VALUES (1)").with_label("insert duplicate"),
+                Statement::new("INSERT INTO t VALUES (3)"),
+            ])
+            .await
+            .unwrap_err();
+        assert!(
+            format!("{err:#}").starts_with("Statement `insert duplicate` failed: "),
+            "{err:#}"
+        );
+        match err.downcast_ref::<crate::Error>() {
+            Some(crate::Error::Sql { message, code }) => {
+                assert!(message.contains("UNIQUE constraint failed"), "{message}");
+                assert_eq!(*code, Some(2067));
+            }
+            other => panic!("unexpected error {other:?}"),
+        }
+        // The statements before the failed one were rolled back
+        let rs = db.execute("SELECT count(*) FROM t").await.unwrap();
+        assert_eq!(rs.rows[0].try_get::<i64>(0).unwrap(), 1);
+    }
+
+    #[cfg(feature = "local_backend")]
+    #[tokio::test]
+    async fn test_busy_timeout() {
+        let db = Client::in_memory()
+            .unwrap()
+            .with_busy_timeout(2500)
+            .await
+            .unwrap();
+        let rs = db.execute("PRAGMA busy_timeout").await.unwrap();
+        assert_eq!(rs.rows[0].try_get::<i64>(0).unwrap(), 2500);
+        db.execute("CREATE TABLE t(id)").await.unwrap();
+        let rs = db.execute("SELECT count(*) FROM t").await.unwrap();
+        assert_eq!(rs.rows[0].try_get::<i64>(0).unwrap(), 0);
+    }
+
+    #[cfg(feature = "local_backend")]
+    #[tokio::test]
+    async fn test_maintenance() {
+        let path = std::env::temp_dir().join(format!(
+            "libsql-client-maintenance-{}-{}.db",
+            std::process::id(),
+            rand::random::<u32>()
+        ));
+        let db = Client::Local(crate::local::Client::new(path.to_str().unwrap()).unwrap());
+        db.execute("CREATE TABLE t(id INTEGER PRIMARY KEY, v TEXT)")
+            .await
+            .unwrap();
+        db.execute("CREATE INDEX t_v ON t(v)").await.unwrap();
+        db.insert_all(
+            "t",
+            &(0..500)
+                .map(|i| serde_json::json!({"id": i, "v": format!("value {i}")}))
+                .collect::<Vec<_>>(),
+        )
+        .await
+        .unwrap();
+        db.execute("DELETE FROM t WHERE id % 2 = 0").await.unwrap();
+
+        db.vacuum().await.unwrap();
+        db.analyze().await.unwrap();
+        let rs = db
+            .execute("SELECT count(*) FROM sqlite_stat1 WHERE tbl = 't'")
+            .await
+            .unwrap();
+        assert!(rs.rows[0].try_get::<i64>(0).unwrap() > 0);
+        let rs = db.execute("SELECT count(*) FROM t").await.unwrap();
+        assert_eq!(rs.rows[0].try_get::<i64>(0).unwrap(), 250);
+
+        drop(db);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(all(feature = "local_backend", feature = "mapping_names_to_values_in_rows"))]
+    #[tokio::test]
+    async fn test_execute_returning() {
+        #[derive(Debug, PartialEq, serde::Deserialize)]
+        struct User {
+            id: i64,
+            name: String,
+        }
+
+        let db = Client::in_memory().unwrap();
+        db.execute("CREATE TABLE users(id INTEGER PRIMARY KEY, name TEXT, age INTEGER)")
+            .await
+            .unwrap();
+        let users: Vec<User> = db
+            .execute_returning(
+                "INSERT INTO users(name, age) VALUES ('alice', 42), ('bob', 69) RETURNING id, name",
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            users,
+            [
+                User {
+                    id: 1,
+                    name: "alice".into()
+                },
+                User {
+                    id: 2,
+                    name: "bob".into()
+                }
+            ]
+        );
+
+        let deleted: Vec<User> = db
+            .execute_returning("DELETE FROM users WHERE age > 50 RETURNING id, name")
+            .await
+            .unwrap();
+        assert_eq!(deleted.len(), 1);
+        assert_eq!(deleted[0].name, "bob");
+
+        // A RETURNING clause without the fields of the struct fails to deserialize
+        assert!(db
+            .execute_returning::<User>("UPDATE users SET age = 43 RETURNING id")
+            .await
+            .is_err());
+    }
+
+    #[cfg(feature = "local_backend")]
+    #[tokio::test]
+    async fn test_user_and_schema_versions() {
+        let db = Client::in_memory().unwrap();
+        assert_eq!(db.user_version().await.unwrap(), 0);
+        let schema_version = db.schema_version().await.unwrap();
+
+        db.set_user_version(3).await.unwrap();
+        assert_eq!(db.user_version().await.unwrap(), 3);
+        // Setting the user version does not change the schema
+        assert_eq!(db.schema_version().await.unwrap(), schema_version);
+
+        db.execute("CREATE TABLE t(x)").await.unwrap();
+        assert!(db.schema_version().await.unwrap() > schema_version);
+        assert_eq!(db.user_version().await.unwrap(), 3);
+
+        let db = SyncClient::in_memory().unwrap();
+        db.set_user_version(-7).unwrap();
+        assert_eq!(db.user_version().unwrap(), -7);
+        db.set_user_version(i32::MAX).unwrap();
+        assert_eq!(db.user_version().unwrap(), i32::MAX);
+    }
+
+    #[cfg(feature = "local_backend")]
+    #[test]
+    fn test_paginate_validation() {
+        let db = Client::in_memory().unwrap();
+        assert!(db.paginate("SELECT id FROM t", 10).is_err());
+        assert!(db
+            .paginate("SELECT id FROM t ORDER BY id LIMIT 5", 10)
+            .is_err());
+        assert!(db.paginate("DELETE FROM t", 10).is_err());
+        assert!(db.paginate("SELECT id FROM t ORDER BY id", 0).is_err());
+    }
+
+    #[cfg(feature = "local_backend")]
+    #[tokio::test]
+    async fn test_batch_transaction() {
+        let db = Client::in_memory().unwrap();
+        db.execute("CREATE TABLE t(id INTEGER PRIMARY KEY)")
+            .await
+            .unwrap();
+
+        let err = db
+            .batch_transaction([
+                "INSERT INTO t VALUES (1)",
+                "INSERT INTO t VALUES (1)",
+                "INSERT INTO t VALUES (2)",
+            ])
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("UNIQUE"), "{err}");
+        let rs = db.execute("SELECT COUNT(*) FROM t").await.unwrap();
+        assert_eq!(rs.rows[0].try_get::<i64>(0).unwrap(), 0);
+
+        let results = db
+            .batch_transaction(["INSERT INTO t VALUES (1)", "SELECT id FROM t"])
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[1].rows.len(), 1);
+    }
+
+    #[cfg(feature = "local_backend")]
+    #[tokio::test]
+    async fn test_batch_transaction_counts() {
+        let db = Client::in_memory().unwrap();
+        db.batch([
+            "CREATE TABLE t(id INTEGER PRIMARY KEY, x INTEGER)",
+            "INSERT INTO t VALUES (1, 0), (2, 0), (3, 0), (4, 10)",
+        ])
+        .await
+        .unwrap();
+        let changed = db
+            .batch_transaction_counts([
+                "UPDATE t SET x = 1 WHERE id = 1",
+                "UPDATE t SET x = 2 WHERE id IN (2, 3)",
+                "UPDATE t SET x = 3 WHERE x = 10",
+            ])
+            .await
+            .unwrap();
+        assert_eq!(changed, 4);
+
+        // Failures roll back the whole transaction
+        assert!(db
+            .batch_transaction_counts(["UPDATE t SET x = 4", "UPDATE t SET id = 1"])
+            .await
+            .is_err());
+        assert_eq!(
+            db.count("t", Some("x = 4"), &[] as &[i64]).await.unwrap(),
+            0
+        );
+    }
+
+    #[cfg(feature = "local_backend")]
+    #[tokio::test]
+    async fn test_batch_transaction_with_mode() {
+        use std::sync::Mutex;
+
+        let logged = Arc::new(Mutex::new(Vec::new()));
+        let sink = logged.clone();
+        let db = Client::in_memory()
+            .unwrap()
+            .with_query_logger(Arc::new(move |stmt| {
+                sink.lock().unwrap().push(stmt.sql.clone())
+            }));
+        db.execute("CREATE TABLE t(id INTEGER PRIMARY KEY)")
+            .await
+            .unwrap();
+
+        for (mode, begin) in [
+            (TxMode::Deferred, "BEGIN"),
+            (TxMode::Immediate, "BEGIN IMMEDIATE"),
+            (TxMode::Exclusive, "BEGIN EXCLUSIVE"),
+        ] {
+            logged.lock().unwrap().clear();
+            db.batch_transaction_with(["INSERT INTO t VALUES (NULL)"], mode)
+                .await
+                .unwrap();
+            assert_eq!(
+                *logged.lock().unwrap(),
+                [begin, "INSERT INTO t VALUES (NULL)"]
+            );
+        }
+        let rs = db.execute("SELECT COUNT(*) FROM t").await.unwrap();
+        assert_eq!(rs.rows[0].try_get::<i64>(0).unwrap(), 3);
+    }
+
+    #[cfg(feature = "local_backend")]
+    #[tokio::test]
+    async fn test_batch_stream() {
+        use futures::StreamExt;
+
+        let db = Client::in_memory().unwrap();
+        db.execute("CREATE TABLE t(id INTEGER PRIMARY KEY)")
+            .await
+            .unwrap();
+        let stmts = futures::stream::iter(0..1000)
+            .map(|id| Statement::with_args("INSERT INTO t VALUES (?)", &[id]));
+        assert_eq!(db.batch_stream(stmts, 64).await.unwrap(), 1000);
+        let rs = db.execute("SELECT COUNT(*) FROM t").await.unwrap();
+        assert_eq!(rs.rows[0].try_get::<i64>(0).unwrap(), 1000);
+
+        assert!(db
+            .batch_stream(futures::stream::iter(["SELECT 1"]), 0)
+            .await
+            .is_err());
+
+        // A failing chunk is rolled back, the chunks before it stay committed
+        db.execute("DELETE FROM t").await.unwrap();
+        let stmts = futures::stream::iter([0, 1, 2, 3, 4, 5, 4, 6])
+            .map(|id| Statement::with_args("INSERT INTO t VALUES (?)", &[id]));
+        assert!(db.batch_stream(stmts, 4).await.is_err());
+        let rs = db.execute("SELECT COUNT(*) FROM t").await.unwrap();
+        assert_eq!(rs.rows[0].try_get::<i64>(0).unwrap(), 4);
+    }
+
+    #[cfg(feature = "local_backend")]
+    #[tokio::test]
+    async fn test_batch_merge() {
+        let db = Client::in_memory().unwrap();
+        db.batch([
+            "CREATE TABLE a(id INTEGER, name TEXT)",
+            "CREATE TABLE b(id INTEGER, name TEXT)",
+            "INSERT INTO a VALUES (1, 'one'), (2, 'two')",
+            "INSERT INTO b VALUES (3, 'three'), (4, 'four')",
+        ])
+        .await
+        .unwrap();
+
+        let rs = db
+            .batch_merge(["SELECT id, name FROM a", "SELECT id, name FROM b"])
+            .await
+            .unwrap();
+        assert_eq!(rs.columns, ["id", "name"]);
+        let ids: Vec<i64> = rs.rows.iter().map(|row| row.try_get(0).unwrap()).collect();
+        assert_eq!(ids, [1, 2, 3, 4]);
+
+        let err = db
+            .batch_merge(["SELECT id, name FROM a", "SELECT name, id FROM b"])
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("different columns"), "{err}");
+
+        let err = db
+            .batch_merge([
+                "SELECT id, name FROM a",
+                "SELECT name AS id, id AS name FROM b",
+            ])
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Cannot merge results with different types in column `id`: statement 0 returned Integer, statement 1 returned Text"
+        );
+        // NULLs match any type
+        let rs = db
+            .batch_merge(["SELECT NULL AS id", "SELECT 'x' AS id", "SELECT NULL AS id"])
+            .await
+            .unwrap();
+        assert_eq!(rs.rows.len(), 3);
+    }
+
+    #[cfg(feature = "local_backend")]
+    #[tokio::test]
+    async fn test_insert_all() {
+        #[derive(serde::Serialize)]
+        struct Item {
+            id: i64,
+            name: String,
+            price: Option<f64>,
+        }
+
+        let db = Client::in_memory().unwrap();
+        db.execute("CREATE TABLE items(id INTEGER PRIMARY KEY, name TEXT, price REAL)")
+            .await
+            .unwrap();
+        // Enough rows to be split into several statements
+        let items: Vec<Item> = (0..1000)
+            .map(|id| Item {
+                id,
+                name: format!("item {id}"),
+                price: (id % 2 == 0).then_some(id as f64 / 2.0),
+            })
+            .collect();
+        assert_eq!(db.insert_all("items", items).await.unwrap(), 1000);
+        let rs = db
+            .execute("SELECT COUNT(*), COUNT(price) FROM items")
+            .await
+            .unwrap();
+        assert_eq!(rs.rows[0].try_get::<i64>(0).unwrap(), 1000);
+        assert_eq!(rs.rows[0].try_get::<i64>(1).unwrap(), 500);
+
+        assert_eq!(db.insert_all("items", Vec::<Item>::new()).await.unwrap(), 0);
+        let inconsistent = [
+            std::collections::HashMap::from([("id", 2000)]),
+            std::collections::HashMap::from([("price", 1)]),
+        ];
+        let err = db.insert_all("items", inconsistent).await.unwrap_err();
+        assert!(err.to_string().contains("same fields"), "{err}");
+    }
+
+    #[test]
+    fn test_resolve_libsql_scheme() {
+        let resolve = |url: &str| resolve_libsql_scheme(url::Url::parse(url).unwrap()).to_string();
+        assert_eq!(resolve("libsql://db.turso.io"), "https://db.turso.io/");
+        assert_eq!(
+            resolve("libsql://db-org.turso.io:8080/path?tls=1"),
+            "https://db-org.turso.io:8080/path?tls=1"
+        );
+        assert_eq!(resolve("wss://db.turso.io"), "wss://db.turso.io/");
+        assert_eq!(resolve("file:///tmp/db"), "file:///tmp/db");
+    }
+
+    #[cfg(feature = "local_backend")]
+    #[tokio::test]
+    async fn test_execute_returning_id() {
+        let db = Client::in_memory().unwrap();
+        db.execute("CREATE TABLE t(id INTEGER PRIMARY KEY, name TEXT UNIQUE)")
+            .await
+            .unwrap();
+        let insert = |name: &str| Statement::with_args("INSERT INTO t(name) VALUES (?)", &[name]);
+        let first = db.execute_returning_id(insert("a")).await.unwrap();
+        let second = db.execute_returning_id(insert("b")).await.unwrap();
+        assert_eq!(second, first + 1);
+
+        let err = db
+            .execute_returning_id(Statement::with_args(
+                "INSERT OR IGNORE INTO t(name) VALUES (?)",
+                &["a"],
+            ))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("did not insert"), "{err}");
+        assert!(db.execute_returning_id("SELECT 1").await.is_err());
+    }
+
+    #[cfg(feature = "local_backend")]
+    #[tokio::test]
+    async fn test_dollar_params() {
+        let db = Client::in_memory().unwrap();
+        let rs = db
+            .execute(Statement::with_args(
+                "SELECT $2 AS second, $1 AS first, $2 AS again",
+                &["a", "b"],
+            ))
+            .await
+            .unwrap();
+        let row = &rs.rows[0];
+        assert_eq!(row.try_get::<&str>(0).unwrap(), "b");
+        assert_eq!(row.try_get::<&str>(1).unwrap(), "a");
+        assert_eq!(row.try_get::<&str>(2).unwrap(), "b");
+    }
+
+    #[cfg(feature = "local_backend")]
+    #[tokio::test]
+    async fn test_returning() {
+        let db = Client::in_memory().unwrap();
+        db.batch([
+            "CREATE TABLE t(id INTEGER PRIMARY KEY, n INTEGER)",
+            "INSERT INTO t VALUES (1, 1), (2, 1)",
+        ])
+        .await
+        .unwrap();
+
+        let rs = db
+            .execute("UPDATE t SET n = 2 WHERE id = 1 RETURNING n")
+            .await
+            .unwrap();
+        assert_eq!(rs.columns, ["n"]);
+        assert_eq!(rs.rows.len(), 1);
+        assert_eq!(rs.rows[0].try_get::<i64>(0).unwrap(), 2);
+        assert_eq!(rs.rows_affected, 1);
+
+        let rs = db
+            .execute("DELETE FROM t WHERE id = 1 RETURNING *")
+            .await
+            .unwrap();
+        assert_eq!(rs.columns, ["id", "n"]);
+        assert_eq!(rs.rows.len(), 1);
+        assert_eq!(rs.rows[0].try_get::<i64>(0).unwrap(), 1);
+        assert_eq!(rs.rows[0].try_get::<i64>(1).unwrap(), 2);
+        assert_eq!(rs.rows_affected, 1);
+    }
+
+    #[cfg(feature = "local_backend")]
+    #[tokio::test]
+    async fn test_labeled_statement_errors() {
+        let db = Client::in_memory().unwrap();
+        db.batch([
+            "CREATE TABLE t(id INTEGER PRIMARY KEY)",
+            "INSERT INTO t VALUES (0)",
+        ])
+        .await
+        .unwrap();
+        let failing = || Statement::new("INSERT INTO t VALUES (0)").with_label("insert duplicate");
+        let expected = "Statement `insert duplicate` failed: ";
+
+        let err = db.execute(failing()).await.unwrap_err();
+        assert!(format!("{err:#}").starts_with(expected), "{err:#}");
+        assert!(
+            format!("{err:#}").contains("UNIQUE constraint failed"),
+            "{err:#}"
+        );
+
+        let err = db
+            .batch_transaction([Statement::new("SELECT 1").with_label("select"), failing()])
+            .await
+            .unwrap_err();
+        assert!(format!("{err:#}").starts_with(expected), "{err:#}");
+        assert!(
+            format!("{err:#}").contains("UNIQUE constraint failed"),
+            "{err:#}"
+        );
+
+        // Unlabeled statements keep their original error
+        let err = db.execute("INSERT INTO t VALUES (0)").await.unwrap_err();
+        assert!(!format!("{err:#}").contains("insert duplicate"), "{err:#}");
+
+        let err = db
+            .batch([
+                Statement::new("INSERT INTO t VALUES (1)").with_label("insert t"),
+                failing(),
+            ])
+            .await
+            .unwrap_err();
+        assert!(format!("{err:#}").starts_with(expected), "{err:#}");
+        assert!(
+            format!("{err:#}").contains("UNIQUE constraint failed"),
+            "{err:#}"
+        );
+    }
+
+    #[cfg(feature = "local_backend")]
+    #[tokio::test]
+    async fn test_default_params() {
+        let db = Client::in_memory()
+            .unwrap()
+            .with_default_params(&[(":tenant", 1)]);
+        db.batch([
+            "CREATE TABLE orders(tenant INTEGER, id INTEGER)",
+            "INSERT INTO orders VALUES (1, 10), (1, 11), (2, 20)",
+        ])
+        .await
+        .unwrap();
+        let count = |stmt: Statement| {
+            let db = &db;
+            async move {
+                let rs = db.execute(stmt).await.unwrap();
+                rs.rows[0].try_get::<i64>(0).unwrap()
+            }
+        };
+
+        // The default is bound when the statement leaves `:tenant` unbound
+        let sql = "SELECT COUNT(*) FROM orders WHERE tenant = :tenant";
+        assert_eq!(count(Statement::new(sql)).await, 2);
+        // Explicit arguments take precedence
+        assert_eq!(count(Statement::with_args(sql, &[2])).await, 1);
+        // Positional parameters come first
+        let sql = "SELECT COUNT(*) FROM orders WHERE id > ? AND tenant = :tenant";
+        assert_eq!(count(Statement::with_args(sql, &[10])).await, 1);
+        // Statements without the parameter are left alone
+        assert_eq!(
+            count(Statement::new("SELECT COUNT(*) FROM orders")).await,
+            3
+        );
+
+        let results = db
+            .batch(["SELECT id FROM orders WHERE tenant = :tenant ORDER BY id"])
+            .await
+            .unwrap();
+        assert_eq!(results[0].rows.len(), 2);
+    }
+
+    #[cfg(feature = "local_backend")]
+    #[tokio::test]
+    async fn test_query_logger() {
+        use std::sync::Mutex;
+
+        let logged = Arc::new(Mutex::new(Vec::new()));
+        let sink = logged.clone();
+        let db = Client::in_memory()
+            .unwrap()
+            .with_query_logger(Arc::new(move |stmt| {
+                sink.lock().unwrap().push(stmt.to_string())
+            }));
+        db.execute("CREATE TABLE t(x)").await.unwrap();
+        db.raw_batch([
+            Statement::with_args("INSERT INTO t VALUES (?)", &["secret"]),
+            Statement::new("SELECT x FROM t"),
+        ])
+        .await
+        .unwrap();
+        assert_eq!(
+            *logged.lock().unwrap(),
+            [
+                r#"{"sql": "CREATE TABLE t(x)", "args": []}"#,
+                r#"{"sql": "INSERT INTO t VALUES (?)", "args": ["secret"]}"#,
+                r#"{"sql": "SELECT x FROM t", "args": []}"#,
+            ]
+        );
+    }
+
+    #[cfg(feature = "local_backend")]
+    #[tokio::test]
+    async fn test_sql_trace_hook() {
+        use std::sync::Mutex;
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = events.clone();
+        let db = Client::in_memory()
+            .unwrap()
+            .with_sql_trace_hook(Arc::new(move |event| sink.lock().unwrap().push(event)));
+        db.execute("CREATE TABLE t(x)").await.unwrap();
+        db.execute(Statement::with_args(
+            "INSERT INTO t VALUES (?), (?)",
+            &[1, 2],
+        ))
+        .await
+        .unwrap();
+        db.execute("SELECT x FROM t").await.unwrap();
+        db.execute("SELECT * FROM missing").await.unwrap_err();
+        // Statements of batches are not traced
+        db.batch(["SELECT 1"]).await.unwrap();
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 4);
+        assert_eq!(events[1].sql, "INSERT INTO t VALUES (?), (?)");
+        assert_eq!(events[1].params.len(), 2);
+        assert_eq!(events[1].result.as_ref().unwrap().rows_affected, 2);
+        assert_eq!(events[2].sql, "SELECT x FROM t");
+        assert_eq!(events[2].result.as_ref().unwrap().rows, 2);
+        assert!(events[2].duration > std::time::Duration::ZERO);
+        let error = events[3].result.as_ref().unwrap_err();
+        assert!(error.contains("no such table"), "{error}");
+    }
+
+    #[cfg(feature = "local_backend")]
+    #[tokio::test]
+    async fn test_redacted_query_logger() {
+        use std::sync::Mutex;
+
+        let logged = Arc::new(Mutex::new(Vec::new()));
+        let sink = logged.clone();
+        let db = Client::in_memory()
+            .unwrap()
+            .with_redacted_query_logger(Arc::new(move |stmt| {
+                sink.lock().unwrap().push(stmt.to_string())
+            }));
+        db.execute(Statement::with_args("SELECT ?", &["secret"]))
+            .await
+            .unwrap();
+        assert_eq!(
+            *logged.lock().unwrap(),
+            [r#"{"sql": "SELECT ?", "args": []}"#]
+        );
+    }
+
+    #[cfg(feature = "local_backend")]
+    #[tokio::test]
+    async fn test_batch_transaction_rejects_nesting() {
+        let db = Client::in_memory().unwrap();
+        for stmt in [
+            "BEGIN",
+            "commit",
+            "END TRANSACTION",
+            "ROLLBACK",
+            "SAVEPOINT s",
+        ] {
+            assert!(
+                db.batch_transaction(["SELECT 1", stmt]).await.is_err(),
+                "{stmt}"
+            );
+        }
+        assert!(db.batch_transaction(["SELECT 'BEGIN'"]).await.is_ok());
+    }
+
+    #[cfg(feature = "reqwest_backend")]
+    #[tokio::test]
+    async fn test_batch_transaction_http_single_request() {
+        let server = MockServer::start(|req| {
+            let step_results = if req.body.contains("fail") {
+                r#"[{"cols":[],"rows":[],"affected_row_count":0,"last_insert_rowid":null},null,null,{"cols":[],"rows":[],"affected_row_count":0,"last_insert_rowid":null}],"step_errors":[null,{"message":"no such table: fail","code":"SQLITE_ERROR"},null,null]"#
+            } else {
+                r#"[{"cols":[],"rows":[],"affected_row_count":0,"last_insert_rowid":null},{"cols":[],"rows":[],"affected_row_count":1,"last_insert_rowid":null},{"cols":[],"rows":[],"affected_row_count":0,"last_insert_rowid":null},null],"step_errors":[null,null,null,null]"#
+            };
+            MockResponse::json(format!(
+                r#"{{"baton":null,"base_url":null,"results":[{{"type":"ok","response":{{"type":"batch","result":{{"step_results":{step_results}}}}}}},{{"type":"ok","response":{{"type":"close"}}}}]}}"#
+            ))
+        })
+        .await;
+        let db = Client::from_config(Config::new(server.url.as_str()).unwrap())
+            .await
+            .unwrap();
+
+        let results = db
+            .batch_transaction_with(["INSERT INTO t VALUES (1)"], TxMode::Immediate)
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].rows_affected, 1);
+        let err = db
+            .batch_transaction([Statement::new("INSERT INTO fail VALUES (1)").with_label("insert")])
+            .await
+            .unwrap_err();
+        assert!(
+            format!("{err:#}").contains("no such table: fail"),
+            "{err:#}"
+        );
+        assert!(matches!(
+            err.downcast_ref::<crate::Error>(),
+            Some(crate::Error::Sql { code: Some(1), .. })
+        ));
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        let body: serde_json::Value = serde_json::from_str(&requests[0].body).unwrap();
+        let steps = body["requests"][0]["batch"]["steps"].as_array().unwrap();
+        let steps: Vec<(&str, &serde_json::Value)> = steps
+            .iter()
+            .map(|step| (step["stmt"]["sql"].as_str().unwrap(), &step["condition"]))
+            .collect();
+        assert_eq!(
+            steps,
+            [
+                ("BEGIN IMMEDIATE", &serde_json::Value::Null),
+                (
+                    "INSERT INTO t VALUES (1)",
+                    &serde_json::json!({"type": "ok", "step": 0})
+                ),
+                ("COMMIT", &serde_json::json!({"type": "ok", "step": 1})),
+                (
+                    "ROLLBACK",
+                    &serde_json::json!({"type": "not", "cond": {"type": "ok", "step": 2}})
+                ),
+            ]
+        );
+    }
+
+    #[cfg(all(feature = "local_backend", feature = "reqwest_backend"))]
+    #[tokio::test]
+    async fn test_backend_selected_at_runtime() {
+        let server = MockServer::start(|_| {
+            MockResponse::json(
+                r#"{"baton":null,"base_url":null,"results":[{"type":"ok","response":{"type":"batch","result":{"step_results":[{"cols":[],"rows":[],"affected_row_count":0,"last_insert_rowid":null},{"cols":[{"name":"n"}],"rows":[[{"type":"integer","value":"1"}]],"affected_row_count":0,"last_insert_rowid":null},{"cols":[],"rows":[],"affected_row_count":0,"last_insert_rowid":null}],"step_errors":[null,null,null]}}},{"type":"ok","response":{"type":"close"}}]}"#,
+            )
+        })
+        .await;
+        let open = |remote: Option<&str>| -> Client {
+            match remote {
+                Some(url) => {
+                    let inner =
+                        crate::http::InnerClient::Reqwest(crate::reqwest::HttpClient::new());
+                    crate::http::Client::new(inner, url, "").into()
+                }
+                None => crate::local::Client::in_memory().unwrap().into(),
+            }
+        };
+
+        for remote in [Some(server.url.as_str()), None] {
+            let db = open(remote);
+            assert_eq!(matches!(db, Client::Http(_)), remote.is_some());
+            let results = db.batch(["SELECT 1 AS n"]).await.unwrap();
+            assert_eq!(results[0].rows[0].try_get::<i64>(0).unwrap(), 1);
+        }
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[cfg(feature = "reqwest_backend")]
+    #[tokio::test]
+    async fn test_batch_with_auth() {
+        let server = MockServer::start(|_| {
+            MockResponse::json(
+                r#"{"baton":null,"base_url":null,"results":[{"type":"ok","response":{"type":"batch","result":{"step_results":[{"cols":[],"rows":[],"affected_row_count":0,"last_insert_rowid":null},{"cols":[],"rows":[],"affected_row_count":0,"last_insert_rowid":null},{"cols":[],"rows":[],"affected_row_count":0,"last_insert_rowid":null}],"step_errors":[null,null,null]}}},{"type":"ok","response":{"type":"close"}}]}"#,
+            )
+        })
+        .await;
+        let db = Client::from_config(
+            Config::new(server.url.as_str())
+                .unwrap()
+                .with_auth_token("default"),
+        )
+        .await
+        .unwrap();
+        db.batch_with_auth(["SELECT 1"], crate::http::Auth::Token("user".into()))
+            .await
+            .unwrap();
+        db.batch(["SELECT 1"]).await.unwrap();
+        let auth = crate::http::Auth::Basic {
+            username: "user".into(),
+            password: "pass".into(),
+        };
+        assert_eq!(
+            format!("{auth:?}"),
+            r#"Basic { username: "user", password: "<redacted>" }"#
+        );
+        db.batch_with_auth(["SELECT 1"], auth).await.unwrap();
+
+        let headers: Vec<_> = server
+            .requests()
+            .iter()
+            .map(|r| r.header("authorization").unwrap().to_string())
+            .collect();
+        assert_eq!(
+            headers,
+            ["Bearer user", "Bearer default", "Basic dXNlcjpwYXNz"]
+        );
+    }
+
+    #[cfg(feature = "reqwest_backend")]
+    #[tokio::test]
+    async fn test_batch_until() {
+        use std::time::{Duration, Instant};
+
+        let server = MockServer::start(|_| {
+            MockResponse::json(
+                r#"{"baton":null,"base_url":null,"results":[{"type":"ok","response":{"type":"batch","result":{"step_results":[{"cols":[],"rows":[],"affected_row_count":0,"last_insert_rowid":null},{"cols":[],"rows":[],"affected_row_count":0,"last_insert_rowid":null},{"cols":[],"rows":[],"affected_row_count":0,"last_insert_rowid":null}],"step_errors":[null,null,null]}}},{"type":"ok","response":{"type":"close"}}]}"#,
+            )
+            .with_delay(Duration::from_millis(200))
+        })
+        .await;
+        let db = Client::from_config(Config::new(server.url.as_str()).unwrap())
+            .await
+            .unwrap();
+        let is_timeout = |e: &anyhow::Error| {
+            matches!(
+                e.downcast_ref::<crate::Error>(),
+                Some(crate::Error::Timeout)
+            )
+        };
+
+        // A deadline in the past fails without sending anything
+        let err = db
+            .batch_until(["SELECT 1"], Instant::now() - Duration::from_secs(1))
+            .await
+            .unwrap_err();
+        assert!(is_timeout(&err));
+        assert!(server.requests().is_empty());
+
+        // A deadline in the future allows the request
+        let results = db
+            .batch_until(["SELECT 1"], Instant::now() + Duration::from_secs(10))
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+
+        // The remaining time is applied as the request timeout
+        let err = db
+            .batch_until(["SELECT 1"], Instant::now() + Duration::from_millis(50))
+            .await
+            .unwrap_err();
+        assert!(is_timeout(&err));
+    }
+
+    #[cfg(feature = "reqwest_backend")]
+    #[tokio::test]
+    async fn test_connect_and_verify() {
+        let server = MockServer::start(|req| {
+            if req.header("authorization") == Some("Bearer valid") {
+                MockResponse::execute(
+                    r#"{"cols":[{"name":"1"}],"rows":[[{"type":"integer","value":"1"}]],"affected_row_count":0,"last_insert_rowid":null}"#,
+                )
+            } else {
+                MockResponse::status(401)
+            }
+        })
+        .await;
+
+        let config = Config::new(server.url.as_str())
+            .unwrap()
+            .with_auth_token("valid");
+        Client::connect_and_verify(config).await.unwrap();
+        assert_eq!(server.requests().len(), 1);
+
+        let config = Config::new(server.url.as_str())
+            .unwrap()
+            .with_auth_token("invalid");
+        let err = Client::connect_and_verify(config).await.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<crate::Error>(),
+            Some(crate::Error::Auth { .. })
+        ));
+    }
+}
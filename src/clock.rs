@@ -0,0 +1,88 @@
+//! `Clock` is the source of time of time-dependent logic, such as the
+//! [circuit breaker](crate::circuit_breaker), so that tests can control it.
+//!
+//! Times are [`Duration`]s since an arbitrary origin, fixed for each clock, rather than
+//! [`Instant`](std::time::Instant)s, which are not available on `wasm32-unknown-unknown`.
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// A source of the current time
+pub trait Clock: Send + Sync + std::fmt::Debug {
+    /// Returns the time elapsed since the origin of this clock
+    fn now(&self) -> Duration;
+}
+
+/// The real time. This is the default clock.
+///
+/// It is based on [`Instant`](std::time::Instant), except in Cloudflare Workers where it
+/// reads the JavaScript clock. On other `wasm32-unknown-unknown` runtimes, where `Instant`
+/// panics, a clock of your own must be supplied instead.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    #[cfg(all(
+        target_arch = "wasm32",
+        target_os = "unknown",
+        feature = "workers_backend"
+    ))]
+    fn now(&self) -> Duration {
+        Duration::from_millis(worker::Date::now().as_millis())
+    }
+
+    #[cfg(not(all(
+        target_arch = "wasm32",
+        target_os = "unknown",
+        feature = "workers_backend"
+    )))]
+    fn now(&self) -> Duration {
+        static ORIGIN: std::sync::OnceLock<std::time::Instant> = std::sync::OnceLock::new();
+        ORIGIN.get_or_init(std::time::Instant::now).elapsed()
+    }
+}
+
+/// A clock which only moves when told to, for testing time-dependent logic
+/// deterministically and without sleeping
+///
+/// # Examples
+///
+/// ```
+/// use libsql_client::clock::{Clock, ManualClock};
+/// use std::time::Duration;
+///
+/// let clock = ManualClock::new();
+/// let start = clock.now();
+/// clock.advance(Duration::from_secs(10));
+/// assert_eq!(clock.now() - start, Duration::from_secs(10));
+/// ```
+#[derive(Debug)]
+pub struct ManualClock {
+    elapsed: Mutex<Duration>,
+}
+
+impl ManualClock {
+    /// Creates a clock stopped at its origin
+    pub fn new() -> Self {
+        Self {
+            elapsed: Mutex::new(Duration::ZERO),
+        }
+    }
+
+    /// Moves the clock forward by `duration`
+    pub fn advance(&self, duration: Duration) {
+        *self.elapsed.lock().unwrap() += duration;
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Duration {
+        *self.elapsed.lock().unwrap()
+    }
+}
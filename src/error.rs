@@ -0,0 +1,187 @@
+//! `Error` lists the failures that callers may want to handle programmatically.
+//!
+//! Errors are still returned as [`anyhow::Error`]; use
+//! [`downcast_ref()`](anyhow::Error::downcast_ref) to check for a specific one.
+
+/// Error raised by the client itself, as opposed to errors reported by the database
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// The circuit breaker is open after repeated failures, so the request was not sent.
+    /// See [`CircuitBreakerConfig`](crate::circuit_breaker::CircuitBreakerConfig).
+    CircuitOpen { retry_after: std::time::Duration },
+    /// The server answered with a non-successful HTTP status
+    Http { status: u16, body: String },
+    /// The server rejected the credentials
+    Auth { message: String },
+    /// The request did not complete before its deadline
+    Timeout,
+    /// A statement failed. `code` is SQLite's extended result code, e.g. 2067 for
+    /// `SQLITE_CONSTRAINT_UNIQUE`, if known: the local backend always reports it,
+    /// the HTTP backends map it from the name reported by the server for common codes.
+    ///
+    /// Only statements executed on their own by these backends report this error,
+    /// other failures are reported by message only.
+    Sql { message: String, code: Option<i32> },
+    /// The response body exceeded the limit set with
+    /// [`http::Client::with_max_response_size()`](crate::http::Client::with_max_response_size)
+    ResponseTooLarge { limit: usize },
+    /// A statement other than a read was passed to a
+    /// [`ReadOnlyClient`](crate::read_only::ReadOnlyClient), so it was not sent
+    ReadOnlyViolation {
+        kind: crate::statement::StatementKind,
+    },
+    /// The result at `position` of a batch is for the statement at `index`, so something
+    /// between the client and the database reordered them. Only detected with
+    /// [`http::Client::with_strict_ordering()`](crate::http::Client::with_strict_ordering)
+    ResultsOutOfOrder { position: usize, index: usize },
+    /// A statement matching none of the templates of an
+    /// [`AllowlistClient`](crate::allowlist::AllowlistClient) was passed to it, so it was not sent
+    StatementNotAllowed { sql: String },
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::CircuitOpen { retry_after } => write!(
+                f,
+                "Circuit breaker is open after repeated failures, retry in {retry_after:?}"
+            ),
+            Error::Http { status, body } => write!(f, "{status}: {body}"),
+            Error::Auth { message } => write!(f, "Authentication failed: {message}"),
+            Error::Timeout => write!(f, "Request timed out"),
+            Error::Sql { message, .. } => write!(f, "{message}"),
+            Error::ResponseTooLarge { limit } => {
+                write!(f, "Response body exceeds the limit of {limit} bytes")
+            }
+            Error::ReadOnlyViolation { kind } => {
+                write!(
+                    f,
+                    "{kind:?} statements are not allowed on a read-only client"
+                )
+            }
+            Error::ResultsOutOfOrder { position, index } => write!(
+                f,
+                "Result at position {position} is for statement {index}, results were reordered"
+            ),
+            Error::StatementNotAllowed { sql } => {
+                write!(f, "Statement is not in the allowlist: {sql}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl Error {
+    /// Returns SQLite's extended result code of a failed statement, if known
+    pub fn sqlite_code(&self) -> Option<i32> {
+        match self {
+            Error::Sql { code, .. } => *code,
+            _ => None,
+        }
+    }
+
+    /// Checks whether a statement failed because it violated a constraint,
+    /// like `UNIQUE`, `NOT NULL` or a foreign key
+    ///
+    /// # Examples
+    /// ```
+    /// # fn f(err: anyhow::Error) {
+    /// let duplicate = err
+    ///     .downcast_ref::<libsql_client::Error>()
+    ///     .is_some_and(libsql_client::Error::is_constraint_violation);
+    /// # }
+    /// ```
+    pub fn is_constraint_violation(&self) -> bool {
+        const SQLITE_CONSTRAINT: i32 = 19;
+        // The primary result code is stored in the lowest byte of extended ones
+        self.sqlite_code()
+            .is_some_and(|code| code & 0xff == SQLITE_CONSTRAINT)
+    }
+}
+
+/// Maps the name of an SQLite result code, as reported by remote servers, to its number
+#[cfg(any(
+    feature = "reqwest_backend",
+    feature = "workers_backend",
+    feature = "spin_backend",
+    feature = "tower"
+))]
+pub(crate) fn sqlite_code_from_name(name: &str) -> Option<i32> {
+    let code = match name.strip_prefix("SQLITE_")? {
+        "ERROR" => 1,
+        "INTERNAL" => 2,
+        "PERM" => 3,
+        "ABORT" => 4,
+        "BUSY" => 5,
+        "LOCKED" => 6,
+        "NOMEM" => 7,
+        "READONLY" => 8,
+        "INTERRUPT" => 9,
+        "IOERR" => 10,
+        "CORRUPT" => 11,
+        "NOTFOUND" => 12,
+        "FULL" => 13,
+        "CANTOPEN" => 14,
+        "PROTOCOL" => 15,
+        "EMPTY" => 16,
+        "SCHEMA" => 17,
+        "TOOBIG" => 18,
+        "CONSTRAINT" => 19,
+        "MISMATCH" => 20,
+        "MISUSE" => 21,
+        "NOLFS" => 22,
+        "AUTH" => 23,
+        "FORMAT" => 24,
+        "RANGE" => 25,
+        "NOTADB" => 26,
+        "CONSTRAINT_CHECK" => 275,
+        "CONSTRAINT_COMMITHOOK" => 531,
+        "CONSTRAINT_FOREIGNKEY" => 787,
+        "CONSTRAINT_FUNCTION" => 1043,
+        "CONSTRAINT_NOTNULL" => 1299,
+        "CONSTRAINT_PRIMARYKEY" => 1555,
+        "CONSTRAINT_TRIGGER" => 1811,
+        "CONSTRAINT_UNIQUE" => 2067,
+        "CONSTRAINT_VTAB" => 2323,
+        "CONSTRAINT_ROWID" => 2579,
+        "CONSTRAINT_PINNED" => 2835,
+        "CONSTRAINT_DATATYPE" => 3091,
+        _ => return None,
+    };
+    Some(code)
+}
+
+/// Context attached to errors of HTTP requests, identifying the request in the logs of the server.
+/// The original error is its source, printed after it with `{:#}`.
+/// Retrieve it with `downcast_ref::<RequestId>()`.
+/// See [`http::Client::with_request_id()`](crate::http::Client::with_request_id).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestId {
+    id: String,
+}
+
+impl RequestId {
+    /// Attaches the id of the failed request to `error`
+    #[cfg(any(
+        feature = "reqwest_backend",
+        feature = "workers_backend",
+        feature = "spin_backend",
+        feature = "tower"
+    ))]
+    pub(crate) fn attach(error: anyhow::Error, id: String) -> anyhow::Error {
+        error.context(RequestId { id })
+    }
+
+    /// Returns the id of the request, sent in its `X-Request-Id` header
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+impl std::fmt::Display for RequestId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Request {} failed", self.id)
+    }
+}
@@ -0,0 +1,206 @@
+//! `FanoutClient` sends writes to several databases, e.g. for migrating between instances.
+
+use anyhow::Result;
+
+use crate::statement::{classify, StatementKind};
+use crate::{Client, ResultSet, Statement};
+
+/// How [FanoutClient] handles writes which fail on a backup database
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FailurePolicy {
+    /// Logs the failure as a warning and returns the result of the primary database
+    #[default]
+    BestEffort,
+    /// Returns the error of the first failing backup database if the write succeeded
+    /// on the primary one, in which case it stays committed there.
+    /// Errors of the primary database are returned first.
+    FailFast,
+}
+
+/// A [Client] wrapper sending writes to a primary database and to any number of backups,
+/// for dual writes or backfills while migrating between instances
+///
+/// Statements which are [reads](StatementKind::Read) only go to the primary database,
+/// everything else goes to all of them concurrently. Results always come from the primary
+/// database, and its errors are always returned. Failures of backups are handled according
+/// to the [FailurePolicy], which defaults to [`FailurePolicy::BestEffort`].
+///
+/// There is no coordination between the databases: a write which fails on one of them
+/// is not rolled back on the others.
+///
+/// # Examples
+///
+/// ```
+/// # async fn f() -> anyhow::Result<()> {
+/// use libsql_client::fanout::{FailurePolicy, FanoutClient};
+/// use libsql_client::Client;
+///
+/// let db = FanoutClient::new(vec![Client::in_memory()?, Client::in_memory()?])?
+///     .with_policy(FailurePolicy::FailFast);
+/// db.execute("CREATE TABLE users(id INTEGER)").await?;
+/// db.execute("SELECT * FROM users").await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct FanoutClient {
+    clients: Vec<Client>,
+    policy: FailurePolicy,
+}
+
+impl FanoutClient {
+    /// Wraps `clients`, the first of which is the primary database and the others backups.
+    ///
+    /// Will return an error if `clients` is empty.
+    pub fn new(clients: Vec<Client>) -> Result<Self> {
+        if clients.is_empty() {
+            anyhow::bail!("FanoutClient needs at least a primary client");
+        }
+        Ok(Self {
+            clients,
+            policy: FailurePolicy::default(),
+        })
+    }
+
+    /// Sets how failed writes to backup databases are handled
+    pub fn with_policy(mut self, policy: FailurePolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Returns the client of the primary database
+    pub fn primary(&self) -> &Client {
+        &self.clients[0]
+    }
+
+    /// Returns the clients of the backup databases
+    pub fn backups(&self) -> &[Client] {
+        &self.clients[1..]
+    }
+
+    /// Executes a single SQL statement on the primary database,
+    /// as well as on the backups unless it is a read
+    ///
+    /// # Arguments
+    /// * `stmt` - SQL statement
+    pub async fn execute(&self, stmt: impl Into<Statement> + Send) -> Result<ResultSet> {
+        let stmt: Statement = stmt.into();
+        if classify(&stmt.sql) == StatementKind::Read {
+            return self.primary().execute(stmt).await;
+        }
+        let results =
+            futures::future::join_all(self.clients.iter().map(|c| c.execute(stmt.clone()))).await;
+        self.primary_result(results)
+    }
+
+    /// Executes a batch of SQL statements in a transaction on the primary database,
+    /// as well as on the backups unless all of them are reads
+    ///
+    /// # Arguments
+    /// * `stmts` - SQL statements
+    pub async fn batch<I: IntoIterator<Item = impl Into<Statement> + Send> + Send>(
+        &self,
+        stmts: I,
+    ) -> Result<Vec<ResultSet>>
+    where
+        <I as IntoIterator>::IntoIter: Send,
+    {
+        let stmts: Vec<Statement> = stmts.into_iter().map(|s| s.into()).collect();
+        if stmts
+            .iter()
+            .all(|stmt| classify(&stmt.sql) == StatementKind::Read)
+        {
+            return self.primary().batch(stmts).await;
+        }
+        let results =
+            futures::future::join_all(self.clients.iter().map(|c| c.batch(stmts.clone()))).await;
+        self.primary_result(results)
+    }
+
+    fn primary_result<T>(&self, results: Vec<Result<T>>) -> Result<T> {
+        let mut results = results.into_iter();
+        let primary = results
+            .next()
+            .expect("FanoutClient without a primary client");
+        for (i, result) in results.enumerate() {
+            let Err(e) = result else {
+                continue;
+            };
+            match self.policy {
+                // Errors of the primary database take precedence
+                FailurePolicy::FailFast if primary.is_ok() => {
+                    return Err(e.context(format!(
+                        "Write to backup database {i} failed, after succeeding on the primary database"
+                    )));
+                }
+                _ => tracing::warn!("Write to backup database {i} failed: {e:#}"),
+            }
+        }
+        primary
+    }
+}
+
+#[cfg(all(test, feature = "reqwest_backend"))]
+mod tests {
+    use super::*;
+    use crate::mock_server::{MockResponse, MockServer};
+    use crate::Config;
+
+    #[tokio::test]
+    async fn test_fanout() {
+        let primary = MockServer::start(|req| {
+            if req.body.contains("DELETE") {
+                MockResponse::status(500)
+            } else {
+                MockResponse::empty_execute()
+            }
+        })
+        .await;
+        let backup = MockServer::start(|req| {
+            if req.body.contains("INSERT") || req.body.contains("DELETE") {
+                MockResponse::status(400)
+            } else {
+                MockResponse::empty_execute()
+            }
+        })
+        .await;
+        let connect = |url: &str| Client::from_config(Config::new(url).unwrap());
+        let db = FanoutClient::new(vec![
+            connect(&primary.url).await.unwrap(),
+            connect(&backup.url).await.unwrap(),
+        ])
+        .unwrap();
+
+        // Writes hit both databases
+        db.execute("CREATE TABLE t(x)").await.unwrap();
+        assert_eq!(primary.requests().len(), 1);
+        assert_eq!(backup.requests().len(), 1);
+
+        // Reads only hit the primary one
+        db.execute("SELECT x FROM t").await.unwrap();
+        assert_eq!(primary.requests().len(), 2);
+        assert_eq!(backup.requests().len(), 1);
+
+        // Failures of the backup are only logged by default
+        db.execute("INSERT INTO t VALUES (1)").await.unwrap();
+        assert_eq!(backup.requests().len(), 2);
+
+        let db = db.with_policy(FailurePolicy::FailFast);
+        let err = db.execute("INSERT INTO t VALUES (2)").await.unwrap_err();
+        assert!(
+            format!("{err:#}").starts_with(
+                "Write to backup database 0 failed, after succeeding on the primary database: "
+            ),
+            "{err:#}"
+        );
+        assert!(format!("{err:#}").contains("400"), "{err:#}");
+        assert_eq!(primary.requests().len(), 4);
+
+        // The error of the primary database is returned even if a backup failed too
+        let err = db.execute("DELETE FROM t").await.unwrap_err();
+        assert!(!format!("{err:#}").contains("backup"), "{err:#}");
+        assert!(format!("{err:#}").contains("500"), "{err:#}");
+
+        assert!(FanoutClient::new(vec![]).is_err());
+    }
+}
@@ -0,0 +1,312 @@
+//! `FromValue` converts cells of a [Row](crate::Row) into Rust types.
+
+use crate::Value;
+
+/// A type which can be read from a [Value], as in [`Row::try_get()`](crate::Row::try_get)
+///
+/// Conversions fail with a message describing the mismatch, e.g. when reading
+/// a text cell as an integer or a negative integer as an unsigned one.
+pub trait FromValue<'a>: Sized {
+    fn from_value(value: &'a Value) -> Result<Self, String>;
+}
+
+fn mismatch(value: &Value, expected: &str) -> String {
+    format!("cannot transform {value:?} to {expected}")
+}
+
+macro_rules! impl_from_integer {
+    ($($typename: ty),*) => {
+        $(
+            impl FromValue<'_> for $typename {
+                fn from_value(value: &Value) -> Result<Self, String> {
+                    match value {
+                        Value::Integer { value } => (*value).try_into().map_err(|e| format!("{e}")),
+                        other => Err(mismatch(other, "Integer")),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_from_integer!(i8, i16, i32, i64, u8, u16, u32, usize, isize);
+
+/// Integers out of the range of `i64` cannot be stored as SQLite integers,
+/// so they are kept as text instead. These types can be read from both forms.
+macro_rules! impl_from_wide_integer {
+    ($($typename: ty),*) => {
+        $(
+            impl FromValue<'_> for $typename {
+                fn from_value(value: &Value) -> Result<Self, String> {
+                    match value {
+                        Value::Integer { value } => (*value).try_into().map_err(|e| format!("{e}")),
+                        Value::Text { value } => value
+                            .trim()
+                            .parse()
+                            .map_err(|e| format!("cannot parse {value:?} as {}: {e}", stringify!($typename))),
+                        other => Err(mismatch(other, "Integer")),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_from_wide_integer!(u64, i128);
+
+impl FromValue<'_> for f64 {
+    fn from_value(value: &Value) -> Result<Self, String> {
+        match value {
+            Value::Float { value } => Ok(*value),
+            other => Err(mismatch(other, "Float")),
+        }
+    }
+}
+
+/// Floats are stored as `f64`, and narrowed to the nearest `f32`, losing precision beyond
+/// about 7 significant digits. Finite values out of the range of `f32` are rejected instead
+/// of becoming infinite. `f32` values are bound with the `From<f32>` conversion of [Value],
+/// which widens them to `f64` exactly.
+impl FromValue<'_> for f32 {
+    fn from_value(value: &Value) -> Result<Self, String> {
+        let value = f64::from_value(value)?;
+        let narrowed = value as f32;
+        if narrowed.is_infinite() && value.is_finite() {
+            return Err(format!("{value} is out of the range of f32"));
+        }
+        Ok(narrowed)
+    }
+}
+
+impl<'a> FromValue<'a> for &'a str {
+    fn from_value(value: &'a Value) -> Result<Self, String> {
+        match value {
+            Value::Text { value } => Ok(value),
+            other => Err(mismatch(other, "Text")),
+        }
+    }
+}
+
+impl FromValue<'_> for String {
+    fn from_value(value: &Value) -> Result<Self, String> {
+        <&str>::from_value(value).map(str::to_string)
+    }
+}
+
+impl<'a> FromValue<'a> for &'a [u8] {
+    fn from_value(value: &'a Value) -> Result<Self, String> {
+        match value {
+            Value::Blob { value } => Ok(value),
+            other => Err(mismatch(other, "Blob")),
+        }
+    }
+}
+
+impl FromValue<'_> for Vec<u8> {
+    fn from_value(value: &Value) -> Result<Self, String> {
+        <&[u8]>::from_value(value).map(<[u8]>::to_vec)
+    }
+}
+
+impl<const N: usize> FromValue<'_> for [u8; N] {
+    fn from_value(value: &Value) -> Result<Self, String> {
+        let bytes = <&[u8]>::from_value(value)?;
+        bytes.try_into().map_err(|_| {
+            format!(
+                "cannot transform a blob of {} bytes to [u8; {N}]",
+                bytes.len()
+            )
+        })
+    }
+}
+
+/// Timestamps are stored as integer seconds since the Unix epoch, the format of SQLite's
+/// `unixepoch()` function. Negative integers are times before the epoch.
+///
+/// `Value` cannot implement `From<SystemTime>`, so bind timestamps with
+/// [`ser::timestamp_value()`](crate::ser::timestamp_value).
+impl FromValue<'_> for std::time::SystemTime {
+    fn from_value(value: &Value) -> Result<Self, String> {
+        let secs = i64::from_value(value)?;
+        let offset = std::time::Duration::from_secs(secs.unsigned_abs());
+        let time = if secs >= 0 {
+            std::time::UNIX_EPOCH.checked_add(offset)
+        } else {
+            std::time::UNIX_EPOCH.checked_sub(offset)
+        };
+        time.ok_or_else(|| format!("timestamp {secs} is out of the range of SystemTime"))
+    }
+}
+
+/// Dates are stored as `YYYY-MM-DD` text, the format of SQLite's `date()` function
+#[cfg(feature = "chrono")]
+impl FromValue<'_> for chrono::NaiveDate {
+    fn from_value(value: &Value) -> Result<Self, String> {
+        let text = <&str>::from_value(value)?;
+        chrono::NaiveDate::parse_from_str(text, "%Y-%m-%d")
+            .map_err(|e| format!("cannot parse {text:?} as a date: {e}"))
+    }
+}
+
+/// Decimals are stored as text, since SQLite has no decimal type and floats are inexact.
+/// Integers are accepted as well, but floats are not, to avoid silently losing precision:
+/// store decimals in columns without `NUMERIC`, `INTEGER` or `REAL` affinity,
+/// which would convert them to floats.
+///
+/// `Value` cannot implement `From<Decimal>`, so bind decimals as text with
+/// `decimal.to_string()`.
+#[cfg(feature = "decimal")]
+impl FromValue<'_> for rust_decimal::Decimal {
+    fn from_value(value: &Value) -> Result<Self, String> {
+        match value {
+            Value::Text { value } => value
+                .parse()
+                .map_err(|e| format!("cannot parse {value:?} as a decimal: {e}")),
+            Value::Integer { value } => Ok((*value).into()),
+            _ => Err(format!("cannot transform {value:?} to a decimal")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_system_time() {
+        use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+        let known = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let value = crate::ser::timestamp_value(known + Duration::from_millis(999));
+        assert!(matches!(
+            value,
+            Value::Integer {
+                value: 1_700_000_000
+            }
+        ));
+        assert_eq!(SystemTime::from_value(&value), Ok(known));
+
+        let before_epoch = UNIX_EPOCH - Duration::from_millis(1500);
+        let value = crate::ser::timestamp_value(before_epoch);
+        assert!(matches!(value, Value::Integer { value: -2 }));
+        assert_eq!(
+            SystemTime::from_value(&value),
+            Ok(UNIX_EPOCH - Duration::from_secs(2))
+        );
+
+        assert!(SystemTime::from_value(&Value::Text {
+            value: "2023-11-14".into()
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn test_wide_integers() {
+        let max = Value::Text {
+            value: "18446744073709551615".to_string(),
+        };
+        assert_eq!(u64::from_value(&max), Ok(u64::MAX));
+        assert_eq!(i128::from_value(&max), Ok(u64::MAX as i128));
+        assert!(i64::from_value(&max).is_err());
+
+        let below_min = Value::Text {
+            value: "-9223372036854775809".to_string(),
+        };
+        assert_eq!(i128::from_value(&below_min), Ok(i64::MIN as i128 - 1));
+        assert!(u64::from_value(&below_min).is_err());
+
+        let integer = Value::Integer { value: 42 };
+        assert_eq!(u64::from_value(&integer), Ok(42));
+        assert_eq!(i128::from_value(&integer), Ok(42));
+        assert!(u64::from_value(&Value::Integer { value: -1 }).is_err());
+        assert!(u64::from_value(&Value::Text {
+            value: "forty-two".to_string()
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn test_mismatch() {
+        let text = Value::Text {
+            value: "1".to_string(),
+        };
+        assert_eq!(
+            i64::from_value(&text),
+            Err("cannot transform Text { value: \"1\" } to Integer".to_string())
+        );
+        assert_eq!(<&str>::from_value(&text), Ok("1"));
+        assert!(f64::from_value(&text).is_err());
+        assert!(<&[u8]>::from_value(&text).is_err());
+    }
+
+    #[test]
+    fn test_f32() {
+        let value = Value::from(0.1f32);
+        assert!(matches!(value, Value::Float { value } if value == f64::from(0.1f32)));
+        assert_eq!(f32::from_value(&value), Ok(0.1));
+        assert_eq!(f32::from_value(&Value::Float { value: 0.1 }), Ok(0.1));
+        assert_eq!(
+            f32::from_value(&Value::Float {
+                value: f64::NEG_INFINITY
+            }),
+            Ok(f32::NEG_INFINITY)
+        );
+        assert_eq!(
+            f32::from_value(&Value::Float { value: 1e39 }),
+            Err("1000000000000000000000000000000000000000 is out of the range of f32".to_string())
+        );
+        assert!(f32::from_value(&Value::Integer { value: 1 }).is_err());
+    }
+
+    #[test]
+    fn test_fixed_size_blobs() {
+        let blob = Value::Blob {
+            value: (0..16).collect(),
+        };
+        let expected: [u8; 16] = std::array::from_fn(|i| i as u8);
+        assert_eq!(<[u8; 16]>::from_value(&blob), Ok(expected));
+        assert_eq!(
+            <[u8; 8]>::from_value(&blob),
+            Err("cannot transform a blob of 16 bytes to [u8; 8]".to_string())
+        );
+        assert!(<[u8; 16]>::from_value(&Value::Null).is_err());
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_dates() {
+        let date = Value::Text {
+            value: "2023-06-30".to_string(),
+        };
+        assert_eq!(
+            chrono::NaiveDate::from_value(&date),
+            Ok(chrono::NaiveDate::from_ymd_opt(2023, 6, 30).unwrap())
+        );
+        let invalid = Value::Text {
+            value: "2023-02-30".to_string(),
+        };
+        assert!(chrono::NaiveDate::from_value(&invalid).is_err());
+        assert!(chrono::NaiveDate::from_value(&Value::Integer { value: 20230630 }).is_err());
+    }
+
+    #[cfg(feature = "decimal")]
+    #[test]
+    fn test_decimals() {
+        use rust_decimal::Decimal;
+
+        let text = |value: &str| Value::Text {
+            value: value.to_string(),
+        };
+        assert_eq!(
+            Decimal::from_value(&text("123.45")),
+            Ok(Decimal::new(12345, 2))
+        );
+        assert_eq!(
+            Decimal::from_value(&Value::Integer { value: -7 }),
+            Ok(Decimal::new(-7, 0))
+        );
+        assert!(Decimal::from_value(&text("12,3")).is_err());
+        assert!(Decimal::from_value(&Value::Float { value: 0.1 }).is_err());
+        assert!(Decimal::from_value(&Value::Null).is_err());
+    }
+}
@@ -15,6 +15,8 @@ pub struct Client {
     client: hrana_client::Client,
     client_future: hrana_client::ConnFut,
     streams_for_transactions: RwLock<HashMap<u64, Arc<hrana_client::Stream>>>,
+    busy_timeout: Option<u64>,
+    pub(crate) options: crate::client::Options,
 }
 
 impl std::fmt::Debug for Client {
@@ -45,6 +47,8 @@ impl Client {
             client,
             client_future,
             streams_for_transactions: RwLock::new(HashMap::new()),
+            busy_timeout: None,
+            options: Default::default(),
         })
     }
 
@@ -100,6 +104,30 @@ impl Client {
         Self::new(config.url, config.auth_token.unwrap_or_default()).await
     }
 
+    /// Sets the busy timeout of SQLite, making statements wait up to `ms` milliseconds
+    /// for locks held by other connections instead of failing with `database is locked`.
+    ///
+    /// `PRAGMA busy_timeout` is issued on each stream opened afterwards.
+    ///
+    /// # Arguments
+    /// * `ms` - busy timeout in milliseconds
+    pub fn with_busy_timeout(mut self, ms: u64) -> Self {
+        self.busy_timeout = Some(ms);
+        self
+    }
+
+    // Open a stream, applying the busy timeout if set.
+    async fn open_stream(&self) -> Result<hrana_client::Stream> {
+        let stream = self.client.open_stream().await?;
+        if let Some(ms) = self.busy_timeout {
+            stream
+                .execute(Self::into_hrana(crate::client::busy_timeout_pragma(ms)))
+                .await
+                .map_err(|e| anyhow::anyhow!("{}", e))?;
+        }
+        Ok(stream)
+    }
+
     pub async fn shutdown(self) -> Result<()> {
         self.client.shutdown().await?;
         self.client_future.await?;
@@ -119,7 +147,7 @@ impl Client {
         // Pessimistic path - let's drop the mutex, create the stream and try to reinsert it.
         // Another way out of this situation is an async mutex, but I don't want to rely on Tokio or any other specific runtime
         // unless absolutely necessary.
-        let stream = Arc::new(self.client.open_stream().await?);
+        let stream = Arc::new(self.open_stream().await?);
         tracing::trace!("Created new stream");
         let mut streams = self.streams_for_transactions.write().unwrap();
         if let std::collections::hash_map::Entry::Vacant(e) = streams.entry(tx_id) {
@@ -159,7 +187,7 @@ impl Client {
             batch.step(None, hrana_stmt);
         }
 
-        let stream = self.client.open_stream().await?;
+        let stream = self.open_stream().await?;
         stream
             .execute_batch(batch)
             .await
@@ -169,7 +197,7 @@ impl Client {
     pub async fn execute(&self, stmt: impl Into<Statement>) -> Result<ResultSet> {
         let stmt = Self::into_hrana(stmt.into());
 
-        let stream = self.client.open_stream().await?;
+        let stream = self.open_stream().await?;
         stream
             .execute(stmt)
             .await
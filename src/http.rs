@@ -1,9 +1,14 @@
 use crate::client::Config;
-use anyhow::Result;
+use anyhow::{Context, Result};
+use base64::prelude::BASE64_STANDARD;
+use base64::Engine;
+use std::any::{Any, TypeId};
 use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
+use std::time::Duration;
 
-use crate::{proto::pipeline, BatchResult, ResultSet, Statement};
+use crate::circuit_breaker::{CircuitBreaker, CircuitBreakerConfig};
+use crate::{proto::pipeline, BatchResult, RequestId, ResultSet, Statement, Timing};
 
 /// Information about the current session: the server-generated cookie
 /// and the URL that should be used for further communication.
@@ -13,14 +18,195 @@ struct Cookie {
     base_url: Option<String>,
 }
 
+/// Hook post-processing the JSON tree of each response before it is decoded,
+/// see [`Client::with_json_hook()`]
+pub type JsonHook = Arc<dyn Fn(&mut serde_json::Value) + Send + Sync>;
+
+/// Callback fetching a fresh auth token, see [`Client::with_token_provider()`]
+pub type TokenProvider =
+    Arc<dyn Fn() -> futures::future::BoxFuture<'static, Result<String>> + Send + Sync>;
+
+/// Tokens are refreshed this long before they expire, to account for clock skew and latency
+const TOKEN_EXPIRY_MARGIN: Duration = Duration::from_secs(30);
+
 /// Generic HTTP client. Needs a helper function that actually sends
 /// the request.
-#[derive(Clone, Debug)]
+///
+/// Cloning is cheap: clones share the underlying HTTP client, and so its connection pool,
+/// as well as the circuit breaker and the state of open transactions. To share a client
+/// between tasks, clone it instead of creating a new one per task.
+#[derive(Clone)]
 pub struct Client {
     inner: InnerClient,
     cookies: Arc<RwLock<HashMap<u64, Cookie>>>,
+    base_url: String,
     url_for_queries: String,
     auth: String,
+    circuit_breaker: Option<Arc<CircuitBreaker>>,
+    clock: Arc<dyn crate::clock::Clock>,
+    json_hook: Option<JsonHook>,
+    timeout: Option<Duration>,
+    max_response_size: Option<usize>,
+    request_id: Option<String>,
+    busy_timeout: Option<u64>,
+    strict_ordering: bool,
+    extensions: Extensions,
+    sqld_options: serde_json::Map<String, serde_json::Value>,
+    token_provider: Option<TokenProvider>,
+    /// Last token returned by the token provider, shared by all clones
+    provided_token: Arc<RwLock<Option<String>>>,
+    pub(crate) options: crate::client::Options,
+}
+
+/// Credentials overriding those of a client for a single call,
+/// see [`crate::Client::batch_with_auth()`]
+#[derive(Clone)]
+pub enum Auth {
+    /// JWT sent as a bearer token
+    Token(String),
+    /// HTTP basic credentials
+    Basic { username: String, password: String },
+}
+
+impl Auth {
+    /// Returns the value of the `Authorization` header for these credentials
+    pub(crate) fn header(&self) -> String {
+        match self {
+            Auth::Token(token) => format!("Bearer {token}"),
+            Auth::Basic { username, password } => {
+                let credentials = BASE64_STANDARD.encode(format!("{username}:{password}"));
+                format!("Basic {credentials}")
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for Auth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Keep secrets out of logs
+        match self {
+            Auth::Token(_) => f.debug_tuple("Token").field(&"<redacted>").finish(),
+            Auth::Basic { username, .. } => f
+                .debug_struct("Basic")
+                .field("username", username)
+                .field("password", &"<redacted>")
+                .finish(),
+        }
+    }
+}
+
+/// Status of a server, as reported by its health endpoint, see [`Client::server_health()`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct HealthStatus {
+    /// Whether the server answered at all
+    pub reachable: bool,
+    /// Whether the server reported itself healthy, with a 2xx status
+    pub ok: bool,
+    /// HTTP status of the response, if any
+    pub status: Option<u16>,
+}
+
+/// Body of a response, as returned by [`crate::reqwest::HttpClient::send_streaming()`]
+pub enum ResponseBody {
+    /// A regular response, read in full
+    Full(String),
+    /// A newline-delimited JSON response, read line by line as it arrives
+    Lines(futures::stream::BoxStream<'static, Result<String>>),
+}
+
+/// A decoded response of the server, see [`Client::send()`]
+struct Response {
+    msg: pipeline::ServerMsg,
+    /// Round-trip time of the request
+    network: Duration,
+    /// Time spent executing each statement, as far as reported by the server
+    server_durations: Vec<Option<Duration>>,
+    /// SQLite result code of the first error in the response, if any
+    error_code: Option<i32>,
+    /// Indices of the statements the results of a batch are for, if the server reports them
+    step_indices: Vec<Option<usize>>,
+    replication_index: Option<u64>,
+}
+
+/// Arbitrary values attached to requests, which are never sent to the server,
+/// see [`crate::Client::batch_with_extensions()`]
+///
+/// The tower backend inserts them into the extensions of each `http::Request`,
+/// where middleware can find them to correlate requests with application context.
+/// Other backends ignore them. Values are keyed by their type, and cloning is cheap.
+///
+/// # Examples
+///
+/// ```
+/// use libsql_client::http::Extensions;
+///
+/// #[derive(Debug, PartialEq)]
+/// struct TenantId(u64);
+///
+/// let mut ext = Extensions::new();
+/// ext.insert(TenantId(7));
+/// assert_eq!(ext.get::<TenantId>(), Some(&TenantId(7)));
+/// ```
+#[derive(Clone, Default)]
+pub struct Extensions {
+    values: HashMap<TypeId, Arc<dyn Any + Send + Sync>>,
+}
+
+impl Extensions {
+    /// Creates an empty set of extensions
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Inserts `value`, replacing any previous value of the same type
+    pub fn insert<T: Send + Sync + 'static>(&mut self, value: T) {
+        self.values.insert(TypeId::of::<T>(), Arc::new(value));
+    }
+
+    /// Returns the value of type `T`, if any
+    pub fn get<T: Send + Sync + 'static>(&self) -> Option<&T> {
+        self.values.get(&TypeId::of::<T>())?.downcast_ref()
+    }
+
+    /// Returns true if no values were inserted
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
+
+impl std::fmt::Debug for Extensions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Extensions")
+            .field("len", &self.values.len())
+            .finish()
+    }
+}
+
+/// A single request to be sent by one of the HTTP backends
+#[derive(Clone, Debug)]
+pub struct HttpRequest {
+    pub url: String,
+    /// Value of the `Authorization` header
+    pub auth: String,
+    pub body: String,
+    /// Maximum time for the whole request, if supported by the backend
+    pub timeout: Option<Duration>,
+    /// Maximum size of the response body in bytes, if supported by the backend
+    pub max_response_size: Option<usize>,
+    /// Value of the `X-Request-Id` header
+    pub request_id: String,
+    /// Values for middleware, which are not sent to the server
+    pub extensions: Extensions,
+}
+
+impl std::fmt::Debug for Client {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("http::Client")
+            .field("inner", &self.inner)
+            .field("url_for_queries", &self.url_for_queries)
+            .finish()
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -31,23 +217,26 @@ pub enum InnerClient {
     Workers(crate::workers::HttpClient),
     #[cfg(feature = "spin_backend")]
     Spin(crate::spin::HttpClient),
+    #[cfg(feature = "tower")]
+    Tower(crate::tower::HttpClient),
+    #[cfg(all(unix, feature = "reqwest_backend"))]
+    Unix(crate::unix::HttpClient),
     Default,
 }
 
 impl InnerClient {
-    pub async fn send(
-        &self,
-        url: String,
-        auth: String,
-        body: String,
-    ) -> Result<pipeline::ServerMsg> {
+    pub async fn send(&self, request: HttpRequest) -> Result<String> {
         match self {
             #[cfg(feature = "reqwest_backend")]
-            InnerClient::Reqwest(client) => client.send(url, auth, body).await,
+            InnerClient::Reqwest(client) => client.send(request).await,
             #[cfg(feature = "workers_backend")]
-            InnerClient::Workers(client) => client.send(url, auth, body).await,
+            InnerClient::Workers(client) => client.send(request).await,
             #[cfg(feature = "spin_backend")]
-            InnerClient::Spin(client) => client.send(url, auth, body).await,
+            InnerClient::Spin(client) => client.send(request).await,
+            #[cfg(feature = "tower")]
+            InnerClient::Tower(client) => client.send(request).await,
+            #[cfg(all(unix, feature = "reqwest_backend"))]
+            InnerClient::Unix(client) => client.send(request).await,
             _ => panic!("Must enable at least one feature"),
         }
     }
@@ -62,9 +251,13 @@ impl Client {
     pub fn new(inner: InnerClient, url: impl Into<String>, token: impl Into<String>) -> Self {
         let token = token.into();
         let url = url.into();
-        // Auto-update the URL to start with https:// if no protocol was specified
-        let base_url = if !url.contains("://") {
-            format!("https://{}", &url)
+        // Auto-update the URL to start with a protocol if none was specified:
+        // http:// for local development and private networks, https:// otherwise.
+        // Turso-style libsql:// URLs are served over https://
+        let base_url = if let Some(rest) = url.strip_prefix("libsql://") {
+            format!("https://{rest}")
+        } else if !url.contains("://") {
+            format!("{}://{}", default_scheme(&url), &url)
         } else {
             url
         };
@@ -72,8 +265,22 @@ impl Client {
         Self {
             inner,
             cookies: Arc::new(RwLock::new(HashMap::new())),
+            base_url,
             url_for_queries,
             auth: format!("Bearer {token}"),
+            circuit_breaker: None,
+            clock: Arc::new(crate::clock::SystemClock),
+            json_hook: None,
+            timeout: None,
+            max_response_size: None,
+            request_id: None,
+            busy_timeout: None,
+            strict_ordering: false,
+            extensions: Extensions::new(),
+            sqld_options: Default::default(),
+            token_provider: None,
+            provided_token: Default::default(),
+            options: Default::default(),
         }
     }
 
@@ -94,61 +301,545 @@ impl Client {
         let token = std::env::var("LIBSQL_CLIENT_TOKEN").unwrap_or_default();
         Ok(Client::new(inner, url, token))
     }
+
+    /// Creates a copy of this client which authenticates with a new JWT token.
+    ///
+    /// The underlying HTTP client is shared with the original, so connection pools
+    /// are reused instead of being rebuilt. Open transactions are not carried over.
+    ///
+    /// # Arguments
+    /// * `new_token` - auth token
+    pub fn clone_with_token(&self, new_token: &str) -> Self {
+        self.clone_with_auth(Auth::Token(new_token.to_string()).header())
+    }
+
+    /// Creates a copy of this client which authenticates with HTTP basic credentials.
+    ///
+    /// See [`Client::clone_with_token()`] for details on what is shared with the original.
+    ///
+    /// # Arguments
+    /// * `username` - user name
+    /// * `password` - password
+    pub fn clone_with_credentials(&self, username: &str, password: &str) -> Self {
+        let auth = Auth::Basic {
+            username: username.to_string(),
+            password: password.to_string(),
+        };
+        self.clone_with_auth(auth.header())
+    }
+
+    /// Creates a copy of this client which attaches `extensions` to all of its requests.
+    ///
+    /// Like other copies, it shares the underlying HTTP client and open transactions.
+    pub(crate) fn clone_with_extensions(&self, extensions: Extensions) -> Self {
+        Self {
+            extensions,
+            ..self.clone()
+        }
+    }
+
+    pub(crate) fn clone_with_auth(&self, auth: String) -> Self {
+        Self {
+            cookies: Arc::new(RwLock::new(HashMap::new())),
+            auth,
+            token_provider: None,
+            ..self.clone()
+        }
+    }
+
+    /// Fetches auth tokens from `provider` instead of using a fixed one, for short-lived JWTs.
+    ///
+    /// The provider is called before the first request, when the `exp` claim of the current
+    /// token says it is about to expire, and when the server rejects a request with
+    /// a 401 status. In the latter case, the request is retried once with the new token.
+    /// The token is shared by all clones of this client.
+    ///
+    /// # Arguments
+    /// * `provider` - function returning a fresh token
+    pub fn with_token_provider(mut self, provider: TokenProvider) -> Self {
+        self.token_provider = Some(provider);
+        self.provided_token = Default::default();
+        self
+    }
+
+    /// Returns the value of the `Authorization` header, asking the token provider
+    /// for a new token if there is none yet, it is about to expire, or `refresh` is set
+    async fn auth(&self, refresh: bool) -> Result<String> {
+        let Some(provider) = &self.token_provider else {
+            return Ok(self.auth.clone());
+        };
+        let current = self.provided_token.read().unwrap().clone();
+        let token = match current {
+            Some(token) if !refresh && !token_expires_soon(&token) => token,
+            _ => {
+                let token = provider().await.context("Token provider failed")?;
+                *self.provided_token.write().unwrap() = Some(token.clone());
+                token
+            }
+        };
+        Ok(format!("Bearer {token}"))
+    }
+
+    /// Protects the server from being hammered with requests after repeated failures.
+    ///
+    /// Once open, the breaker fails requests with [`Error::CircuitOpen`](crate::Error::CircuitOpen)
+    /// without sending them. See [`crate::circuit_breaker`] for details.
+    /// The breaker is shared by all clones of this client.
+    ///
+    /// # Arguments
+    /// * `config` - circuit breaker configuration
+    pub fn with_circuit_breaker(mut self, config: CircuitBreakerConfig) -> Self {
+        self.circuit_breaker = Some(Arc::new(CircuitBreaker::with_clock(
+            config,
+            self.clock.clone(),
+        )));
+        self
+    }
+
+    /// Uses `clock` as the source of time of the circuit breaker, instead of the real time.
+    ///
+    /// This is meant for tests, which can use a [`ManualClock`](crate::clock::ManualClock)
+    /// to go through cooldowns without sleeping, and for `wasm32-unknown-unknown` runtimes
+    /// where the [`SystemClock`](crate::clock::SystemClock) is not available.
+    /// A circuit breaker set up before is reset.
+    pub fn with_clock(mut self, clock: Arc<dyn crate::clock::Clock>) -> Self {
+        self.clock = clock;
+        match self.circuit_breaker.take() {
+            Some(breaker) => self.with_circuit_breaker(breaker.config().clone()),
+            None => self,
+        }
+    }
+
+    /// Post-processes the JSON tree of each response before it is decoded.
+    ///
+    /// Integer values are sent by the server as decimal strings and floats as JSON numbers,
+    /// so the default decoding never rounds integers. The hook is useful for servers that
+    /// deviate from that, e.g. by sending integers as plain JSON numbers. Note that
+    /// `serde_json`'s `arbitrary_precision` feature is not supported, because it breaks
+    /// decoding of float values.
+    ///
+    /// # Arguments
+    /// * `hook` - function modifying the response in place
+    pub fn with_json_hook(mut self, hook: JsonHook) -> Self {
+        self.json_hook = Some(hook);
+        self
+    }
+
+    /// Fails requests which take longer than `timeout`
+    /// with [`Error::Timeout`](crate::Error::Timeout).
+    ///
+    /// Only the reqwest backend supports timeouts, other backends ignore it.
+    ///
+    /// # Arguments
+    /// * `timeout` - maximum duration of a single request
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Sends queries to `path` instead of the `v2/pipeline` endpoint of sqld,
+    /// e.g. for servers behind a proxy which maps endpoints to other paths.
+    ///
+    /// # Arguments
+    /// * `path` - path of the pipeline endpoint, relative to the URL of the database
+    pub fn with_queries_path(mut self, path: &str) -> Self {
+        self.url_for_queries = format!("{}{}", self.base_url, path.trim_start_matches('/'));
+        self
+    }
+
+    /// Fails requests whose response body is larger than `bytes` with
+    /// [`Error::ResponseTooLarge`](crate::Error::ResponseTooLarge), to protect against
+    /// servers returning enormous responses. The limit applies to decompressed bodies,
+    /// and they are never buffered beyond it.
+    ///
+    /// The tower and spin backends only check the limit once they read the whole body,
+    /// and the workers backend ignores it.
+    ///
+    /// # Arguments
+    /// * `bytes` - maximum size of a response body
+    pub fn with_max_response_size(mut self, bytes: usize) -> Self {
+        self.max_response_size = Some(bytes);
+        self
+    }
+
+    /// Sends `id` as the `X-Request-Id` header of every request, instead of a fresh UUID each time.
+    ///
+    /// The id of a request is exposed in [`ResultSet::request_id()`] and attached to its errors
+    /// as a [RequestId], so that they can be correlated with the logs of the server.
+    ///
+    /// # Arguments
+    /// * `id` - request id, e.g. the one of an incoming request being served
+    pub fn with_request_id(mut self, id: impl Into<String>) -> Self {
+        self.request_id = Some(id.into());
+        self
+    }
+
+    /// Checks the health of the server with a `GET` request to its `/health` endpoint,
+    /// next to the endpoint receiving queries.
+    ///
+    /// This is cheaper than running a query, and tells a server which is up but unable
+    /// to serve queries from one which cannot be reached. An unreachable server is reported
+    /// in the returned status rather than as an error. The timeout of the client applies,
+    /// but not its credentials, circuit breaker nor response size limit.
+    ///
+    /// Only the reqwest backend supports health checks, other backends return an error.
+    pub async fn server_health(&self) -> Result<HealthStatus> {
+        match &self.inner {
+            #[cfg(feature = "reqwest_backend")]
+            InnerClient::Reqwest(client) => {
+                let url = format!("{}health", self.base_url);
+                Ok(client.health(url, self.timeout).await)
+            }
+            #[allow(unreachable_patterns)]
+            _ => anyhow::bail!("Health checks are only supported by the reqwest backend"),
+        }
+    }
+
+    /// Verifies that the results of batches come back in the order of their statements
+    /// if `enabled`, for debugging middleware which may reorder them.
+    ///
+    /// Results can only be verified if the server labels them with the index of their statement,
+    /// as an `index` field of each step result, or of each line of streamed batches.
+    /// Unlabeled results are accepted as they are. Reordered results fail the batch with
+    /// [`Error::ResultsOutOfOrder`](crate::Error::ResultsOutOfOrder). Disabled by default.
+    pub fn with_strict_ordering(mut self, enabled: bool) -> Self {
+        self.strict_ordering = enabled;
+        self
+    }
+
+    /// Sets the busy timeout of SQLite, making statements wait up to `ms` milliseconds
+    /// for locks held by other connections instead of failing with `database is locked`.
+    ///
+    /// The server opens a new session for each request, so `PRAGMA busy_timeout` is sent along
+    /// with every request which starts one: at the front of each batch, before each statement
+    /// executed on its own, and before the first statement of each interactive transaction.
+    /// Its result is dropped and not counted in the results of batches.
+    ///
+    /// # Arguments
+    /// * `ms` - busy timeout in milliseconds
+    pub fn with_busy_timeout(mut self, ms: u64) -> Self {
+        self.busy_timeout = Some(ms);
+        self
+    }
+
+    /// Sets an option in the `options` object of every request body, for server features
+    /// which have no dedicated method in this client.
+    ///
+    /// Options are passed through as they are, without validation. Servers which do not
+    /// recognize an option, including older versions of sqld, may silently ignore it.
+    ///
+    /// # Arguments
+    /// * `key` - name of the option
+    /// * `value` - value of the option, serialized as JSON
+    pub fn with_sqld_option(
+        mut self,
+        key: impl Into<String>,
+        value: impl Into<serde_json::Value>,
+    ) -> Self {
+        self.sqld_options.insert(key.into(), value.into());
+        self
+    }
+
+    /// Serializes a request body, adding the options set with [`Client::with_sqld_option()`]
+    fn encode_body(&self, msg: &impl serde::Serialize) -> Result<String> {
+        if self.sqld_options.is_empty() {
+            return Ok(serde_json::to_string(msg)?);
+        }
+        let mut body = serde_json::to_value(msg)?;
+        if let serde_json::Value::Object(fields) = &mut body {
+            fields.insert(
+                "options".to_string(),
+                serde_json::Value::Object(self.sqld_options.clone()),
+            );
+        }
+        Ok(body.to_string())
+    }
+
+    fn next_request_id(&self) -> String {
+        self.request_id
+            .clone()
+            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string())
+    }
+
+    async fn send(&self, url: String, body: String, request_id: &str) -> Result<Response> {
+        let (response, network) = self.send_request(url, body, request_id, false).await?;
+        let ResponseBody::Full(response) = response else {
+            anyhow::bail!("Unexpected streaming response to a regular request");
+        };
+        let msg = crate::proto::decode_server_msg(&response, self.json_hook.as_deref())?;
+        Ok(Response {
+            msg,
+            network,
+            server_durations: crate::proto::server_durations(&response),
+            error_code: crate::proto::error_code(&response),
+            step_indices: crate::proto::step_indices(&response),
+            replication_index: crate::proto::replication_index(&response),
+        })
+    }
+
+    /// Sends a request through the circuit breaker, refreshing the token and retrying once
+    /// if it is rejected. Returns the body of the response, read line by line if `streaming`
+    /// and supported by the backend, along with the round-trip time of the request.
+    async fn send_request(
+        &self,
+        url: String,
+        body: String,
+        request_id: &str,
+        streaming: bool,
+    ) -> Result<(ResponseBody, Duration)> {
+        let mut request = HttpRequest {
+            url,
+            auth: self.auth(false).await?,
+            body,
+            timeout: self.timeout,
+            max_response_size: self.max_response_size,
+            request_id: request_id.to_string(),
+            extensions: self.extensions.clone(),
+        };
+        if let Some(circuit_breaker) = &self.circuit_breaker {
+            circuit_breaker.acquire()?;
+        }
+        // `Instant` is not available on wasm32-unknown-unknown, where round-trips are not measured
+        let started = (!cfg!(target_arch = "wasm32")).then(std::time::Instant::now);
+        let mut response = self.dispatch(request.clone(), streaming).await;
+        if self.token_provider.is_some() && is_unauthorized(&response) {
+            request.auth = self.auth(true).await?;
+            response = self.dispatch(request, streaming).await;
+        }
+        let network = started.map(|t| t.elapsed()).unwrap_or_default();
+        if let Some(circuit_breaker) = &self.circuit_breaker {
+            circuit_breaker.record(response.is_ok());
+        }
+        Ok((response?, network))
+    }
+
+    #[cfg_attr(not(feature = "reqwest_backend"), allow(unused_variables))]
+    async fn dispatch(&self, request: HttpRequest, streaming: bool) -> Result<ResponseBody> {
+        match &self.inner {
+            #[cfg(feature = "reqwest_backend")]
+            InnerClient::Reqwest(client) if streaming => client.send_streaming(request).await,
+            inner => inner.send(request).await.map(ResponseBody::Full),
+        }
+    }
 }
 
-impl Client {
-    fn into_hrana(stmt: Statement) -> crate::proto::Stmt {
-        let mut hrana_stmt = crate::proto::Stmt::new(stmt.sql, true);
-        for param in stmt.args {
-            hrana_stmt.bind(param);
+/// Error of the busy timeout pragma prepended to requests, see [`Client::with_busy_timeout()`]
+fn busy_timeout_error(message: String, code: Option<i32>) -> anyhow::Error {
+    crate::Error::Sql {
+        message: format!("Setting the busy timeout failed: {message}"),
+        code,
+    }
+    .into()
+}
+
+fn is_unauthorized<T>(response: &Result<T>) -> bool {
+    matches!(
+        response
+            .as_ref()
+            .map_err(|e| e.downcast_ref::<crate::Error>()),
+        Err(Some(crate::Error::Http { status: 401, .. }))
+    )
+}
+
+/// Checks the `exp` claim of a JWT. Tokens which cannot be decoded are assumed to be valid,
+/// leaving it to the server to reject them.
+fn token_expires_soon(token: &str) -> bool {
+    // `SystemTime` is not available on wasm32-unknown-unknown
+    if cfg!(target_arch = "wasm32") {
+        return false;
+    }
+    let exp = token
+        .split('.')
+        .nth(1)
+        .and_then(|payload| {
+            base64::engine::general_purpose::URL_SAFE_NO_PAD
+                .decode(payload.trim_end_matches('='))
+                .ok()
+        })
+        .and_then(|payload| serde_json::from_slice::<serde_json::Value>(&payload).ok())
+        .and_then(|claims| claims.get("exp")?.as_u64());
+    let Some(exp) = exp else {
+        return false;
+    };
+    // Expiry times too far in the future to be represented never come
+    let Some(expires_at) = std::time::UNIX_EPOCH.checked_add(Duration::from_secs(exp)) else {
+        return false;
+    };
+    std::time::SystemTime::now() + TOKEN_EXPIRY_MARGIN >= expires_at
+}
+
+/// Picks the scheme for a URL specified without one: `http` for localhost,
+/// loopback and private network addresses, which rarely have TLS set up, and `https` otherwise.
+fn default_scheme(url: &str) -> &'static str {
+    let authority = url.split(['/', '?', '#']).next().unwrap_or_default();
+    let authority = authority.rsplit('@').next().unwrap_or_default();
+    let host = match authority.strip_prefix('[') {
+        // IPv6 addresses are enclosed in brackets, which also separates them from the port
+        Some(rest) => rest.split(']').next().unwrap_or_default(),
+        None => authority.split(':').next().unwrap_or_default(),
+    };
+    let is_local = match host.parse::<std::net::IpAddr>() {
+        Ok(std::net::IpAddr::V4(ip)) => {
+            ip.is_loopback() || ip.is_private() || ip.is_link_local() || ip.is_unspecified()
+        }
+        Ok(std::net::IpAddr::V6(ip)) => {
+            // fc00::/7 are unique local addresses
+            ip.is_loopback() || ip.is_unspecified() || (ip.segments()[0] & 0xfe00) == 0xfc00
         }
-        hrana_stmt
+        Err(_) => {
+            let host = host.to_ascii_lowercase();
+            host == "localhost" || host.ends_with(".localhost")
+        }
+    };
+    if is_local {
+        "http"
+    } else {
+        "https"
     }
+}
 
+impl Client {
     pub async fn raw_batch(
         &self,
         stmts: impl IntoIterator<Item = impl Into<Statement>>,
     ) -> anyhow::Result<BatchResult> {
-        let mut batch = crate::proto::Batch::new();
-        for stmt in stmts.into_iter() {
-            batch.step(None, Self::into_hrana(stmt.into()));
-        }
-
-        let msg = pipeline::ClientMsg {
-            baton: None,
-            requests: vec![
-                pipeline::StreamRequest::Batch(pipeline::StreamBatchReq { batch }),
-                pipeline::StreamRequest::Close,
-            ],
-        };
-        let body = serde_json::to_string(&msg)?;
-        let mut response: pipeline::ServerMsg = self
-            .inner
-            .send(self.url_for_queries.clone(), self.auth.clone(), body)
+        let pragma = self.busy_timeout.map(crate::client::busy_timeout_pragma);
+        let prepended = pragma.is_some();
+        let stmts = pragma.into_iter().chain(stmts.into_iter().map(Into::into));
+        let (result, _) = self
+            .send_batch(&crate::proto::batch_msg(stmts), prepended)
             .await?;
+        Ok(result)
+    }
 
-        if response.results.is_empty() {
-            anyhow::bail!(
-                "Unexpected empty response from server: {:?}",
-                response.results
-            );
+    /// Executes `stmts` in a transaction started by `begin`, in a single request.
+    ///
+    /// Returns the results of all steps of the batch: `begin`, `stmts`, then `COMMIT` and
+    /// `ROLLBACK`, see [`crate::proto::transaction_batch_msg()`], along with the SQLite
+    /// result code of the failed step, if any. A failed statement skips the following ones
+    /// and the `COMMIT`, rolling the transaction back.
+    pub(crate) async fn batch_transaction(
+        &self,
+        begin: Statement,
+        stmts: Vec<Statement>,
+    ) -> anyhow::Result<(BatchResult, Option<i32>)> {
+        let pragma = self.busy_timeout.map(crate::client::busy_timeout_pragma);
+        let prepended = pragma.is_some();
+        let msg = crate::proto::transaction_batch_msg(pragma, begin, stmts);
+        self.send_batch(&msg, prepended).await
+    }
+
+    /// Sends a pipeline request built by [`crate::proto::batch_msg()`] or alike,
+    /// dropping the result of the busy timeout pragma if it was `prepended`,
+    /// or failing with its error. Returns the SQLite result code of the first failed step
+    /// along with the result.
+    async fn send_batch(
+        &self,
+        msg: &impl serde::Serialize,
+        prepended: bool,
+    ) -> anyhow::Result<(BatchResult, Option<i32>)> {
+        let body = self.encode_body(msg)?;
+        let request_id = self.next_request_id();
+        let response = self
+            .send(self.url_for_queries.clone(), body, &request_id)
+            .await
+            .map_err(|e| RequestId::attach(e, request_id.clone()))?;
+        if self.strict_ordering {
+            crate::proto::check_order(&response.step_indices)
+                .map_err(|e| RequestId::attach(e, request_id))?;
         }
-        if response.results.len() > 2 {
-            // One with actual results, one closing the stream
-            anyhow::bail!(
-                "Unexpected multiple responses from server: {:?}",
-                response.results
-            );
+        let mut result = crate::proto::batch_result(response.msg)?;
+        if prepended && !result.step_results.is_empty() {
+            result.step_results.remove(0);
+            if let Some(error) = result.step_errors.remove(0) {
+                return Err(busy_timeout_error(error.message, response.error_code));
+            }
         }
-        match response.results.swap_remove(0) {
-            pipeline::Response::Ok(pipeline::StreamResponseOk {
-                response: pipeline::StreamResponse::Batch(batch_result),
-            }) => Ok(batch_result.result),
-            pipeline::Response::Ok(_) => {
-                anyhow::bail!("Unexpected response from server: {:?}", response.results)
+        Ok((result, response.error_code))
+    }
+
+    /// Executes a batch of statements, yielding their results as they arrive.
+    ///
+    /// Servers may stream the results of a batch as newline-delimited JSON, with one result
+    /// of a statement, or an object with an `error` field, per line. With the reqwest backend,
+    /// such responses are decoded line by line, so that the first results are available
+    /// before the whole batch completes. Regular responses, and all responses of other
+    /// backends, are read in full first.
+    ///
+    /// Like [`Client::raw_batch()`], the statements are not wrapped in a transaction,
+    /// and a failed statement does not prevent the following ones from running.
+    ///
+    /// # Arguments
+    /// * `stmts` - SQL statements
+    pub async fn batch_streaming(
+        &self,
+        stmts: impl IntoIterator<Item = impl Into<Statement>>,
+    ) -> Result<futures::stream::BoxStream<'static, Result<ResultSet>>> {
+        use futures::StreamExt;
+
+        let pragma = self.busy_timeout.map(crate::client::busy_timeout_pragma);
+        let skipped = usize::from(pragma.is_some());
+        let stmts = pragma.into_iter().chain(stmts.into_iter().map(Into::into));
+        let body = self.encode_body(&crate::proto::batch_msg(stmts))?;
+        let request_id = self.next_request_id();
+        let (response, _) = self
+            .send_request(self.url_for_queries.clone(), body, &request_id, true)
+            .await
+            .map_err(|e| RequestId::attach(e, request_id))?;
+        match response {
+            ResponseBody::Full(body) => {
+                if self.strict_ordering {
+                    crate::proto::check_order(&crate::proto::step_indices(&body))?;
+                }
+                let msg = crate::proto::decode_server_msg(&body, self.json_hook.as_deref())?;
+                let mut result = crate::proto::batch_result(msg)?;
+                if skipped > 0 {
+                    if let Some(Some(error)) = result.step_errors.first_mut().map(Option::take) {
+                        let code = crate::proto::error_code(&body);
+                        return Err(busy_timeout_error(error.message, code));
+                    }
+                }
+                let results: Vec<Result<ResultSet>> = result
+                    .step_results
+                    .into_iter()
+                    .zip(result.step_errors)
+                    .map(|(rs, error)| match (rs, error) {
+                        (Some(rs), _) => Ok(ResultSet::from(rs)),
+                        (None, Some(error)) => Err(anyhow::anyhow!(error.message)),
+                        (None, None) => Err(anyhow::anyhow!("Unexpected missing result set")),
+                    })
+                    .skip(skipped)
+                    .collect();
+                Ok(futures::stream::iter(results).boxed())
             }
-            pipeline::Response::Error(e) => {
-                anyhow::bail!("Error from server: {:?}", e)
+            ResponseBody::Lines(lines) => {
+                let hook = self.json_hook.clone();
+                let strict_ordering = self.strict_ordering;
+                Ok(lines
+                    .enumerate()
+                    .map(move |(position, line)| {
+                        let line = line?;
+                        if position < skipped {
+                            // The result of the busy timeout pragma is dropped below
+                            return match crate::proto::decode_result_line(&line, hook.as_deref()) {
+                                Ok(_) => Ok(None),
+                                Err(e) => Err(busy_timeout_error(e.to_string(), None)),
+                            };
+                        }
+                        if strict_ordering {
+                            match crate::proto::line_index(&line) {
+                                Some(index) if index != position => {
+                                    return Err(
+                                        crate::Error::ResultsOutOfOrder { position, index }.into()
+                                    )
+                                }
+                                _ => {}
+                            }
+                        }
+                        crate::proto::decode_result_line(&line, hook.as_deref()).map(Some)
+                    })
+                    .filter_map(|result| futures::future::ready(result.transpose()))
+                    .boxed())
             }
         }
     }
@@ -158,7 +849,23 @@ impl Client {
         stmt: impl Into<Statement> + Send,
         tx_id: u64,
     ) -> Result<ResultSet> {
-        let stmt = Self::into_hrana(stmt.into());
+        let request_id = self.next_request_id();
+        match self.execute_request(stmt.into(), tx_id, &request_id).await {
+            Ok(rs) => Ok(ResultSet {
+                request_id: Some(request_id),
+                ..rs
+            }),
+            Err(e) => Err(RequestId::attach(e, request_id)),
+        }
+    }
+
+    async fn execute_request(
+        &self,
+        stmt: Statement,
+        tx_id: u64,
+        request_id: &str,
+    ) -> Result<ResultSet> {
+        let stmt = crate::proto::into_hrana(stmt);
 
         let cookie = if tx_id > 0 {
             self.cookies
@@ -170,7 +877,7 @@ impl Client {
         } else {
             Cookie::default()
         };
-        let requests = if tx_id != 0 {
+        let mut requests = if tx_id != 0 {
             vec![pipeline::StreamRequest::Execute(
                 pipeline::StreamExecuteReq { stmt },
             )]
@@ -180,16 +887,46 @@ impl Client {
                 pipeline::StreamRequest::Close,
             ]
         };
+        // Only requests without a baton start a new session
+        let prepended = match self.busy_timeout {
+            Some(ms) if cookie.baton.is_none() => {
+                let stmt = crate::proto::into_hrana(crate::client::busy_timeout_pragma(ms));
+                requests.insert(
+                    0,
+                    pipeline::StreamRequest::Execute(pipeline::StreamExecuteReq { stmt }),
+                );
+                true
+            }
+            _ => false,
+        };
         let msg = pipeline::ClientMsg {
             baton: cookie.baton,
             requests,
         };
-        let body = serde_json::to_string(&msg)?;
+        let body = self.encode_body(&msg)?;
         let url = cookie
             .base_url
             .unwrap_or_else(|| self.url_for_queries.clone());
-        let mut response: pipeline::ServerMsg =
-            self.inner.send(url, self.auth.clone(), body).await?;
+        let Response {
+            msg: mut response,
+            network,
+            server_durations,
+            error_code,
+            replication_index,
+            ..
+        } = self.send(url, body, request_id).await?;
+        if prepended && !response.results.is_empty() {
+            if let pipeline::Response::Error(e) = response.results.remove(0) {
+                return Err(busy_timeout_error(e.error.message, error_code));
+            }
+        }
+        let timing = Timing {
+            network,
+            server: server_durations
+                .get(usize::from(prepended))
+                .copied()
+                .flatten(),
+        };
 
         if tx_id > 0 {
             let base_url = response.base_url;
@@ -223,13 +960,19 @@ impl Client {
         match response.results.swap_remove(0) {
             pipeline::Response::Ok(pipeline::StreamResponseOk {
                 response: pipeline::StreamResponse::Execute(execute_result),
-            }) => Ok(ResultSet::from(execute_result.result)),
+            }) => Ok(ResultSet {
+                timing,
+                replication_index,
+                ..ResultSet::from(execute_result.result)
+            }),
             pipeline::Response::Ok(_) => {
                 anyhow::bail!("Unexpected response from server: {:?}", response.results)
             }
-            pipeline::Response::Error(e) => {
-                anyhow::bail!("Error from server: {:?}", e)
+            pipeline::Response::Error(e) => Err(crate::Error::Sql {
+                message: e.error.message,
+                code: error_code,
             }
+            .into()),
         }
     }
 
@@ -248,8 +991,8 @@ impl Client {
         let url = cookie
             .base_url
             .unwrap_or_else(|| self.url_for_queries.clone());
-        let body = serde_json::to_string(&msg)?;
-        self.inner.send(url, self.auth.clone(), body).await.ok();
+        let body = self.encode_body(&msg)?;
+        self.send(url, body, &self.next_request_id()).await.ok();
         self.cookies.write().unwrap().remove(&tx_id);
         Ok(())
     }
@@ -276,3 +1019,607 @@ impl Client {
         Ok(())
     }
 }
+
+#[cfg(all(test, feature = "reqwest_backend"))]
+mod tests {
+    use super::*;
+    use crate::mock_server::{MockResponse, MockServer};
+
+    fn reqwest_client(url: &str, token: &str) -> Client {
+        let inner = InnerClient::Reqwest(crate::reqwest::HttpClient::new());
+        Client::new(inner, url, token)
+    }
+
+    #[tokio::test]
+    async fn test_clone_with_token() {
+        let server = MockServer::start(|_| MockResponse::empty_execute()).await;
+        let client = reqwest_client(&server.url, "old");
+        client.execute("SELECT 1").await.unwrap();
+
+        let rotated = client.clone_with_token("new");
+        rotated.execute("SELECT 1").await.unwrap();
+        client.execute("SELECT 1").await.unwrap();
+
+        let auth: Vec<_> = server
+            .requests()
+            .iter()
+            .map(|r| r.header("authorization").unwrap().to_string())
+            .collect();
+        assert_eq!(auth, ["Bearer old", "Bearer new", "Bearer old"]);
+        // All requests went through the same pooled connection
+        assert_eq!(server.connections(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_clones_share_connection_pool() {
+        let server = MockServer::start(|_| MockResponse::empty_execute()).await;
+        let client = reqwest_client(&server.url, "");
+        let clones: Vec<Client> = (0..3).map(|_| client.clone()).collect();
+        for clone in &clones {
+            clone.execute("SELECT 1").await.unwrap();
+        }
+        client.execute("SELECT 1").await.unwrap();
+        assert_eq!(server.requests().len(), 4);
+        assert_eq!(server.connections(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_circuit_breaker() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::time::Duration;
+
+        let healthy = Arc::new(AtomicBool::new(false));
+        let server_healthy = healthy.clone();
+        let server = MockServer::start(move |_| {
+            if server_healthy.load(Ordering::SeqCst) {
+                MockResponse::empty_execute()
+            } else {
+                MockResponse::status(503)
+            }
+        })
+        .await;
+        let cooldown = Duration::from_millis(100);
+        let client = reqwest_client(&server.url, "").with_circuit_breaker(CircuitBreakerConfig {
+            failure_threshold: 2,
+            window: Duration::from_secs(60),
+            cooldown,
+        });
+        let is_circuit_open = |e: &anyhow::Error| {
+            matches!(
+                e.downcast_ref::<crate::Error>(),
+                Some(crate::Error::CircuitOpen { .. })
+            )
+        };
+
+        // Closed: failures reach the server until the threshold is hit
+        for _ in 0..2 {
+            let err = client.execute("SELECT 1").await.unwrap_err();
+            assert!(!is_circuit_open(&err));
+        }
+        // Open: requests are rejected without reaching the server
+        let err = client.execute("SELECT 1").await.unwrap_err();
+        assert!(is_circuit_open(&err));
+        assert_eq!(server.requests().len(), 2);
+
+        // Half-open: a failing trial reopens the breaker
+        tokio::time::sleep(cooldown).await;
+        let err = client.execute("SELECT 1").await.unwrap_err();
+        assert!(!is_circuit_open(&err));
+        assert!(is_circuit_open(
+            &client.execute("SELECT 1").await.unwrap_err()
+        ));
+        assert_eq!(server.requests().len(), 3);
+
+        // Half-open: a successful trial closes the breaker
+        healthy.store(true, Ordering::SeqCst);
+        tokio::time::sleep(cooldown).await;
+        client.execute("SELECT 1").await.unwrap();
+        client.execute("SELECT 1").await.unwrap();
+        assert_eq!(server.requests().len(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_circuit_breaker_with_manual_clock() {
+        let server = MockServer::start(|_| MockResponse::status(503)).await;
+        let clock = Arc::new(crate::clock::ManualClock::new());
+        let client = reqwest_client(&server.url, "")
+            .with_circuit_breaker(CircuitBreakerConfig {
+                failure_threshold: 2,
+                window: Duration::from_secs(60),
+                cooldown: Duration::from_secs(3600),
+            })
+            .with_clock(clock.clone());
+        let retry_after = |e: anyhow::Error| match e.downcast_ref::<crate::Error>() {
+            Some(crate::Error::CircuitOpen { retry_after }) => Some(*retry_after),
+            _ => None,
+        };
+
+        for _ in 0..2 {
+            client.execute("SELECT 1").await.unwrap_err();
+        }
+        let err = client.execute("SELECT 1").await.unwrap_err();
+        assert_eq!(retry_after(err), Some(Duration::from_secs(3600)));
+
+        clock.advance(Duration::from_secs(3599));
+        let err = client.execute("SELECT 1").await.unwrap_err();
+        assert_eq!(retry_after(err), Some(Duration::from_secs(1)));
+        assert_eq!(server.requests().len(), 2);
+
+        // The cooldown of an hour is over without waiting for it
+        clock.advance(Duration::from_secs(1));
+        let err = client.execute("SELECT 1").await.unwrap_err();
+        assert_eq!(retry_after(err), None);
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[test]
+    fn test_default_scheme() {
+        let url_for = |url: &str| reqwest_client(url, "").url_for_queries;
+        assert_eq!(
+            url_for("localhost:8080/"),
+            "http://localhost:8080/v2/pipeline"
+        );
+        assert_eq!(
+            url_for("127.0.0.1:8080/"),
+            "http://127.0.0.1:8080/v2/pipeline"
+        );
+        assert_eq!(url_for("192.168.1.10/"), "http://192.168.1.10/v2/pipeline");
+        assert_eq!(url_for("[::1]:8080/"), "http://[::1]:8080/v2/pipeline");
+        assert_eq!(url_for("db.localhost/"), "http://db.localhost/v2/pipeline");
+        assert_eq!(url_for("example.com/"), "https://example.com/v2/pipeline");
+        assert_eq!(url_for("8.8.8.8/"), "https://8.8.8.8/v2/pipeline");
+        assert_eq!(
+            url_for("libsql://db.turso.io/"),
+            "https://db.turso.io/v2/pipeline"
+        );
+        // An explicit scheme is always kept
+        assert_eq!(
+            url_for("https://localhost:8080/"),
+            "https://localhost:8080/v2/pipeline"
+        );
+    }
+
+    #[test]
+    fn test_decode_large_integers() {
+        let body = r#"{"baton":null,"base_url":null,"results":[{"type":"ok","response":{"type":"execute","result":{"cols":[{"name":"n"}],"rows":[[{"type":"integer","value":"9223372036854775807"}]],"affected_row_count":0,"last_insert_rowid":null}}}]}"#;
+        let msg = crate::proto::decode_server_msg(body, None).unwrap();
+        let pipeline::Response::Ok(pipeline::StreamResponseOk {
+            response: pipeline::StreamResponse::Execute(result),
+        }) = &msg.results[0]
+        else {
+            panic!("unexpected response: {msg:?}");
+        };
+        assert!(matches!(
+            result.result.rows[0][0],
+            crate::Value::Integer { value: i64::MAX }
+        ));
+    }
+
+    #[test]
+    fn test_json_hook() {
+        // Integers sent as JSON numbers instead of strings
+        let body = r#"{"baton":null,"base_url":null,"results":[{"type":"ok","response":{"type":"execute","result":{"cols":[{"name":"n"}],"rows":[[{"type":"integer","value":9223372036854775807}]],"affected_row_count":0,"last_insert_rowid":null}}}]}"#;
+        assert!(crate::proto::decode_server_msg(body, None).is_err());
+
+        fn stringify_integers(tree: &mut serde_json::Value) {
+            match tree {
+                serde_json::Value::Object(map) => {
+                    if map.get("type").and_then(|t| t.as_str()) == Some("integer") {
+                        if let Some(value) = map.get_mut("value").filter(|v| v.is_number()) {
+                            *value = serde_json::Value::String(value.to_string());
+                        }
+                    }
+                    map.values_mut().for_each(stringify_integers);
+                }
+                serde_json::Value::Array(values) => values.iter_mut().for_each(stringify_integers),
+                _ => {}
+            }
+        }
+        let hook: JsonHook = Arc::new(stringify_integers);
+        let msg = crate::proto::decode_server_msg(body, Some(hook.as_ref())).unwrap();
+        let pipeline::Response::Ok(pipeline::StreamResponseOk {
+            response: pipeline::StreamResponse::Execute(result),
+        }) = &msg.results[0]
+        else {
+            panic!("unexpected response: {msg:?}");
+        };
+        assert!(matches!(
+            result.result.rows[0][0],
+            crate::Value::Integer { value: i64::MAX }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_returning() {
+        let server = MockServer::start(|_| {
+            MockResponse::execute(
+                r#"{"cols":[{"name":"id"},{"name":"n"}],"rows":[[{"type":"integer","value":"1"},{"type":"integer","value":"2"}]],"affected_row_count":1,"last_insert_rowid":null}"#,
+            )
+        })
+        .await;
+        let client = reqwest_client(&server.url, "");
+        let rs = client
+            .execute("DELETE FROM t WHERE id = 1 RETURNING *")
+            .await
+            .unwrap();
+        assert_eq!(rs.columns, ["id", "n"]);
+        assert_eq!(rs.rows.len(), 1);
+        assert_eq!(rs.rows[0].try_get::<i64>(1).unwrap(), 2);
+        assert_eq!(rs.rows_affected, 1);
+    }
+
+    #[tokio::test]
+    async fn test_timing() {
+        let delay = Duration::from_millis(50);
+        let server =
+            MockServer::start(move |_| MockResponse::empty_execute().with_delay(delay)).await;
+        let rs = reqwest_client(&server.url, "")
+            .execute("SELECT 1")
+            .await
+            .unwrap();
+        assert!(rs.timing().network >= delay, "{:?}", rs.timing());
+        assert_eq!(rs.timing().server, None);
+
+        let server = MockServer::start(|_| {
+            MockResponse::execute(
+                r#"{"cols":[],"rows":[],"affected_row_count":0,"last_insert_rowid":null,"query_duration_ms":12.5}"#,
+            )
+        })
+        .await;
+        let rs = reqwest_client(&server.url, "")
+            .execute("SELECT 1")
+            .await
+            .unwrap();
+        assert_eq!(rs.timing().server, Some(Duration::from_micros(12_500)));
+        assert!(rs.timing().network > Duration::ZERO);
+    }
+
+    #[tokio::test]
+    async fn test_replication_index() {
+        let server = MockServer::start(|_| {
+            MockResponse::execute(
+                r#"{"cols":[],"rows":[],"affected_row_count":1,"last_insert_rowid":null,"replication_index":"1024"}"#,
+            )
+        })
+        .await;
+        let rs = reqwest_client(&server.url, "")
+            .execute("INSERT INTO t VALUES (1)")
+            .await
+            .unwrap();
+        assert_eq!(rs.replication_index(), Some(1024));
+
+        let server = MockServer::start(|_| MockResponse::empty_execute()).await;
+        let rs = reqwest_client(&server.url, "")
+            .execute("SELECT 1")
+            .await
+            .unwrap();
+        assert_eq!(rs.replication_index(), None);
+    }
+
+    #[tokio::test]
+    async fn test_token_provider() {
+        let server = MockServer::start(|request| match request.header("authorization") {
+            Some("Bearer token-1") => MockResponse::status(401),
+            _ => MockResponse::empty_execute(),
+        })
+        .await;
+        let calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let provider: TokenProvider = {
+            let calls = calls.clone();
+            Arc::new(move || {
+                let n = calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst) + 1;
+                Box::pin(async move { Ok(format!("token-{n}")) })
+            })
+        };
+        let client = reqwest_client(&server.url, "unused").with_token_provider(provider);
+        // The first token is rejected, so a new one is fetched and the request retried
+        client.execute("SELECT 1").await.unwrap();
+        // The new token is reused, also by clones
+        client.clone().execute("SELECT 1").await.unwrap();
+
+        let auth: Vec<_> = server
+            .requests()
+            .iter()
+            .map(|r| r.header("authorization").unwrap().to_string())
+            .collect();
+        assert_eq!(auth, ["Bearer token-1", "Bearer token-2", "Bearer token-2"]);
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_token_expires_soon() {
+        let jwt = |claims: &str| {
+            let payload = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(claims);
+            format!("eyJhbGciOiJFZERTQSJ9.{payload}.signature")
+        };
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        assert!(token_expires_soon(&jwt(&format!(
+            r#"{{"exp":{}}}"#,
+            now - 10
+        ))));
+        assert!(token_expires_soon(&jwt(&format!(
+            r#"{{"exp":{}}}"#,
+            now + 10
+        ))));
+        assert!(!token_expires_soon(&jwt(&format!(
+            r#"{{"exp":{}}}"#,
+            now + 3600
+        ))));
+        assert!(!token_expires_soon(&jwt(&format!(
+            r#"{{"exp":{}}}"#,
+            u64::MAX
+        ))));
+        assert!(!token_expires_soon(&jwt(r#"{"a":"rw"}"#)));
+        assert!(!token_expires_soon("opaque-token"));
+    }
+
+    #[tokio::test]
+    async fn test_request_id() {
+        let server = MockServer::start(|request| match request.body.contains("fail") {
+            true => MockResponse::status(500),
+            false => MockResponse::empty_execute(),
+        })
+        .await;
+        let client = reqwest_client(&server.url, "");
+        let rs = client.execute("SELECT 1").await.unwrap();
+        let sent = server.requests()[0]
+            .header("x-request-id")
+            .unwrap()
+            .to_string();
+        assert_eq!(sent.len(), 36, "{sent}");
+        assert_eq!(rs.request_id(), Some(sent.as_str()));
+        // Every request gets a fresh id
+        let rs = client.execute("SELECT 1").await.unwrap();
+        assert_ne!(rs.request_id(), Some(sent.as_str()));
+
+        let client = client.with_request_id("my-request");
+        let rs = client.execute("SELECT 1").await.unwrap();
+        assert_eq!(
+            server.requests()[2].header("x-request-id"),
+            Some("my-request")
+        );
+        assert_eq!(rs.request_id(), Some("my-request"));
+
+        let err = client.execute("SELECT 'fail'").await.unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<RequestId>().map(RequestId::id),
+            Some("my-request")
+        );
+        let http_error = err.downcast_ref::<crate::Error>().unwrap().to_string();
+        assert_eq!(
+            format!("{err:#}"),
+            format!("Request my-request failed: {http_error}")
+        );
+        assert!(matches!(
+            err.downcast_ref::<crate::Error>(),
+            Some(crate::Error::Http { status: 500, .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_sql_error_code() {
+        let server = MockServer::start(|request| {
+            let code = match request.body.contains("users") {
+                true => r#""SQLITE_CONSTRAINT_UNIQUE""#,
+                false => r#""SQLITE_ERROR""#,
+            };
+            MockResponse::json(format!(
+                r#"{{"baton":null,"base_url":null,"results":[{{"type":"error","error":{{"message":"SQLite error","code":{code}}}}},{{"type":"ok","response":{{"type":"close"}}}}]}}"#
+            ))
+        })
+        .await;
+        let client = reqwest_client(&server.url, "");
+        let err = client
+            .execute("INSERT INTO users VALUES (1)")
+            .await
+            .unwrap_err();
+        let err = err.downcast_ref::<crate::Error>().unwrap();
+        assert_eq!(err.sqlite_code(), Some(2067));
+        assert!(err.is_constraint_violation());
+
+        let err = client.execute("SELECT * FROM t").await.unwrap_err();
+        let err = err.downcast_ref::<crate::Error>().unwrap();
+        assert_eq!(err.sqlite_code(), Some(1));
+        assert!(!err.is_constraint_violation());
+    }
+
+    #[tokio::test]
+    async fn test_sqld_options() {
+        let server = MockServer::start(|_| MockResponse::empty_execute()).await;
+        let client = reqwest_client(&server.url, "");
+        client.execute("SELECT 1").await.unwrap();
+        let client = client
+            .with_sqld_option("read_your_writes", true)
+            .with_sqld_option("priority", "low");
+        client.execute("SELECT 1").await.unwrap();
+
+        let bodies: Vec<serde_json::Value> = server
+            .requests()
+            .iter()
+            .map(|r| serde_json::from_str(&r.body).unwrap())
+            .collect();
+        assert!(bodies[0].get("options").is_none());
+        assert_eq!(
+            bodies[1]["options"],
+            serde_json::json!({"read_your_writes": true, "priority": "low"})
+        );
+        assert_eq!(bodies[1]["requests"][0]["type"], "execute");
+    }
+
+    #[tokio::test]
+    async fn test_server_health() {
+        let server = MockServer::start(|request| match request.path.as_str() {
+            "/health" => MockResponse::status(200),
+            _ => MockResponse::status(404),
+        })
+        .await;
+        let health = reqwest_client(&server.url, "")
+            .server_health()
+            .await
+            .unwrap();
+        assert_eq!(
+            health,
+            HealthStatus {
+                reachable: true,
+                ok: true,
+                status: Some(200)
+            }
+        );
+        let request = &server.requests()[0];
+        assert_eq!(request.method, "GET");
+
+        let server = MockServer::start(|_| MockResponse::status(503)).await;
+        let health = reqwest_client(&server.url, "")
+            .server_health()
+            .await
+            .unwrap();
+        assert!(health.reachable);
+        assert!(!health.ok);
+        assert_eq!(health.status, Some(503));
+
+        // Nothing listens on a port freed right before
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let health = reqwest_client(&format!("http://127.0.0.1:{port}/"), "")
+            .server_health()
+            .await
+            .unwrap();
+        assert!(!health.reachable);
+        assert_eq!(health.status, None);
+    }
+
+    #[tokio::test]
+    async fn test_busy_timeout() {
+        let server = MockServer::start(|_| {
+            let empty = r#"{"cols":[],"rows":[],"affected_row_count":0,"last_insert_rowid":null,"query_duration_ms":1}"#;
+            let one = r#"{"cols":[{"name":"x"}],"rows":[[{"type":"integer","value":"1"}]],"affected_row_count":0,"last_insert_rowid":null,"query_duration_ms":2}"#;
+            MockResponse::json(format!(
+                r#"{{"baton":null,"base_url":null,"results":[{{"type":"ok","response":{{"type":"execute","result":{empty}}}}},{{"type":"ok","response":{{"type":"execute","result":{one}}}}},{{"type":"ok","response":{{"type":"close"}}}}]}}"#
+            ))
+        })
+        .await;
+        let client = reqwest_client(&server.url, "").with_busy_timeout(5000);
+        let rs = client.execute("SELECT 1 AS x").await.unwrap();
+        assert_eq!(rs.rows[0].try_get::<i64>(0).unwrap(), 1);
+        assert_eq!(rs.timing().server, Some(Duration::from_millis(2)));
+
+        let body: serde_json::Value = serde_json::from_str(&server.requests()[0].body).unwrap();
+        assert_eq!(
+            body["requests"][0]["stmt"]["sql"],
+            "PRAGMA busy_timeout = 5000"
+        );
+        assert_eq!(body["requests"][1]["stmt"]["sql"], "SELECT 1 AS x");
+    }
+
+    #[tokio::test]
+    async fn test_busy_timeout_error() {
+        let server = MockServer::start(|request| {
+            let error = r#"{"message":"database is locked","code":"SQLITE_BUSY"}"#;
+            let one = r#"{"cols":[{"name":"x"}],"rows":[[{"type":"integer","value":"1"}]],"affected_row_count":0,"last_insert_rowid":null}"#;
+            if request.body.contains("\"batch\"") {
+                MockResponse::json(format!(
+                    r#"{{"baton":null,"base_url":null,"results":[{{"type":"ok","response":{{"type":"batch","result":{{"step_results":[null,{one}],"step_errors":[{error},null]}}}}}},{{"type":"ok","response":{{"type":"close"}}}}]}}"#
+                ))
+            } else {
+                MockResponse::json(format!(
+                    r#"{{"baton":null,"base_url":null,"results":[{{"type":"error","error":{error}}},{{"type":"ok","response":{{"type":"execute","result":{one}}}}},{{"type":"ok","response":{{"type":"close"}}}}]}}"#
+                ))
+            }
+        })
+        .await;
+        let client = reqwest_client(&server.url, "").with_busy_timeout(5000);
+        let errors = [
+            client.execute("SELECT 1 AS x").await.unwrap_err(),
+            client.raw_batch(["SELECT 1 AS x"]).await.unwrap_err(),
+        ];
+        for err in errors {
+            assert!(
+                format!("{err:#}").ends_with("Setting the busy timeout failed: database is locked"),
+                "{err:#}"
+            );
+            assert!(matches!(
+                err.downcast_ref::<crate::Error>(),
+                Some(crate::Error::Sql { code: Some(5), .. })
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_strict_ordering() {
+        let server = MockServer::start(|_| {
+            let step = |index: usize| {
+                format!(
+                    r#"{{"index":{index},"cols":[{{"name":"x"}}],"rows":[[{{"type":"integer","value":"{index}"}}]],"affected_row_count":0,"last_insert_rowid":null}}"#
+                )
+            };
+            MockResponse::json(format!(
+                r#"{{"baton":null,"base_url":null,"results":[{{"type":"ok","response":{{"type":"batch","result":{{"step_results":[{},{}],"step_errors":[null,null]}}}}}},{{"type":"ok","response":{{"type":"close"}}}}]}}"#,
+                step(1),
+                step(0)
+            ))
+        })
+        .await;
+        let stmts = || {
+            vec![
+                Statement::new("SELECT 0 AS x"),
+                Statement::new("SELECT 1 AS x"),
+            ]
+        };
+
+        let client = reqwest_client(&server.url, "");
+        let results = client.raw_batch(stmts()).await.unwrap();
+        assert_eq!(results.step_results.len(), 2);
+
+        let err = client
+            .with_strict_ordering(true)
+            .raw_batch(stmts())
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<crate::Error>(),
+            Some(crate::Error::ResultsOutOfOrder {
+                position: 0,
+                index: 1
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_cancellation() {
+        let server =
+            MockServer::start(|_| MockResponse::empty_execute().with_delay(Duration::from_secs(5)))
+                .await;
+        let client = reqwest_client(&server.url, "");
+        let dropped = tokio::time::timeout(
+            Duration::from_millis(100),
+            client.raw_batch(vec![Statement::new("SELECT 1")]),
+        );
+        assert!(dropped.await.is_err());
+        assert_eq!(server.requests().len(), 1);
+        // The connection is closed right away rather than after the response
+        for _ in 0..100 {
+            if server.aborted() == 1 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(server.aborted(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_clone_with_credentials() {
+        let server = MockServer::start(|_| MockResponse::empty_execute()).await;
+        let client = reqwest_client(&server.url, "token").clone_with_credentials("user", "pass");
+        client.execute("SELECT 1").await.unwrap();
+        assert_eq!(
+            server.requests()[0].header("authorization"),
+            Some("Basic dXNlcjpwYXNz")
+        );
+    }
+}
@@ -0,0 +1,228 @@
+//! Importing CSV into tables, see [`Client::import_csv()`].
+
+use anyhow::{Context, Result};
+use futures::io::{AsyncBufRead, AsyncBufReadExt};
+
+use crate::{proto::Value, Client};
+
+/// Options of [`Client::import_csv()`]
+#[derive(Clone, Debug)]
+pub struct CsvOptions {
+    /// Character separating fields, `,` by default
+    pub delimiter: char,
+    /// Unquoted field standing for `NULL`, e.g. `\N`. By default, there is none,
+    /// and empty fields are imported as empty text.
+    pub null: Option<String>,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: ',',
+            null: None,
+        }
+    }
+}
+
+/// A parsed field, remembering whether it was quoted, in which case it is never `NULL`
+struct Field {
+    value: String,
+    quoted: bool,
+}
+
+pub(crate) async fn import_csv(
+    client: &Client,
+    table: &str,
+    mut reader: impl AsyncBufRead + Unpin,
+    options: &CsvOptions,
+) -> Result<u64> {
+    let header = read_record(&mut reader, options.delimiter)
+        .await?
+        .context("Missing CSV header")?;
+    // The header comes with the data, so its names are quoted rather than trusted as SQL
+    let columns: Vec<String> = header
+        .iter()
+        .map(|field| format!("\"{}\"", field.value.replace('"', "\"\"")))
+        .collect();
+    let columns: Vec<&str> = columns.iter().map(String::as_str).collect();
+    let mut loader = client.bulk_loader(table, &columns)?;
+    let mut record = 1;
+    while let Some(fields) = read_record(&mut reader, options.delimiter).await? {
+        let row = fields
+            .into_iter()
+            .map(|field| match &options.null {
+                Some(null) if !field.quoted && field.value == *null => Value::Null,
+                _ => Value::Text { value: field.value },
+            })
+            .collect();
+        loader
+            .feed(row)
+            .await
+            .with_context(|| format!("Failed to import CSV record {record}"))?;
+        record += 1;
+    }
+    loader.finish().await
+}
+
+/// Reads the next non-empty record, which spans several lines if quoted fields contain line breaks
+async fn read_record(
+    reader: &mut (impl AsyncBufRead + Unpin),
+    delimiter: char,
+) -> Result<Option<Vec<Field>>> {
+    let mut record = String::new();
+    loop {
+        if reader.read_line(&mut record).await? == 0 {
+            if record.is_empty() {
+                return Ok(None);
+            }
+            anyhow::bail!("Unterminated quoted field in CSV record: {record}");
+        }
+        // Escaped quotes come in pairs, so an odd count means that a quoted field goes on
+        if record.matches('"').count() % 2 == 1 {
+            continue;
+        }
+        let line = record.trim_end_matches(['\r', '\n']);
+        if line.is_empty() {
+            record.clear();
+            continue;
+        }
+        return Ok(Some(parse_record(line, delimiter)));
+    }
+}
+
+/// Splits a record into fields, unquoting them as described in RFC 4180
+fn parse_record(line: &str, delimiter: char) -> Vec<Field> {
+    let mut fields = Vec::new();
+    let mut field = Field {
+        value: String::new(),
+        quoted: false,
+    };
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.value.push('"');
+                }
+                '"' => in_quotes = false,
+                _ => field.value.push(c),
+            }
+        } else if c == '"' && field.value.is_empty() && !field.quoted {
+            in_quotes = true;
+            field.quoted = true;
+        } else if c == delimiter {
+            fields.push(std::mem::replace(
+                &mut field,
+                Field {
+                    value: String::new(),
+                    quoted: false,
+                },
+            ));
+        } else {
+            field.value.push(c);
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+#[cfg(all(test, feature = "local_backend"))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_import_csv() {
+        let db = Client::in_memory().unwrap();
+        db.execute("CREATE TABLE users(id INTEGER, name TEXT, note TEXT)")
+            .await
+            .unwrap();
+        let csv = "id;name;note\r\n\
+                   1;\"Smith; John\";\"said \"\"hi\"\"\"\r\n\
+                   2;bob;\\N\r\n\
+                   3;\"multi\nline\";\"\\N\"\r\n\
+                   \r\n";
+        let options = CsvOptions {
+            delimiter: ';',
+            null: Some("\\N".to_string()),
+        };
+        let imported = db
+            .import_csv("users", csv.as_bytes(), &options)
+            .await
+            .unwrap();
+        assert_eq!(imported, 3);
+
+        let rs = db
+            .execute("SELECT id, name, note FROM users ORDER BY id")
+            .await
+            .unwrap();
+        let rows: Vec<(i64, &str, Option<&str>)> = rs
+            .rows
+            .iter()
+            .map(|row| {
+                (
+                    row.try_get(0).unwrap(),
+                    row.try_get(1).unwrap(),
+                    row.get_str(2),
+                )
+            })
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                (1, "Smith; John", Some("said \"hi\"")),
+                (2, "bob", None),
+                (3, "multi\nline", Some("\\N")),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_import_csv_column_names() {
+        let db = Client::in_memory().unwrap();
+        db.execute("CREATE TABLE t(\"first name\", x)")
+            .await
+            .unwrap();
+        db.execute("CREATE TABLE secrets(s)").await.unwrap();
+        db.execute("INSERT INTO secrets VALUES ('pw')")
+            .await
+            .unwrap();
+        let options = CsvOptions::default();
+
+        let csv = "first name,x\nalice,1\n";
+        let imported = db.import_csv("t", csv.as_bytes(), &options).await.unwrap();
+        assert_eq!(imported, 1);
+
+        // A header is not spliced into the SQL
+        let csv = "x) VALUES ((SELECT group_concat(s) FROM secrets)||?) --\nhello\n";
+        let err = db
+            .import_csv("t", csv.as_bytes(), &options)
+            .await
+            .unwrap_err();
+        assert!(format!("{err:#}").contains("has no column named"));
+        let rs = db.execute("SELECT x FROM t").await.unwrap();
+        assert_eq!(rs.rows.len(), 1);
+        assert_eq!(rs.rows[0].try_get::<&str>(0).unwrap(), "1");
+    }
+
+    #[tokio::test]
+    async fn test_import_csv_errors() {
+        let db = Client::in_memory().unwrap();
+        db.execute("CREATE TABLE t(a, b)").await.unwrap();
+        let options = CsvOptions::default();
+        assert!(db.import_csv("t", "".as_bytes(), &options).await.is_err());
+        let err = db
+            .import_csv("t", "a,b\n1,2\n3\n".as_bytes(), &options)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            format!("{err:#}"),
+            "Failed to import CSV record 2: Expected a row of 2 values, got 1"
+        );
+        assert!(db
+            .import_csv("t", "a,b\n1,\"2\n".as_bytes(), &options)
+            .await
+            .is_err());
+    }
+}
@@ -12,13 +12,30 @@
 )]
 
 pub mod statement;
-pub use statement::Statement;
+pub use statement::{Param, Statement, StatementKind};
 
 pub mod proto;
 pub use proto::{BatchResult, Col, Value};
 
+pub mod error;
+pub use error::{Error, RequestId};
+
+pub mod affinity;
+pub use affinity::Affinity;
+
+pub mod value_type;
+pub use value_type::{RuntimeType, ValueType};
+
+pub mod from_value;
+pub use from_value::FromValue;
+use sealed::Sealed as _;
+
 #[cfg(feature = "mapping_names_to_values_in_rows")]
 pub mod de;
+pub mod ser;
+
+#[cfg(feature = "testing")]
+pub mod testing;
 
 #[cfg(feature = "workers_backend")]
 pub use worker;
@@ -32,10 +49,30 @@ pub struct Row {
 }
 
 impl<'a> Row {
+    /// Creates a row of `values`, named after `columns`
+    #[cfg_attr(
+        not(feature = "mapping_names_to_values_in_rows"),
+        allow(unused_variables)
+    )]
+    fn new(values: Vec<Value>, columns: &[String]) -> Row {
+        #[cfg(feature = "mapping_names_to_values_in_rows")]
+        let value_map = columns
+            .iter()
+            .zip(&values)
+            .map(|(c, v)| (c.to_string(), v.clone()))
+            .collect();
+        Row {
+            values,
+            #[cfg(feature = "mapping_names_to_values_in_rows")]
+            value_map,
+        }
+    }
+
     /// Try to get a value by index from this row and convert it to the desired type
     ///
     /// Will return an error if the index is invalid or if the value cannot be converted to the
-    /// desired type
+    /// desired type. See [FromValue] for the supported types; `u64` and `i128` can also be
+    /// read from text, which is how integers beyond the range of `i64` are stored.
     ///
     /// # Examples
     /// ```
@@ -50,15 +87,112 @@ impl<'a> Row {
     /// let text : &str = row.try_get(1).unwrap();
     /// # }
     /// ```
-    pub fn try_get<V: TryFrom<&'a Value, Error = String>>(
-        &'a self,
-        index: usize,
-    ) -> anyhow::Result<V> {
-        let val = self
-            .values
-            .get(index)
-            .ok_or(anyhow::anyhow!("out of bound index {}", index))?;
-        val.try_into().map_err(|x: String| anyhow::anyhow!(x))
+    pub fn try_get<V: FromValue<'a>>(&'a self, index: usize) -> anyhow::Result<V> {
+        let val = index.value_in(self)?;
+        V::from_value(val).map_err(|x| anyhow::anyhow!(x))
+    }
+
+    /// Gets a value by index or column name from this row and converts it to the desired type
+    ///
+    /// Meant for quick scripts: panics if the column is missing or if the value cannot be
+    /// converted. Use [`Row::try_get()`] or [`Row::try_column()`] to handle errors instead.
+    /// Column names are only accepted with the `mapping_names_to_values_in_rows` feature.
+    ///
+    /// # Examples
+    /// ```
+    /// # async fn f() {
+    /// let db = libsql_client::SyncClient::in_memory().unwrap();
+    /// let rs = db.execute("select 1 as id, 'one' as name").unwrap();
+    /// let row = &rs.rows[0];
+    /// let id = row.get::<i64>(0);
+    /// let name = row.get::<&str>("name");
+    /// # }
+    /// ```
+    #[track_caller]
+    pub fn get<V: FromValue<'a>>(&'a self, index: impl RowIndex) -> V {
+        let result = index
+            .value_in(self)
+            .and_then(|val| V::from_value(val).map_err(|x| anyhow::anyhow!(x)));
+        match result {
+            Ok(v) => v,
+            Err(e) => panic!("Cannot get column {index} of row: {e}"),
+        }
+    }
+
+    /// Parses a column holding JSON text, as returned by SQLite's JSON functions,
+    /// into any type `T` that implements [`serde::de::DeserializeOwned`]
+    ///
+    /// Will return an error if the column is missing, if it is not text,
+    /// or if the text cannot be parsed into `T`. Column names are only accepted
+    /// with the `mapping_names_to_values_in_rows` feature. To bind values as JSON text,
+    /// see [`ser::json_value()`].
+    ///
+    /// # Examples
+    /// ```
+    /// # async fn f() -> anyhow::Result<()> {
+    /// let db = libsql_client::SyncClient::in_memory()?;
+    /// let rs = db.execute("select json_array(1, 2, 3) as ids")?;
+    /// let ids: Vec<i64> = rs.rows[0].try_get_json("ids")?;
+    /// assert_eq!(ids, [1, 2, 3]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn try_get_json<T: serde::de::DeserializeOwned>(
+        &self,
+        index: impl RowIndex,
+    ) -> anyhow::Result<T> {
+        let text = match index.value_in(self)? {
+            Value::Text { value } => value,
+            other => anyhow::bail!(
+                "Cannot parse {:?} value of column {index} as JSON",
+                other.runtime_type()
+            ),
+        };
+        serde_json::from_str(text)
+            .map_err(|e| anyhow::anyhow!("Invalid JSON in column {index}: {e}"))
+    }
+
+    /// Checks whether a value is `NULL`, given its index or column name, without converting it
+    ///
+    /// Returns `false` if the column is missing, since there is no `NULL` value in it either.
+    /// Column names are only accepted with the `mapping_names_to_values_in_rows` feature.
+    ///
+    /// # Examples
+    /// ```
+    /// # async fn f() {
+    /// let db = libsql_client::SyncClient::in_memory().unwrap();
+    /// let rs = db.execute("select 1 as id, null as name").unwrap();
+    /// let row = &rs.rows[0];
+    /// assert!(!row.is_null(0));
+    /// assert!(row.is_null("name"));
+    /// # }
+    /// ```
+    pub fn is_null(&self, index: impl RowIndex) -> bool {
+        matches!(index.value_in(self), Ok(Value::Null))
+    }
+
+    /// Borrows a text value, given its index or column name, without copying it
+    ///
+    /// Returns `None` if the column is missing or if the value is not text, e.g. `NULL`.
+    /// Unlike `try_get::<String>()`, nothing is allocated, which adds up for large result sets
+    /// passing text through. Column names are only accepted with the
+    /// `mapping_names_to_values_in_rows` feature.
+    ///
+    /// # Examples
+    /// ```
+    /// # async fn f() {
+    /// let db = libsql_client::SyncClient::in_memory().unwrap();
+    /// let rs = db.execute("select 1 as id, 'one' as name").unwrap();
+    /// let row = &rs.rows[0];
+    /// assert_eq!(row.get_str("name"), Some("one"));
+    /// assert_eq!(row.get_str(0), None);
+    /// # }
+    /// ```
+    pub fn get_str(&self, index: impl RowIndex) -> Option<&str> {
+        match index.value_in(self) {
+            Ok(Value::Text { value }) => Some(value),
+            _ => None,
+        }
     }
 
     /// Try to get a value given a column name from this row and convert it to the desired type
@@ -80,18 +214,121 @@ impl<'a> Row {
     /// # }
     /// ```
     #[cfg(feature = "mapping_names_to_values_in_rows")]
-    pub fn try_column<V: TryFrom<&'a Value, Error = String>>(
-        &'a self,
-        col: &str,
-    ) -> anyhow::Result<V> {
-        let val = self
-            .value_map
-            .get(col)
-            .ok_or(anyhow::anyhow!("column `{}` not present", col))?;
-        val.try_into().map_err(|x: String| anyhow::anyhow!(x))
+    pub fn try_column<V: FromValue<'a>>(&'a self, col: &str) -> anyhow::Result<V> {
+        let val = col.value_in(self)?;
+        V::from_value(val).map_err(|x| anyhow::anyhow!(x))
+    }
+
+    /// Returns a reader over the contents of a blob column, for piping it into a file or socket
+    ///
+    /// All backends receive the whole response before decoding it, so the blob is already in
+    /// memory and the reader borrows it without copying. Reading in chunks won't lower peak memory
+    /// usage, but it lets blobs be handled uniformly with other asynchronous byte sources.
+    ///
+    /// Will return an error if the column name is invalid or if the value is not a blob
+    ///
+    /// # Examples
+    /// ```
+    /// # async fn f() -> anyhow::Result<()> {
+    /// use futures::io::AsyncReadExt;
+    ///
+    /// let db = libsql_client::SyncClient::in_memory().unwrap();
+    /// let rs = db.execute("select x'deadbeef' as data").unwrap();
+    /// let mut reader = rs.rows[0].blob_reader("data")?;
+    /// let mut buf = Vec::new();
+    /// reader.read_to_end(&mut buf).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "mapping_names_to_values_in_rows")]
+    pub fn blob_reader(&self, col: &str) -> anyhow::Result<impl futures::io::AsyncRead + '_> {
+        let bytes: &[u8] = self.try_column(col)?;
+        Ok(futures::io::Cursor::new(bytes))
+    }
+
+    /// Converts this row into a map from column names to values, for consuming rows
+    /// without a static type to deserialize them into
+    ///
+    /// Columns sharing a name, as produced by joins without aliases, are not disambiguated:
+    /// the rightmost one wins, like in [`Row::try_column()`]. Alias such columns to keep them all.
+    ///
+    /// # Examples
+    /// ```
+    /// # async fn f() {
+    /// let db = libsql_client::SyncClient::in_memory().unwrap();
+    /// let rs = db.execute("select 1 as num, 'one' as str").unwrap();
+    /// let map = rs.rows.into_iter().next().unwrap().into_map();
+    /// assert_eq!(map.len(), 2);
+    /// # }
+    /// ```
+    #[cfg(feature = "mapping_names_to_values_in_rows")]
+    pub fn into_map(self) -> std::collections::HashMap<String, Value> {
+        self.value_map
+    }
+
+    /// Deserializes this row into any type `T` that implements [`serde::de::DeserializeOwned`],
+    /// matching struct fields to columns by name
+    ///
+    /// Same as [`de::from_row()`], see there for the supported field types. Will return an error
+    /// if a field has no matching column or if a value cannot be converted to the field's type.
+    ///
+    /// # Examples
+    /// ```
+    /// # fn f() -> anyhow::Result<()> {
+    /// #[derive(serde::Deserialize)]
+    /// struct User {
+    ///     id: i64,
+    ///     name: String,
+    /// }
+    ///
+    /// let db = libsql_client::SyncClient::in_memory()?;
+    /// let rs = db.execute("select 1 as id, 'alice' as name")?;
+    /// let user: User = rs.rows[0].deserialize_into()?;
+    /// assert_eq!(user.name, "alice");
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "mapping_names_to_values_in_rows")]
+    pub fn deserialize_into<T: serde::de::DeserializeOwned>(&self) -> anyhow::Result<T> {
+        de::from_row(self)
+    }
+}
+
+/// A column of a [Row], given either by its index or by its name. See [`Row::get()`].
+///
+/// This trait is sealed, it is only implemented for `usize` and `&str`.
+pub trait RowIndex: sealed::Sealed + std::fmt::Display {}
+
+mod sealed {
+    use crate::{Row, Value};
+
+    pub trait Sealed {
+        fn value_in<'a>(&self, row: &'a Row) -> anyhow::Result<&'a Value>;
+    }
+
+    impl Sealed for usize {
+        fn value_in<'a>(&self, row: &'a Row) -> anyhow::Result<&'a Value> {
+            row.values
+                .get(*self)
+                .ok_or(anyhow::anyhow!("out of bound index {}", self))
+        }
+    }
+
+    #[cfg(feature = "mapping_names_to_values_in_rows")]
+    impl Sealed for &str {
+        fn value_in<'a>(&self, row: &'a Row) -> anyhow::Result<&'a Value> {
+            row.value_map
+                .get(*self)
+                .ok_or(anyhow::anyhow!("column `{}` not present", self))
+        }
     }
 }
 
+impl RowIndex for usize {}
+
+#[cfg(feature = "mapping_names_to_values_in_rows")]
+impl RowIndex for &str {}
+
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
 /// Represents the result of a database query
 ///
@@ -115,12 +352,311 @@ pub struct ResultSet {
     /// name of the columns present in this `ResultSet`.
     pub columns: Vec<String>,
     /// One entry per row returned from the database. See [Row] for details.
+    /// Besides queries, this includes rows returned by the `RETURNING` clause
+    /// of `INSERT`, `UPDATE` and `DELETE` statements.
     pub rows: Vec<Row>,
     /// How many rows were changed by this statement
     pub rows_affected: u64,
     /// the rowid for last insertion. See <https://www.sqlite.org/c3ref/last_insert_rowid.html> for
     /// details
     pub last_insert_rowid: Option<i64>,
+    /// See [`ResultSet::request_id()`]
+    #[serde(default)]
+    request_id: Option<String>,
+    /// See [`ResultSet::timing()`]
+    #[serde(default)]
+    timing: Timing,
+    /// See [`ResultSet::replication_index()`]
+    #[serde(default)]
+    replication_index: Option<u64>,
+}
+
+/// Time spent on the request which produced a [ResultSet], to tell slow networks from slow queries
+///
+/// Only measured for statements executed on their own by the HTTP backends,
+/// and left at zero otherwise, e.g. for batches and local databases.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Timing {
+    /// Round-trip time of the request as measured by the client, which includes
+    /// the time spent by the server
+    pub network: std::time::Duration,
+    /// Time spent executing the statement, if reported by the server
+    pub server: Option<std::time::Duration>,
+}
+
+impl ResultSet {
+    /// Returns the number of columns present in this `ResultSet`,
+    /// which is known even if no rows were returned
+    pub fn column_count(&self) -> usize {
+        self.columns.len()
+    }
+
+    /// Returns the names of the columns present in this `ResultSet`,
+    /// which are known even if no rows were returned
+    pub fn column_names(&self) -> Vec<&str> {
+        self.columns.iter().map(String::as_str).collect()
+    }
+
+    /// Returns the value of the `X-Request-Id` header of the HTTP request which produced
+    /// this `ResultSet`, for correlating it with the logs of the server.
+    /// `None` for other backends and for batches.
+    pub fn request_id(&self) -> Option<&str> {
+        self.request_id.as_deref()
+    }
+
+    /// Returns the time spent producing this `ResultSet`, see [Timing]
+    pub fn timing(&self) -> Timing {
+        self.timing
+    }
+
+    /// Returns the replication index of the database after producing this `ResultSet`,
+    /// if the server reported one
+    ///
+    /// Servers with replicas report how far the database they answered from has replicated.
+    /// Keeping the greatest index seen, e.g. after a write, tells whether a later read from
+    /// a replica reflects that write. Only reported to statements executed on their own
+    /// by the HTTP backends, and `None` otherwise, e.g. for batches and local databases.
+    pub fn replication_index(&self) -> Option<u64> {
+        self.replication_index
+    }
+
+    /// Returns the row at `index`, counting from the end if negative like in Python:
+    /// `-1` is the last row, `-2` the one before it, and so on.
+    ///
+    /// Returns `None` if `index` is out of range in either direction.
+    ///
+    /// # Examples
+    /// ```
+    /// # async fn f() {
+    /// let db = libsql_client::SyncClient::in_memory().unwrap();
+    /// let rs = db.execute("select 1 union all select 2 union all select 3").unwrap();
+    /// assert_eq!(rs.row(-1).unwrap().get::<i64>(0), 3);
+    /// assert_eq!(rs.row(0).unwrap().get::<i64>(0), 1);
+    /// assert!(rs.row(-4).is_none());
+    /// # }
+    /// ```
+    pub fn row(&self, index: isize) -> Option<&Row> {
+        let index = if index < 0 {
+            self.rows.len().checked_sub(index.unsigned_abs())?
+        } else {
+            index as usize
+        };
+        self.rows.get(index)
+    }
+
+    /// Returns the last row, or `None` if there are no rows. Same as `row(-1)`.
+    pub fn last_row(&self) -> Option<&Row> {
+        self.rows.last()
+    }
+
+    /// Consumes this `ResultSet`, returning just the grid of cells, one `Vec` per row.
+    /// Column names are not included, see [`ResultSet::column_names()`].
+    ///
+    /// # Examples
+    /// ```
+    /// # async fn f() {
+    /// let db = libsql_client::SyncClient::in_memory().unwrap();
+    /// let rs = db.execute("select 1, 2").unwrap();
+    /// let grid = rs.into_values();
+    /// assert_eq!(grid.len(), 1);
+    /// assert_eq!(grid[0].len(), 2);
+    /// # }
+    /// ```
+    pub fn into_values(self) -> Vec<Vec<Value>> {
+        self.rows.into_iter().map(|row| row.values).collect()
+    }
+
+    /// Appends the rows of `other` to this `ResultSet`, e.g. to accumulate the pages of a query
+    ///
+    /// Fails if the columns of `other` differ from the columns of this `ResultSet`, in which case
+    /// this `ResultSet` is left unchanged. Affected row counts are summed, and the
+    /// `last_insert_rowid` of `other` replaces this one if set. The `request_id` and `timing`
+    /// of this `ResultSet` are kept.
+    ///
+    /// # Examples
+    /// ```
+    /// # async fn f() -> anyhow::Result<()> {
+    /// let db = libsql_client::SyncClient::in_memory()?;
+    /// let mut rs = db.execute("select 1 as id")?;
+    /// rs.append(db.execute("select 2 as id")?)?;
+    /// assert_eq!(rs.rows.len(), 2);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn append(&mut self, other: ResultSet) -> anyhow::Result<()> {
+        if self.columns != other.columns {
+            anyhow::bail!(
+                "Cannot append a result with columns {:?} to a result with columns {:?}",
+                other.columns,
+                self.columns
+            );
+        }
+        self.rows.extend(other.rows);
+        self.rows_affected += other.rows_affected;
+        if other.last_insert_rowid.is_some() {
+            self.last_insert_rowid = other.last_insert_rowid;
+        }
+        Ok(())
+    }
+
+    /// Returns a new `ResultSet` with only the given columns, in the given order,
+    /// e.g. to display a subset of the columns without rewriting the query
+    ///
+    /// Fails if any of the columns is not present. If several columns share a name,
+    /// the leftmost one is picked. Columns may be listed more than once. Affected row
+    /// counts and other metadata are kept.
+    ///
+    /// # Examples
+    /// ```
+    /// # async fn f() -> anyhow::Result<()> {
+    /// let db = libsql_client::SyncClient::in_memory()?;
+    /// let rs = db.execute("select 1 as id, 'alice' as name, 42 as age")?;
+    /// let projected = rs.project(&["name", "id"])?;
+    /// assert_eq!(projected.columns, ["name", "id"]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn project(&self, columns: &[&str]) -> anyhow::Result<ResultSet> {
+        let indices = columns
+            .iter()
+            .map(|name| {
+                self.columns
+                    .iter()
+                    .position(|c| c == name)
+                    .ok_or_else(|| anyhow::anyhow!("column `{name}` not present"))
+            })
+            .collect::<anyhow::Result<Vec<usize>>>()?;
+        let projected: Vec<String> = columns.iter().map(|c| c.to_string()).collect();
+        let rows = self
+            .rows
+            .iter()
+            .map(|row| {
+                let values = indices.iter().map(|&i| row.values[i].clone()).collect();
+                Row::new(values, &projected)
+            })
+            .collect();
+        Ok(ResultSet {
+            columns: projected,
+            rows,
+            rows_affected: self.rows_affected,
+            last_insert_rowid: self.last_insert_rowid,
+            request_id: self.request_id.clone(),
+            timing: self.timing,
+            replication_index: self.replication_index,
+        })
+    }
+
+    /// Renders this `ResultSet` as an ASCII table, for human consumption.
+    ///
+    /// NULLs are rendered as `NULL`, blobs as `<blob N bytes>`, and text longer than 32
+    /// characters is truncated with an ellipsis. The same table is produced by formatting
+    /// the `ResultSet` with `{}`. To pick a different width, see [`ResultSet::to_table_string_with_width()`].
+    ///
+    /// # Examples
+    /// ```
+    /// # async fn f() {
+    /// let db = libsql_client::SyncClient::in_memory().unwrap();
+    /// let rs = db.execute("select 1 as id, 'one' as name").unwrap();
+    /// println!("{}", rs.to_table_string());
+    /// # }
+    /// ```
+    pub fn to_table_string(&self) -> String {
+        self.to_table_string_with_width(32)
+    }
+
+    /// Renders this `ResultSet` as an ASCII table, truncating cells to `max_width` characters.
+    /// See [`ResultSet::to_table_string()`] for details.
+    pub fn to_table_string_with_width(&self, max_width: usize) -> String {
+        let header: Vec<String> = self
+            .columns
+            .iter()
+            .map(|c| truncate(c, max_width))
+            .collect();
+        let rows: Vec<Vec<String>> = self
+            .rows
+            .iter()
+            .map(|row| {
+                row.values
+                    .iter()
+                    .map(|v| match v {
+                        Value::Null => "NULL".to_string(),
+                        Value::Integer { value } => value.to_string(),
+                        Value::Float { value } => value.to_string(),
+                        Value::Text { value } => truncate(value, max_width),
+                        Value::Blob { value } => format!("<blob {} bytes>", value.len()),
+                    })
+                    .collect()
+            })
+            .collect();
+        let mut widths: Vec<usize> = header.iter().map(|h| h.chars().count()).collect();
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+
+        let separator: String = widths
+            .iter()
+            .map(|w| format!("+{}", "-".repeat(w + 2)))
+            .chain(std::iter::once("+\n".to_string()))
+            .collect();
+        let line = |cells: &[String]| -> String {
+            cells
+                .iter()
+                .zip(&widths)
+                .map(|(cell, w)| format!("| {cell:<w$} "))
+                .chain(std::iter::once("|\n".to_string()))
+                .collect()
+        };
+        let mut table = separator.clone();
+        table.push_str(&line(&header));
+        table.push_str(&separator);
+        for row in &rows {
+            table.push_str(&line(row));
+        }
+        table.push_str(&separator);
+        table
+    }
+}
+
+/// Returns the total number of rows changed by a batch of statements,
+/// e.g. the results returned by [`Client::batch()`].
+///
+/// Like SQLite's [`changes()`](https://www.sqlite.org/c3ref/changes.html), only rows
+/// directly modified by `INSERT`, `UPDATE` or `DELETE` statements are counted;
+/// changes made by triggers or foreign key actions are not.
+///
+/// # Examples
+/// ```
+/// # async fn f() {
+/// let db = libsql_client::SyncClient::in_memory().unwrap();
+/// let results = db
+///     .batch([
+///         "CREATE TABLE t(x)",
+///         "INSERT INTO t VALUES (1), (2)",
+///         "UPDATE t SET x = x + 1",
+///     ])
+///     .unwrap();
+/// assert_eq!(libsql_client::total_rows_affected(&results), 4);
+/// # }
+/// ```
+pub fn total_rows_affected(results: &[ResultSet]) -> u64 {
+    results.iter().map(|rs| rs.rows_affected).sum()
+}
+
+fn truncate(s: &str, max_width: usize) -> String {
+    if s.chars().count() <= max_width {
+        return s.to_string();
+    }
+    let mut truncated: String = s.chars().take(max_width.saturating_sub(1)).collect();
+    truncated.push('…');
+    truncated
+}
+
+impl std::fmt::Display for ResultSet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.to_table_string())
+    }
 }
 
 impl std::convert::From<proto::StmtResult> for ResultSet {
@@ -133,25 +669,16 @@ impl std::convert::From<proto::StmtResult> for ResultSet {
         let rows = value
             .rows
             .into_iter()
-            .map(|values| {
-                #[cfg(feature = "mapping_names_to_values_in_rows")]
-                let value_map = columns
-                    .iter()
-                    .enumerate()
-                    .map(|(i, c)| (c.to_string(), values[i].clone()))
-                    .collect();
-                Row {
-                    values,
-                    #[cfg(feature = "mapping_names_to_values_in_rows")]
-                    value_map,
-                }
-            })
+            .map(|values| Row::new(values, &columns))
             .collect();
         ResultSet {
             columns,
             rows,
             rows_affected: value.affected_row_count,
             last_insert_rowid: value.last_insert_rowid,
+            request_id: None,
+            timing: Timing::default(),
+            replication_index: None,
         }
     }
 }
@@ -163,10 +690,41 @@ pub use client::{Client, Config, SyncClient};
     feature = "reqwest_backend",
     feature = "workers_backend",
     feature = "spin_backend",
+    feature = "tower",
 ))]
 pub mod http;
+
+#[cfg(any(
+    feature = "reqwest_backend",
+    feature = "workers_backend",
+    feature = "spin_backend",
+    feature = "tower",
+))]
+pub mod circuit_breaker;
+
+pub mod clock;
+
 pub mod transaction;
-pub use transaction::{SyncTransaction, Transaction};
+pub use transaction::{SyncTransaction, Transaction, TxMode};
+
+pub mod cache;
+
+pub mod read_only;
+
+pub mod allowlist;
+
+pub mod prefix;
+
+pub mod fanout;
+
+pub mod bulk;
+
+pub mod export;
+
+pub mod import;
+
+#[cfg(feature = "adapters")]
+pub mod adapters;
 
 #[cfg(feature = "workers_backend")]
 pub mod workers;
@@ -174,16 +732,26 @@ pub mod workers;
 #[cfg(feature = "reqwest_backend")]
 pub mod reqwest;
 
+#[cfg(all(unix, feature = "reqwest_backend"))]
+pub mod unix;
+
 #[cfg(feature = "local_backend")]
 pub mod local;
 
 #[cfg(feature = "spin_backend")]
 pub mod spin;
 
+#[cfg(feature = "tower")]
+pub mod tower;
+
+mod dsn;
 #[cfg(feature = "hrana_backend")]
 pub mod hrana;
 mod utils;
 
+#[cfg(all(test, feature = "reqwest_backend"))]
+mod mock_server;
+
 /// A macro for passing parameters to statements without having to manually
 /// define their types.
 ///
@@ -207,3 +775,470 @@ macro_rules! args {
         &[$($param.into()),+] as &[libsql_client::Value]
     };
 }
+
+#[cfg(all(test, feature = "local_backend"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_try_get_wide_integers() {
+        let db = SyncClient::in_memory().unwrap();
+        db.execute("CREATE TABLE counters(n TEXT)").unwrap();
+        db.execute(Statement::with_args(
+            "INSERT INTO counters VALUES (?)",
+            &[u64::MAX.to_string()],
+        ))
+        .unwrap();
+        let rs = db.execute("SELECT n FROM counters").unwrap();
+        assert_eq!(rs.rows[0].try_get::<u64>(0).unwrap(), u64::MAX);
+        assert_eq!(rs.rows[0].try_get::<i128>(0).unwrap(), u64::MAX as i128);
+        assert!(rs.rows[0].try_get::<i64>(0).is_err());
+    }
+
+    #[test]
+    fn test_constraint_violation() {
+        let db = SyncClient::in_memory().unwrap();
+        db.execute("CREATE TABLE users(email TEXT UNIQUE, name TEXT NOT NULL)")
+            .unwrap();
+        db.execute("INSERT INTO users VALUES ('a@b.c', 'Alice')")
+            .unwrap();
+        let sql_error = |sql: &str| {
+            let err = db.execute(sql).unwrap_err();
+            err.downcast_ref::<Error>().unwrap().clone()
+        };
+
+        let duplicate = sql_error("INSERT INTO users VALUES ('a@b.c', 'Bob')");
+        assert_eq!(duplicate.sqlite_code(), Some(2067));
+        assert!(duplicate.is_constraint_violation());
+        assert!(duplicate.to_string().contains("UNIQUE"), "{duplicate}");
+        let missing_name = sql_error("INSERT INTO users VALUES ('d@e.f', NULL)");
+        assert!(missing_name.is_constraint_violation());
+        let syntax = sql_error("INSERT INTO");
+        assert!(!syntax.is_constraint_violation());
+    }
+
+    #[test]
+    fn test_insert_or_ignore() {
+        let db = SyncClient::in_memory().unwrap();
+        db.execute("CREATE TABLE users(email TEXT PRIMARY KEY, name TEXT)")
+            .unwrap();
+        let insert = |name: &str| {
+            Statement::insert_or_ignore(
+                "users",
+                &["email", "name"],
+                &[Value::from("a@b.c"), Value::from(name)],
+            )
+            .unwrap()
+        };
+        assert_eq!(db.execute(insert("Alice")).unwrap().rows_affected, 1);
+        // The duplicate is skipped without an error
+        assert_eq!(db.execute(insert("Bob")).unwrap().rows_affected, 0);
+        let rs = db.execute("SELECT name FROM users").unwrap();
+        assert_eq!(rs.rows.len(), 1);
+        assert_eq!(rs.rows[0].try_get::<&str>(0).unwrap(), "Alice");
+    }
+
+    #[test]
+    fn test_is_null() {
+        let db = SyncClient::in_memory().unwrap();
+        let rs = db
+            .execute("SELECT 1 AS id, NULL AS name, '' AS email")
+            .unwrap();
+        let row = &rs.rows[0];
+        assert!(!row.is_null(0));
+        assert!(row.is_null(1));
+        assert!(!row.is_null(2));
+        assert!(!row.is_null(3));
+        #[cfg(feature = "mapping_names_to_values_in_rows")]
+        {
+            assert!(!row.is_null("id"));
+            assert!(row.is_null("name"));
+            assert!(!row.is_null("email"));
+            assert!(!row.is_null("missing"));
+        }
+    }
+
+    #[test]
+    fn test_try_get_f32() {
+        let db = SyncClient::in_memory().unwrap();
+        let rs = db
+            .execute(Statement::with_args(
+                "SELECT ?, 1e300",
+                &[std::f32::consts::PI],
+            ))
+            .unwrap();
+        assert_eq!(rs.rows[0].try_get::<f32>(0).unwrap(), std::f32::consts::PI);
+        assert!(rs.rows[0].try_get::<f32>(1).is_err());
+    }
+
+    #[cfg(feature = "decimal")]
+    #[test]
+    fn test_try_get_decimals() {
+        use rust_decimal::Decimal;
+
+        let db = SyncClient::in_memory().unwrap();
+        db.execute("CREATE TABLE prices(amount TEXT)").unwrap();
+        let amounts = [
+            Decimal::new(12345, 2),
+            Decimal::new(1, 1),
+            "0.1000000000000000000000000001".parse().unwrap(),
+        ];
+        for amount in amounts {
+            db.execute(Statement::with_args(
+                "INSERT INTO prices VALUES (?)",
+                &[amount.to_string()],
+            ))
+            .unwrap();
+        }
+        let rs = db.execute("SELECT amount FROM prices").unwrap();
+        let read: Vec<Decimal> = rs.rows.iter().map(|r| r.try_get(0).unwrap()).collect();
+        assert_eq!(read, amounts);
+        // No binary float error creeps in
+        assert_eq!(read[1] + read[1] + read[1], Decimal::new(3, 1));
+        assert_eq!(read[0].to_string(), "123.45");
+    }
+
+    #[cfg(feature = "mapping_names_to_values_in_rows")]
+    #[test]
+    fn test_get() {
+        let db = SyncClient::in_memory().unwrap();
+        let rs = db.execute("SELECT 42 AS id, 'alice' AS name").unwrap();
+        let row = &rs.rows[0];
+        assert_eq!(row.get::<i64>(0), 42);
+        assert_eq!(row.get::<i64>("id"), 42);
+        assert_eq!(row.get::<&str>("name"), "alice");
+
+        let panic_message = |f: &dyn Fn()| {
+            let payload = std::panic::catch_unwind(std::panic::AssertUnwindSafe(f)).unwrap_err();
+            payload.downcast_ref::<String>().unwrap().clone()
+        };
+        assert_eq!(
+            panic_message(&|| {
+                row.get::<i64>("missing");
+            }),
+            "Cannot get column missing of row: column `missing` not present"
+        );
+        assert_eq!(
+            panic_message(&|| {
+                row.get::<i64>(5);
+            }),
+            "Cannot get column 5 of row: out of bound index 5"
+        );
+        assert!(panic_message(&|| {
+            row.get::<i64>("name");
+        })
+        .contains("cannot transform"));
+    }
+
+    #[test]
+    fn test_project() {
+        let db = SyncClient::in_memory().unwrap();
+        let rs = db
+            .execute("SELECT 1 AS id, 'alice' AS name, 42 AS age UNION ALL SELECT 2, 'bob', 69")
+            .unwrap();
+        let projected = rs.project(&["name", "id"]).unwrap();
+        assert_eq!(projected.columns, ["name", "id"]);
+        assert_eq!(projected.rows.len(), 2);
+        assert_eq!(projected.rows[1].try_get::<&str>(0).unwrap(), "bob");
+        assert_eq!(projected.rows[1].try_get::<i64>(1).unwrap(), 2);
+        #[cfg(feature = "mapping_names_to_values_in_rows")]
+        {
+            assert_eq!(
+                projected.rows[0].try_column::<&str>("name").unwrap(),
+                "alice"
+            );
+            assert!(projected.rows[0].try_column::<i64>("age").is_err());
+        }
+
+        let err = rs.project(&["name", "email"]).unwrap_err();
+        assert_eq!(err.to_string(), "column `email` not present");
+    }
+
+    #[test]
+    fn test_json_columns() {
+        #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+        struct Settings {
+            theme: String,
+            font_size: i64,
+        }
+
+        let db = SyncClient::in_memory().unwrap();
+        let rs = db
+            .execute("SELECT json_array(1, 2, 3) AS ids, 'not json' AS text, 7 AS num")
+            .unwrap();
+        let row = &rs.rows[0];
+        assert_eq!(row.try_get_json::<Vec<i64>>(0).unwrap(), [1, 2, 3]);
+        assert!(row.try_get_json::<Vec<i64>>(1).is_err());
+        assert!(row.try_get_json::<i64>(2).is_err());
+        assert!(row.try_get_json::<Vec<String>>(0).is_err());
+
+        let settings = Settings {
+            theme: "dark".into(),
+            font_size: 14,
+        };
+        db.execute("CREATE TABLE users(settings TEXT)").unwrap();
+        db.execute(Statement::with_args(
+            "INSERT INTO users VALUES (?)",
+            &[ser::json_value(&settings).unwrap()],
+        ))
+        .unwrap();
+        let rs = db
+            .execute("SELECT json_extract(settings, '$.font_size'), settings FROM users")
+            .unwrap();
+        assert_eq!(rs.rows[0].try_get::<i64>(0).unwrap(), 14);
+        assert_eq!(rs.rows[0].try_get_json::<Settings>(1).unwrap(), settings);
+    }
+
+    #[test]
+    fn test_row_by_negative_index() {
+        let db = SyncClient::in_memory().unwrap();
+        let rs = db
+            .execute("SELECT 1 AS n UNION ALL SELECT 2 UNION ALL SELECT 3")
+            .unwrap();
+        assert_eq!(rs.last_row().unwrap().try_get::<i64>(0).unwrap(), 3);
+        assert!(std::ptr::eq(rs.row(-1).unwrap(), rs.last_row().unwrap()));
+        assert_eq!(rs.row(-3).unwrap().try_get::<i64>(0).unwrap(), 1);
+        assert_eq!(rs.row(2).unwrap().try_get::<i64>(0).unwrap(), 3);
+        assert!(rs.row(3).is_none());
+        assert!(rs.row(-4).is_none());
+
+        let rs = db.execute("SELECT 1 WHERE 0").unwrap();
+        assert!(rs.last_row().is_none());
+        assert!(rs.row(-1).is_none());
+        assert!(rs.row(0).is_none());
+    }
+
+    #[test]
+    fn test_get_str() {
+        let db = SyncClient::in_memory().unwrap();
+        let rs = db
+            .execute("SELECT 'hello' AS greeting, NULL AS missing, 42 AS answer")
+            .unwrap();
+        let row = &rs.rows[0];
+        // The result borrows from the row, so no copy of the text was made
+        let greeting: &str = row.get_str(0).unwrap();
+        let Value::Text { value } = &row.values[0] else {
+            panic!("expected text");
+        };
+        assert!(std::ptr::eq(greeting, value.as_str()));
+        assert_eq!(greeting, "hello");
+        assert_eq!(row.get_str(1), None);
+        assert_eq!(row.get_str(2), None);
+        assert_eq!(row.get_str(3), None);
+        #[cfg(feature = "mapping_names_to_values_in_rows")]
+        {
+            assert_eq!(row.get_str("greeting"), Some("hello"));
+            assert_eq!(row.get_str("missing"), None);
+            assert_eq!(row.get_str("nope"), None);
+        }
+    }
+
+    #[cfg(feature = "mapping_names_to_values_in_rows")]
+    #[test]
+    fn test_deserialize_into() {
+        #[derive(Debug, serde::Deserialize)]
+        struct User {
+            id: i64,
+            name: String,
+            email: Option<String>,
+        }
+
+        let db = SyncClient::in_memory().unwrap();
+        let rs = db
+            .execute("SELECT 42 AS id, 'alice' AS name, NULL AS email")
+            .unwrap();
+        let user: User = rs.rows[0].deserialize_into().unwrap();
+        assert_eq!(user.id, 42);
+        assert_eq!(user.name, "alice");
+        assert_eq!(user.email, None);
+
+        let rs = db
+            .execute("SELECT 'forty-two' AS id, 'alice' AS name, NULL AS email")
+            .unwrap();
+        assert!(rs.rows[0].deserialize_into::<User>().is_err());
+    }
+
+    #[cfg(feature = "mapping_names_to_values_in_rows")]
+    #[test]
+    fn test_into_map() {
+        let db = SyncClient::in_memory().unwrap();
+        let rs = db.execute("SELECT 42 AS id, 'alice' AS name").unwrap();
+        let map = rs.rows[0].clone().into_map();
+        assert_eq!(map.len(), 2);
+        assert!(matches!(map["id"], Value::Integer { value: 42 }));
+        assert!(matches!(&map["name"], Value::Text { value } if value == "alice"));
+
+        db.batch([
+            "CREATE TABLE users(id INTEGER, name TEXT)",
+            "CREATE TABLE orders(id INTEGER, user_id INTEGER)",
+            "INSERT INTO users VALUES (1, 'alice')",
+            "INSERT INTO orders VALUES (7, 1)",
+        ])
+        .unwrap();
+        let rs = db
+            .execute("SELECT * FROM users JOIN orders ON orders.user_id = users.id")
+            .unwrap();
+        assert_eq!(rs.columns, ["id", "name", "id", "user_id"]);
+        let map = rs.rows[0].clone().into_map();
+        assert_eq!(map.len(), 3);
+        // The id of the order, which comes last, wins
+        assert!(matches!(map["id"], Value::Integer { value: 7 }));
+    }
+
+    #[test]
+    fn test_append() {
+        let db = SyncClient::in_memory().unwrap();
+        db.execute("CREATE TABLE t(id INTEGER PRIMARY KEY, name TEXT)")
+            .unwrap();
+        let mut rs = db
+            .execute("INSERT INTO t VALUES (1, 'a'), (2, 'b') RETURNING id, name")
+            .unwrap();
+        let page = db
+            .execute("INSERT INTO t VALUES (3, 'c') RETURNING id, name")
+            .unwrap();
+        rs.append(page).unwrap();
+        let ids: Vec<i64> = rs.rows.iter().map(|r| r.try_get(0).unwrap()).collect();
+        assert_eq!(ids, [1, 2, 3]);
+        assert_eq!(rs.rows_affected, 3);
+        assert_eq!(rs.last_insert_rowid, Some(3));
+
+        let mismatch = db.execute("SELECT name FROM t").unwrap();
+        let err = rs.append(mismatch).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            r#"Cannot append a result with columns ["name"] to a result with columns ["id", "name"]"#
+        );
+        assert_eq!(rs.rows.len(), 3);
+    }
+
+    #[test]
+    fn test_runtime_types() {
+        let db = SyncClient::in_memory().unwrap();
+        db.batch(["CREATE TABLE t(x INTEGER)", "INSERT INTO t VALUES (1), (2)"])
+            .unwrap();
+        let rs = db
+            .execute("SELECT AVG(x), COUNT(*), MAX(NULL), 'a' || x, x'00' FROM t")
+            .unwrap();
+        let types: Vec<ValueType> = rs.rows[0]
+            .values
+            .iter()
+            .map(RuntimeType::runtime_type)
+            .collect();
+        assert_eq!(
+            types,
+            [
+                ValueType::Real,
+                ValueType::Integer,
+                ValueType::Null,
+                ValueType::Text,
+                ValueType::Blob
+            ]
+        );
+    }
+
+    #[test]
+    fn test_total_rows_affected() {
+        let db = SyncClient::in_memory().unwrap();
+        db.batch([
+            "CREATE TABLE t(x)",
+            "INSERT INTO t VALUES (1), (1), (2), (2), (2), (2), (2)",
+        ])
+        .unwrap();
+        let results = db
+            .batch([
+                "UPDATE t SET x = 10 WHERE x = 1",
+                "UPDATE t SET x = 0 WHERE x = 3",
+                "UPDATE t SET x = 20 WHERE x = 2",
+            ])
+            .unwrap();
+        let counts: Vec<u64> = results.iter().map(|rs| rs.rows_affected).collect();
+        assert_eq!(counts, [2, 0, 5]);
+        assert_eq!(total_rows_affected(&results), 7);
+        assert_eq!(total_rows_affected(&[]), 0);
+    }
+
+    #[test]
+    fn test_columns_of_empty_result() {
+        let db = SyncClient::in_memory().unwrap();
+        db.execute("CREATE TABLE users(id INTEGER, name TEXT, email TEXT)")
+            .unwrap();
+        let rs = db
+            .execute("SELECT id, name AS n, email FROM users")
+            .unwrap();
+        assert!(rs.rows.is_empty());
+        assert_eq!(rs.column_count(), 3);
+        assert_eq!(rs.column_names(), ["id", "n", "email"]);
+    }
+
+    #[test]
+    fn test_into_values() {
+        let db = SyncClient::in_memory().unwrap();
+        let rs = db
+            .execute("SELECT 1 AS a, 'x' AS b, NULL AS c UNION ALL SELECT 2, 'y', 3.5")
+            .unwrap();
+        assert_eq!(rs.column_names(), ["a", "b", "c"]);
+        let grid = rs.into_values();
+        assert_eq!(grid.len(), 2);
+        assert!(grid.iter().all(|row| row.len() == 3));
+        assert!(matches!(grid[0][0], Value::Integer { value: 1 }));
+        assert!(matches!(&grid[0][1], Value::Text { value } if value == "x"));
+        assert!(matches!(grid[0][2], Value::Null));
+        assert!(matches!(grid[1][0], Value::Integer { value: 2 }));
+        assert!(matches!(&grid[1][1], Value::Text { value } if value == "y"));
+        assert!(matches!(grid[1][2], Value::Float { value } if value == 3.5));
+    }
+
+    #[cfg(feature = "mapping_names_to_values_in_rows")]
+    #[tokio::test]
+    async fn test_blob_reader() {
+        use futures::io::AsyncReadExt;
+
+        let db = SyncClient::in_memory().unwrap();
+        let blob: Vec<u8> = (0..=255).cycle().take(10_000).collect();
+        db.execute("CREATE TABLE files(data BLOB, name TEXT)")
+            .unwrap();
+        db.execute(Statement::with_args(
+            "INSERT INTO files VALUES (?, ?)",
+            &[Value::from(blob.clone()), Value::from("f")],
+        ))
+        .unwrap();
+        let rs = db.execute("SELECT * FROM files").unwrap();
+
+        let mut reader = rs.rows[0].blob_reader("data").unwrap();
+        let mut chunk = [0u8; 1024];
+        let mut read = Vec::new();
+        loop {
+            let n = reader.read(&mut chunk).await.unwrap();
+            if n == 0 {
+                break;
+            }
+            read.extend_from_slice(&chunk[..n]);
+        }
+        assert_eq!(read, blob);
+
+        assert!(rs.rows[0].blob_reader("name").is_err());
+        assert!(rs.rows[0].blob_reader("missing").is_err());
+    }
+
+    #[test]
+    fn test_to_table_string() {
+        let db = SyncClient::in_memory().unwrap();
+        let rs = db
+            .execute(
+                "SELECT 1 AS id, 'alice' AS name, x'0102' AS data \
+                 UNION ALL SELECT 22, 'a very long name indeed', NULL",
+            )
+            .unwrap();
+        let expected = "\
++----+------------+----------------+
+| id | name       | data           |
++----+------------+----------------+
+| 1  | alice      | <blob 2 bytes> |
+| 22 | a very lo… | NULL           |
++----+------------+----------------+
+";
+        assert_eq!(rs.to_table_string_with_width(10), expected);
+        assert_eq!(rs.to_string(), rs.to_table_string());
+        assert!(rs.to_string().contains("| a very long name indeed |"));
+    }
+}
@@ -10,6 +10,7 @@ use fallible_iterator::FallibleIterator;
 pub struct Client {
     db: libsql::Database,
     conn: libsql::Connection,
+    pub(crate) options: crate::client::Options,
 }
 
 impl std::fmt::Debug for Client {
@@ -52,14 +53,22 @@ impl Client {
     pub fn new(path: impl Into<String>) -> anyhow::Result<Self> {
         let db = libsql::Database::open(path.into())?;
         let conn = db.connect()?;
-        Ok(Self { db, conn })
+        Ok(Self {
+            db,
+            conn,
+            options: Default::default(),
+        })
     }
 
     /// Establishes a new in-memory database and connects to it.
     pub fn in_memory() -> anyhow::Result<Self> {
         let db = libsql::Database::open(":memory:")?;
         let conn = db.connect()?;
-        Ok(Self { db, conn })
+        Ok(Self {
+            db,
+            conn,
+            options: Default::default(),
+        })
     }
 
     pub fn from_env() -> anyhow::Result<Self> {
@@ -73,6 +82,17 @@ impl Client {
         Self::new(path)
     }
 
+    /// Sets the busy timeout of SQLite on the connection, making statements wait up to
+    /// `ms` milliseconds for locks held by other connections instead of failing
+    /// with `database is locked`.
+    ///
+    /// # Arguments
+    /// * `ms` - busy timeout in milliseconds
+    pub fn with_busy_timeout(self, ms: u64) -> Result<Self> {
+        self.execute(crate::client::busy_timeout_pragma(ms))?;
+        Ok(self)
+    }
+
     pub async fn sync(&self) -> anyhow::Result<usize> {
         self.db.sync().await.map_err(|e| anyhow::anyhow!("{}", e))
     }
@@ -100,26 +120,12 @@ impl Client {
         let mut step_results = vec![];
         let mut step_errors = vec![];
         for stmt in stmts {
-            let stmt = stmt.into();
-            let sql_string = &stmt.sql;
-            let params: libsql::Params = stmt
-                .args
-                .into_iter()
-                .map(ValueWrapper)
-                .map(libsql::Value::from)
-                .collect::<Vec<_>>()
-                .into();
-            let stmt = self.conn.prepare(sql_string)?;
-            let cols: Vec<Col> = stmt
-                .columns()
-                .into_iter()
-                .map(|c| Col {
-                    name: Some(c.name().to_string()),
-                })
-                .collect();
-            let mut rows = Vec::new();
-            let input_rows = match stmt.query(&params) {
-                Ok(rows) => rows,
+            match self.execute_one(stmt.into()) {
+                Ok(stmt_result) => {
+                    step_results.push(Some(stmt_result));
+                    step_errors.push(None);
+                }
+                Err(e @ libsql::Error::PrepareFailed(..)) => return Err(e.into()),
                 Err(e) => {
                     step_results.push(None);
                     step_errors.push(Some(proto::Error {
@@ -127,38 +133,7 @@ impl Client {
                     }));
                     break;
                 }
-            };
-            while let Some(row) = input_rows.next()? {
-                let cells = (0..cols.len())
-                    .map(|i| ValueWrapper::from(row.get_value(i as i32).unwrap()).0)
-                    .collect();
-                rows.push(cells)
             }
-            let parser = Parser::new(sql_string.as_bytes());
-            let cmd = parser.last();
-
-            let last_insert_rowid = match cmd {
-                Ok(Some(Cmd::Stmt(Stmt::Insert { .. }))) => Some(self.conn.last_insert_rowid()),
-                _ => None,
-            };
-
-            let affected_row_count = match cmd {
-                Ok(Some(
-                    Cmd::Stmt(Stmt::Insert { .. })
-                    | Cmd::Stmt(Stmt::Update { .. })
-                    | Cmd::Stmt(Stmt::Delete { .. }),
-                )) => self.conn.changes(),
-                _ => 0,
-            };
-
-            let stmt_result = StmtResult {
-                cols,
-                rows,
-                affected_row_count,
-                last_insert_rowid,
-            };
-            step_results.push(Some(stmt_result));
-            step_errors.push(None);
         }
         Ok(BatchResult {
             step_results,
@@ -166,6 +141,56 @@ impl Client {
         })
     }
 
+    fn execute_one(&self, stmt: Statement) -> Result<StmtResult, libsql::Error> {
+        let sql_string = &stmt.sql;
+        let params: libsql::Params = stmt
+            .args
+            .into_iter()
+            .map(ValueWrapper)
+            .map(libsql::Value::from)
+            .collect::<Vec<_>>()
+            .into();
+        let stmt = self.conn.prepare(sql_string)?;
+        let cols: Vec<Col> = stmt
+            .columns()
+            .into_iter()
+            .map(|c| Col {
+                name: Some(c.name().to_string()),
+            })
+            .collect();
+        let mut rows = Vec::new();
+        let input_rows = stmt.query(&params)?;
+        while let Some(row) = input_rows.next()? {
+            let cells = (0..cols.len())
+                .map(|i| ValueWrapper::from(row.get_value(i as i32).unwrap()).0)
+                .collect();
+            rows.push(cells)
+        }
+        let parser = Parser::new(sql_string.as_bytes());
+        let cmd = parser.last();
+
+        let last_insert_rowid = match cmd {
+            Ok(Some(Cmd::Stmt(Stmt::Insert { .. }))) => Some(self.conn.last_insert_rowid()),
+            _ => None,
+        };
+
+        let affected_row_count = match cmd {
+            Ok(Some(
+                Cmd::Stmt(Stmt::Insert { .. })
+                | Cmd::Stmt(Stmt::Update { .. })
+                | Cmd::Stmt(Stmt::Delete { .. }),
+            )) => self.conn.changes(),
+            _ => 0,
+        };
+
+        Ok(StmtResult {
+            cols,
+            rows,
+            affected_row_count,
+            last_insert_rowid,
+        })
+    }
+
     /// Executes a batch of SQL statements, wrapped in "BEGIN", "END", transaction-style.
     /// Each statement is going to run in its own transaction,
     /// unless they're wrapped in BEGIN and END
@@ -208,11 +233,21 @@ impl Client {
     /// # Arguments
     /// * `stmt` - the SQL statement
     pub fn execute(&self, stmt: impl Into<Statement> + Send) -> Result<ResultSet> {
-        let results = self.raw_batch(std::iter::once(stmt))?;
-        match (results.step_results.first(), results.step_errors.first()) {
-            (Some(Some(result)), Some(None)) => Ok(ResultSet::from(result.clone())),
-            (Some(None), Some(Some(err))) => Err(anyhow::anyhow!(err.message.clone())),
-            _ => unreachable!(),
+        match self.execute_one(stmt.into()) {
+            Ok(result) => Ok(ResultSet::from(result)),
+            Err(e) => {
+                let code = match &e {
+                    libsql::Error::PrepareFailed(code, ..)
+                    | libsql::Error::FetchRowFailed(code, ..)
+                    | libsql::Error::LibError(code, ..) => Some(*code),
+                    _ => None,
+                };
+                Err(crate::Error::Sql {
+                    message: e.to_string(),
+                    code,
+                }
+                .into())
+            }
         }
     }
 
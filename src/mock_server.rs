@@ -0,0 +1,240 @@
+//! A minimal HTTP/1.1 server used to test the HTTP backends.
+#![allow(dead_code)] // not every test needs every helper
+
+use std::sync::{Arc, Mutex};
+
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+
+/// A request received by the [MockServer]
+#[derive(Clone, Debug)]
+pub(crate) struct RecordedRequest {
+    pub method: String,
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl RecordedRequest {
+    /// Returns the value of the first header called `name`, compared case-insensitively
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// A canned response sent back by the [MockServer]
+pub(crate) struct MockResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    pub delay: Option<std::time::Duration>,
+}
+
+impl MockResponse {
+    pub fn json(body: impl Into<String>) -> Self {
+        Self {
+            status: 200,
+            headers: vec![("Content-Type".into(), "application/json".into())],
+            body: body.into().into_bytes(),
+            delay: None,
+        }
+    }
+
+    pub fn status(status: u16) -> Self {
+        Self {
+            status,
+            headers: vec![],
+            body: vec![],
+            delay: None,
+        }
+    }
+
+    /// Makes the server wait for `delay` before responding
+    pub fn with_delay(mut self, delay: std::time::Duration) -> Self {
+        self.delay = Some(delay);
+        self
+    }
+
+    /// A successful pipeline response to a single `execute` request,
+    /// returning `result` as the statement result
+    pub fn execute(result: &str) -> Self {
+        Self::json(format!(
+            r#"{{"baton":null,"base_url":null,"results":[{{"type":"ok","response":{{"type":"execute","result":{result}}}}},{{"type":"ok","response":{{"type":"close"}}}}]}}"#
+        ))
+    }
+
+    /// A successful pipeline response to an `execute` request for a statement
+    /// which returns no rows
+    pub fn empty_execute() -> Self {
+        Self::execute(r#"{"cols":[],"rows":[],"affected_row_count":0,"last_insert_rowid":null}"#)
+    }
+}
+
+type Handler = dyn Fn(&RecordedRequest) -> MockResponse + Send + Sync;
+
+#[derive(Default)]
+struct State {
+    requests: Vec<RecordedRequest>,
+    connections: usize,
+    aborted: usize,
+}
+
+/// HTTP server listening on a random local port, answering each request
+/// with the response produced by its handler.
+pub(crate) struct MockServer {
+    pub url: String,
+    state: Arc<Mutex<State>>,
+}
+
+impl MockServer {
+    pub async fn start(
+        handler: impl Fn(&RecordedRequest) -> MockResponse + Send + Sync + 'static,
+    ) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let state = Arc::new(Mutex::new(State::default()));
+        let handler: Arc<Handler> = Arc::new(handler);
+
+        let server_state = state.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                serve(stream, server_state.clone(), handler.clone());
+            }
+        });
+        Self { url, state }
+    }
+
+    /// Like [`MockServer::start()`], but listens on a Unix domain socket at `path`
+    #[cfg(unix)]
+    pub async fn start_unix(
+        path: &std::path::Path,
+        handler: impl Fn(&RecordedRequest) -> MockResponse + Send + Sync + 'static,
+    ) -> Self {
+        let listener = tokio::net::UnixListener::bind(path).unwrap();
+        let state = Arc::new(Mutex::new(State::default()));
+        let handler: Arc<Handler> = Arc::new(handler);
+
+        let server_state = state.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                serve(stream, server_state.clone(), handler.clone());
+            }
+        });
+        Self {
+            url: "http://localhost/".to_string(),
+            state,
+        }
+    }
+
+    /// All requests received so far, in order
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    /// The number of TCP connections accepted so far
+    pub fn connections(&self) -> usize {
+        self.state.lock().unwrap().connections
+    }
+
+    /// The number of requests whose connection was closed by the client
+    /// before the response was sent, e.g. because the request was cancelled
+    pub fn aborted(&self) -> usize {
+        self.state.lock().unwrap().aborted
+    }
+}
+
+/// Answers the requests received on a single connection
+fn serve(
+    stream: impl AsyncRead + AsyncWrite + Send + 'static,
+    state: Arc<Mutex<State>>,
+    handler: Arc<Handler>,
+) {
+    state.lock().unwrap().connections += 1;
+    tokio::spawn(async move {
+        let (reader, mut writer) = tokio::io::split(stream);
+        let mut reader = BufReader::new(reader);
+        while let Some(request) = read_request(&mut reader).await {
+            let response = handler(&request);
+            state.lock().unwrap().requests.push(request);
+            tokio::select! {
+                result = write_response(&mut writer, response) => {
+                    if result.is_err() {
+                        break;
+                    }
+                }
+                _ = closed(&mut reader) => {
+                    state.lock().unwrap().aborted += 1;
+                    break;
+                }
+            }
+        }
+    });
+}
+
+async fn read_request<R: AsyncBufReadExt + Unpin>(reader: &mut R) -> Option<RecordedRequest> {
+    let mut line = String::new();
+    if reader.read_line(&mut line).await.ok()? == 0 {
+        return None;
+    }
+    let mut parts = line.split_whitespace();
+    let method = parts.next()?.to_string();
+    let path = parts.next()?.to_string();
+
+    let mut headers = Vec::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).await.ok()?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        let (k, v) = line.split_once(':')?;
+        headers.push((k.trim().to_string(), v.trim().to_string()));
+    }
+    let len: usize = headers
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, v)| v.parse().ok())
+        .unwrap_or(0);
+    let mut body = vec![0; len];
+    reader.read_exact(&mut body).await.ok()?;
+    Some(RecordedRequest {
+        method,
+        path,
+        headers,
+        body: String::from_utf8_lossy(&body).into_owned(),
+    })
+}
+
+/// Resolves when the client closes the connection. Clients don't send anything
+/// while waiting for a response, so any data means that the connection is still open.
+async fn closed<R: AsyncBufReadExt + Unpin>(reader: &mut R) {
+    match reader.fill_buf().await {
+        Ok([]) | Err(_) => {}
+        Ok(_) => std::future::pending().await,
+    }
+}
+
+async fn write_response<W: AsyncWriteExt + Unpin>(
+    writer: &mut W,
+    response: MockResponse,
+) -> std::io::Result<()> {
+    if let Some(delay) = response.delay {
+        tokio::time::sleep(delay).await;
+    }
+    let mut head = format!(
+        "HTTP/1.1 {} Mock\r\nContent-Length: {}\r\n",
+        response.status,
+        response.body.len()
+    );
+    for (k, v) in &response.headers {
+        head.push_str(&format!("{k}: {v}\r\n"));
+    }
+    head.push_str("\r\n");
+    writer.write_all(head.as_bytes()).await?;
+    writer.write_all(&response.body).await?;
+    writer.flush().await
+}
@@ -0,0 +1,470 @@
+//! `PrefixedClient` adds a prefix to table names, for prefix-based multi-tenancy.
+
+use anyhow::Result;
+use sqlite3_parser::dialect::TokenType;
+use sqlite3_parser::lexer::sql::Tokenizer;
+use sqlite3_parser::lexer::Scanner;
+
+use crate::{Client, ResultSet, Statement};
+
+/// A [Client] wrapper adding a prefix to the names of tables in statements,
+/// so that each tenant of an application gets its own set of tables
+///
+/// Table names are found with a light scan of the SQL rather than a full parse,
+/// and statements it cannot rewrite are rejected. See [rewrite_table_names()]
+/// for which statements are supported.
+///
+/// # Examples
+///
+/// ```
+/// # async fn f() -> anyhow::Result<()> {
+/// use libsql_client::prefix::PrefixedClient;
+///
+/// let db = PrefixedClient::new(libsql_client::Client::in_memory()?, "tenant1_");
+/// // Creates and queries `tenant1_users`
+/// db.execute("CREATE TABLE users(id INTEGER)").await?;
+/// db.execute("SELECT * FROM users").await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct PrefixedClient {
+    inner: Client,
+    prefix: String,
+}
+
+impl PrefixedClient {
+    /// Wraps `inner`, adding `prefix` to table names
+    pub fn new(inner: Client, prefix: impl Into<String>) -> Self {
+        Self {
+            inner,
+            prefix: prefix.into(),
+        }
+    }
+
+    /// Returns the wrapped client, which does not rewrite statements
+    pub fn inner(&self) -> &Client {
+        &self.inner
+    }
+
+    /// Returns the prefix added to table names
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    /// Executes a single SQL statement, after adding the prefix to its table names
+    ///
+    /// # Arguments
+    /// * `stmt` - SQL statement
+    pub async fn execute(&self, stmt: impl Into<Statement> + Send) -> Result<ResultSet> {
+        self.inner.execute(self.rewrite(stmt.into())?).await
+    }
+
+    /// Executes a batch of SQL statements in a transaction,
+    /// after adding the prefix to their table names
+    ///
+    /// # Arguments
+    /// * `stmts` - SQL statements
+    pub async fn batch<I: IntoIterator<Item = impl Into<Statement> + Send> + Send>(
+        &self,
+        stmts: I,
+    ) -> Result<Vec<ResultSet>>
+    where
+        <I as IntoIterator>::IntoIter: Send,
+    {
+        let stmts = stmts
+            .into_iter()
+            .map(|stmt| self.rewrite(stmt.into()))
+            .collect::<Result<Vec<Statement>>>()?;
+        self.inner.batch(stmts).await
+    }
+
+    fn rewrite(&self, stmt: Statement) -> Result<Statement> {
+        Ok(Statement {
+            sql: rewrite_table_names(&stmt.sql, &self.prefix)?,
+            ..stmt
+        })
+    }
+}
+
+/// Adds `prefix` to the names of tables referenced by `sql`
+///
+/// Names following `FROM` (including comma-separated lists), `JOIN`, `INTO`, `UPDATE`,
+/// `TABLE`, `IN`, `REFERENCES` and `RENAME TO` are rewritten, as well as quoted names,
+/// the table part of schema-qualified names, and names of indexes. Names of SQLite's
+/// internal `sqlite_` tables and of common table expressions are left alone.
+///
+/// The rewrite fails closed: statements which cannot be tokenized, or which use constructs
+/// it does not handle, are rejected with an error rather than sent partly rewritten.
+/// These are views, triggers, pragmas, attached databases, maintenance statements,
+/// `INDEXED BY`, table-valued functions, `WITH` clauses not starting the statement,
+/// and table names which are keywords or string literals. Table names used as column
+/// qualifiers, like `users.id`, are not prefixed, so use aliases instead.
+///
+/// This is a light scan of the SQL rather than a full parse, meant to keep the tables
+/// of tenants apart in a trusted application. It is not a security boundary
+/// against tenants who can run arbitrary SQL.
+///
+/// # Examples
+///
+/// ```
+/// use libsql_client::prefix::rewrite_table_names;
+///
+/// assert_eq!(
+///     rewrite_table_names("SELECT * FROM users u JOIN orders o ON o.user_id = u.id", "t1_")
+///         .unwrap(),
+///     "SELECT * FROM t1_users u JOIN t1_orders o ON o.user_id = u.id"
+/// );
+/// assert!(rewrite_table_names("SELECT * FROM pragma_table_info('users')", "t1_").is_err());
+/// ```
+pub fn rewrite_table_names(sql: &str, prefix: &str) -> Result<String> {
+    let input = sql.as_bytes();
+    let mut scanner = Scanner::new(Tokenizer::new());
+    let mut tokens = Vec::new();
+    loop {
+        match scanner.scan(input) {
+            Ok((start, Some((token, token_type)), _)) => tokens.push((start, token, token_type)),
+            Ok((_, None, _)) => break,
+            Err(e) => anyhow::bail!("Cannot add a table prefix to invalid SQL: {e}"),
+        }
+    }
+    let ctes = cte_names(&tokens)?;
+
+    let mut insertions = Vec::new();
+    let mut expected: Option<TableRef> = None;
+    // Depths of parentheses at which `FROM` clauses are open, innermost last
+    let mut from_clauses: Vec<usize> = Vec::new();
+    let mut depth = 0;
+    let mut after_index = false;
+    for (i, (start, token, token_type)) in tokens.iter().enumerate() {
+        let next_type = tokens.get(i + 1).map(|(_, _, t)| *t);
+        if let Some(table_ref) = expected {
+            match token_type {
+                // `IF [NOT] EXISTS` and conflict clauses of `UPDATE OR ...`
+                TokenType::TK_IF
+                | TokenType::TK_NOT
+                | TokenType::TK_EXISTS
+                | TokenType::TK_OR
+                | TokenType::TK_REPLACE
+                | TokenType::TK_IGNORE
+                | TokenType::TK_ABORT
+                | TokenType::TK_FAIL
+                | TokenType::TK_ROLLBACK => continue,
+                // The schema of a qualified name
+                TokenType::TK_ID if next_type == Some(TokenType::TK_DOT) => continue,
+                TokenType::TK_DOT => continue,
+                TokenType::TK_ID => {
+                    if table_ref == TableRef::Source && next_type == Some(TokenType::TK_LP) {
+                        anyhow::bail!("Cannot add a table prefix to table-valued functions");
+                    }
+                    let name = unquote(token);
+                    if !is_internal_table(&name) && !ctes.contains(&name) {
+                        insertions.push(match token.first() {
+                            Some(b'"' | b'`' | b'[') => start + 1,
+                            _ => *start,
+                        });
+                    }
+                    expected = None;
+                    continue;
+                }
+                // Subqueries and lists of values
+                TokenType::TK_LP if table_ref == TableRef::Source => expected = None,
+                // Upserts and actions of foreign keys, like `ON UPDATE SET NULL`
+                TokenType::TK_SET
+                | TokenType::TK_CASCADE
+                | TokenType::TK_RESTRICT
+                | TokenType::TK_NO
+                    if table_ref == TableRef::Updated =>
+                {
+                    expected = None
+                }
+                _ => anyhow::bail!(
+                    "Cannot add a table prefix to `{}`, expected a table name",
+                    String::from_utf8_lossy(token)
+                ),
+            }
+        }
+        match token_type {
+            TokenType::TK_VIEW
+            | TokenType::TK_TRIGGER
+            | TokenType::TK_PRAGMA
+            | TokenType::TK_ATTACH
+            | TokenType::TK_DETACH
+            | TokenType::TK_VACUUM
+            | TokenType::TK_REINDEX
+            | TokenType::TK_ANALYZE
+            | TokenType::TK_INDEXED => anyhow::bail!(
+                "Cannot add a table prefix to statements using `{}`",
+                String::from_utf8_lossy(token).to_uppercase()
+            ),
+            TokenType::TK_WITH if i > 0 => anyhow::bail!(
+                "Cannot add a table prefix to `WITH` clauses which do not start the statement"
+            ),
+            TokenType::TK_LP => depth += 1,
+            TokenType::TK_RP => {
+                if from_clauses.last() == Some(&depth) {
+                    from_clauses.pop();
+                }
+                depth = depth.saturating_sub(1);
+            }
+            TokenType::TK_FROM => {
+                expected = Some(TableRef::Source);
+                if from_clauses.last() != Some(&depth) {
+                    from_clauses.push(depth);
+                }
+            }
+            TokenType::TK_JOIN | TokenType::TK_IN => expected = Some(TableRef::Source),
+            TokenType::TK_COMMA if from_clauses.last() == Some(&depth) => {
+                expected = Some(TableRef::Source)
+            }
+            // Clauses ending a `FROM` clause
+            TokenType::TK_WHERE
+            | TokenType::TK_GROUP
+            | TokenType::TK_HAVING
+            | TokenType::TK_WINDOW
+            | TokenType::TK_ORDER
+            | TokenType::TK_LIMIT
+            | TokenType::TK_UNION
+            | TokenType::TK_EXCEPT
+            | TokenType::TK_INTERSECT
+            | TokenType::TK_DO
+            | TokenType::TK_RETURNING
+            | TokenType::TK_SEMI => {
+                if from_clauses.last() == Some(&depth) {
+                    from_clauses.pop();
+                }
+                if *token_type == TokenType::TK_SEMI {
+                    after_index = false;
+                }
+            }
+            TokenType::TK_UPDATE => expected = Some(TableRef::Updated),
+            TokenType::TK_INTO | TokenType::TK_TABLE | TokenType::TK_REFERENCES => {
+                expected = Some(TableRef::Named)
+            }
+            TokenType::TK_TO if i > 0 && tokens[i - 1].2 == TokenType::TK_RENAME => {
+                expected = Some(TableRef::Named)
+            }
+            TokenType::TK_INDEX => {
+                expected = Some(TableRef::Named);
+                after_index = true;
+            }
+            TokenType::TK_ON if after_index => {
+                expected = Some(TableRef::Named);
+                after_index = false;
+            }
+            _ => {}
+        }
+    }
+
+    let mut rewritten = sql.to_string();
+    for position in insertions.into_iter().rev() {
+        rewritten.insert_str(position, prefix);
+    }
+    Ok(rewritten)
+}
+
+/// Position of an expected table name, telling which other tokens may follow instead
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum TableRef {
+    /// A table read from, or a subquery
+    Source,
+    /// The table of an `UPDATE`, or the action of a foreign key, like `ON UPDATE CASCADE`
+    Updated,
+    /// A table or index being created, altered, dropped, written to or referenced
+    Named,
+}
+
+/// Returns the names of the common table expressions of `tokens`, unquoted and lowercase
+fn cte_names(tokens: &[(usize, &[u8], TokenType)]) -> Result<Vec<Vec<u8>>> {
+    let mut names = Vec::new();
+    let mut tokens = tokens
+        .iter()
+        .map(|(_, token, token_type)| (*token, *token_type));
+    if !matches!(tokens.next(), Some((_, TokenType::TK_WITH))) {
+        return Ok(names);
+    }
+    let mut depth = 0;
+    let mut expect_name = true;
+    for (token, token_type) in tokens {
+        match token_type {
+            TokenType::TK_RECURSIVE if expect_name => {}
+            TokenType::TK_ID if expect_name => {
+                names.push(unquote(token));
+                expect_name = false;
+            }
+            _ if expect_name => anyhow::bail!(
+                "Cannot add a table prefix to `{}`, expected the name of a common table expression",
+                String::from_utf8_lossy(token)
+            ),
+            TokenType::TK_LP => depth += 1,
+            TokenType::TK_RP => depth -= 1,
+            TokenType::TK_COMMA if depth == 0 => expect_name = true,
+            // The statement using the common table expressions
+            TokenType::TK_SELECT
+            | TokenType::TK_INSERT
+            | TokenType::TK_REPLACE
+            | TokenType::TK_UPDATE
+            | TokenType::TK_DELETE
+            | TokenType::TK_VALUES
+                if depth == 0 =>
+            {
+                break
+            }
+            _ => {}
+        }
+    }
+    Ok(names)
+}
+
+/// Strips the quotes around an identifier and lowercases it
+fn unquote(token: &[u8]) -> Vec<u8> {
+    let name = match (token.first(), token.last()) {
+        (Some(b'"'), Some(b'"')) | (Some(b'`'), Some(b'`')) | (Some(b'['), Some(b']'))
+            if token.len() >= 2 =>
+        {
+            &token[1..token.len() - 1]
+        }
+        _ => token,
+    };
+    name.to_ascii_lowercase()
+}
+
+fn is_internal_table(name: &[u8]) -> bool {
+    name.starts_with(b"sqlite_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rewrite_table_names() {
+        let cases = [
+            ("SELECT * FROM users", "SELECT * FROM tenant1_users"),
+            (
+                "SELECT u.name FROM users AS u, orders o WHERE o.user_id = u.id",
+                "SELECT u.name FROM tenant1_users AS u, tenant1_orders o WHERE o.user_id = u.id",
+            ),
+            (
+                "SELECT * FROM users u LEFT JOIN orders o ON o.user_id = u.id",
+                "SELECT * FROM tenant1_users u LEFT JOIN tenant1_orders o ON o.user_id = u.id",
+            ),
+            (
+                "INSERT OR IGNORE INTO users (id, name) VALUES (?, 'FROM x')",
+                "INSERT OR IGNORE INTO tenant1_users (id, name) VALUES (?, 'FROM x')",
+            ),
+            (
+                "UPDATE OR REPLACE users SET name = ? WHERE id IN (SELECT id FROM banned)",
+                "UPDATE OR REPLACE tenant1_users SET name = ? WHERE id IN (SELECT id FROM tenant1_banned)",
+            ),
+            (
+                "DELETE FROM main.users WHERE id = ?",
+                "DELETE FROM main.tenant1_users WHERE id = ?",
+            ),
+            (
+                "CREATE TABLE IF NOT EXISTS \"users\"(id)",
+                "CREATE TABLE IF NOT EXISTS \"tenant1_users\"(id)",
+            ),
+            (
+                "CREATE UNIQUE INDEX users_email ON users(email)",
+                "CREATE UNIQUE INDEX tenant1_users_email ON tenant1_users(email)",
+            ),
+            ("DROP TABLE IF EXISTS users", "DROP TABLE IF EXISTS tenant1_users"),
+            (
+                "ALTER TABLE users ADD COLUMN age",
+                "ALTER TABLE tenant1_users ADD COLUMN age",
+            ),
+            (
+                "ALTER TABLE users RENAME TO people",
+                "ALTER TABLE tenant1_users RENAME TO tenant1_people",
+            ),
+            (
+                "CREATE TABLE orders(user_id REFERENCES users ON UPDATE CASCADE)",
+                "CREATE TABLE tenant1_orders(user_id REFERENCES tenant1_users ON UPDATE CASCADE)",
+            ),
+            (
+                "SELECT * FROM orders WHERE user_id NOT IN banned",
+                "SELECT * FROM tenant1_orders WHERE user_id NOT IN tenant1_banned",
+            ),
+            (
+                "SELECT * FROM (SELECT * FROM a), b JOIN c USING (x, y), d",
+                "SELECT * FROM (SELECT * FROM tenant1_a), tenant1_b JOIN tenant1_c USING (x, y), tenant1_d",
+            ),
+            (
+                "WITH RECURSIVE Active(id) AS (SELECT id FROM users), \"old\" AS NOT MATERIALIZED (SELECT 1) \
+                 SELECT * FROM active, [OLD] JOIN orders ON orders.user_id = active.id",
+                "WITH RECURSIVE Active(id) AS (SELECT id FROM tenant1_users), \"old\" AS NOT MATERIALIZED (SELECT 1) \
+                 SELECT * FROM active, [OLD] JOIN tenant1_orders ON orders.user_id = active.id",
+            ),
+            (
+                "INSERT INTO users VALUES (1) ON CONFLICT DO UPDATE SET name = excluded.name",
+                "INSERT INTO tenant1_users VALUES (1) ON CONFLICT DO UPDATE SET name = excluded.name",
+            ),
+            (
+                "DROP INDEX idx; SELECT * FROM a JOIN b ON b.id = a.id",
+                "DROP INDEX tenant1_idx; SELECT * FROM tenant1_a JOIN tenant1_b ON b.id = a.id",
+            ),
+            (
+                "SELECT name FROM sqlite_master",
+                "SELECT name FROM sqlite_master",
+            ),
+            (
+                "SELECT * FROM [SQLITE_SCHEMA], `sqlite_sequence`",
+                "SELECT * FROM [SQLITE_SCHEMA], `sqlite_sequence`",
+            ),
+            (
+                "SELECT * FROM (SELECT 1) AS t",
+                "SELECT * FROM (SELECT 1) AS t",
+            ),
+        ];
+        for (sql, expected) in cases {
+            assert_eq!(
+                rewrite_table_names(sql, "tenant1_").unwrap(),
+                expected,
+                "{sql}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_rewrite_table_names_unsupported() {
+        let cases = [
+            "SELECT 'unterminated",
+            "SELECT * FROM pragma_table_info('users')",
+            "SELECT * FROM users WHERE id IN json_each(?)",
+            "SELECT * FROM users INDEXED BY idx",
+            "CREATE VIEW v AS SELECT * FROM users",
+            "CREATE TRIGGER t AFTER INSERT ON users BEGIN DELETE FROM orders; END",
+            "PRAGMA table_info(users)",
+            "ATTACH 'other.db' AS other",
+            "SELECT * FROM (WITH c AS (SELECT 1) SELECT * FROM c)",
+            "SELECT * FROM 'users'",
+        ];
+        for sql in cases {
+            assert!(rewrite_table_names(sql, "tenant1_").is_err(), "{sql}");
+        }
+    }
+
+    #[cfg(feature = "local_backend")]
+    #[tokio::test]
+    async fn test_prefixed_client() {
+        let inner = Client::in_memory().unwrap();
+        let tenant1 = PrefixedClient::new(inner, "tenant1_");
+        tenant1
+            .batch([
+                "CREATE TABLE users(id INTEGER, name TEXT)",
+                "INSERT INTO users VALUES (1, 'alice')",
+            ])
+            .await
+            .unwrap();
+        let rs = tenant1.execute("SELECT name FROM users").await.unwrap();
+        assert_eq!(rs.rows[0].try_get::<&str>(0).unwrap(), "alice");
+
+        let rs = tenant1
+            .inner()
+            .execute("SELECT name FROM sqlite_master WHERE type = 'table'")
+            .await
+            .unwrap();
+        assert_eq!(rs.rows[0].try_get::<&str>(0).unwrap(), "tenant1_users");
+    }
+}
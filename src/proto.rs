@@ -1,5 +1,9 @@
 //! `proto` contains libSQL/sqld/hrana wire protocol.
 
+use anyhow::Result;
+
+use crate::{ResultSet, Statement};
+
 #[cfg(feature = "hrana_backend")]
 pub use hrana_client::proto::{
     pipeline, Batch, BatchReq, BatchResp, BatchResult, ClientMsg, Col, Error, ExecuteReq,
@@ -10,3 +14,628 @@ pub use hrana_client_proto::{
     pipeline, Batch, BatchReq, BatchResp, BatchResult, ClientMsg, Col, Error, ExecuteReq,
     ExecuteResp, OpenStreamReq, Request, Response, ServerMsg, Stmt, StmtResult, Value,
 };
+
+/// Encodes `stmts` as the body of a single request to the `/v2/pipeline` endpoint
+/// of a libSQL server, returning it along with the number of statements.
+///
+/// The statements are sent as one batch, but are not wrapped in a transaction.
+/// Together with [decode_response], this allows talking to the server
+/// over any HTTP client. Both are available without any backend feature, so with
+/// `default-features = false` they can be used on targets without networking support.
+///
+/// # Examples
+///
+/// ```
+/// # fn f() -> anyhow::Result<()> {
+/// use libsql_client::{args, proto, Statement};
+///
+/// let (body, count) = proto::encode_request([
+///     Statement::new("SELECT 1"),
+///     Statement::with_args("SELECT ?", args!(2)),
+/// ])?;
+/// assert_eq!(count, 2);
+/// // POST `body` to {url}/v2/pipeline, then decode the response:
+/// // let results = proto::decode_response(&response, count)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn encode_request(
+    stmts: impl IntoIterator<Item = impl Into<Statement>>,
+) -> Result<(String, usize)> {
+    let stmts: Vec<Statement> = stmts.into_iter().map(Into::into).collect();
+    let count = stmts.len();
+    let msg = batch_msg(stmts);
+    Ok((serde_json::to_string(&msg)?, count))
+}
+
+/// Decodes the response of the server to a request built with [encode_request],
+/// returning one [ResultSet] per statement.
+///
+/// Fails if the server returned an error, if any of the statements failed,
+/// or if the response does not contain exactly `count` results.
+pub fn decode_response(json: &str, count: usize) -> Result<Vec<ResultSet>> {
+    let result = batch_result(decode_server_msg(json, None)?)?;
+    if result.step_results.len() != count {
+        anyhow::bail!(
+            "Expected {count} results from server, got {}",
+            result.step_results.len()
+        );
+    }
+    if let Some(error) = result.step_errors.into_iter().flatten().next() {
+        anyhow::bail!(error.message);
+    }
+    result
+        .step_results
+        .into_iter()
+        .map(|maybe_rs| {
+            maybe_rs
+                .map(ResultSet::from)
+                .ok_or_else(|| anyhow::anyhow!("Unexpected missing result set"))
+        })
+        .collect()
+}
+
+pub(crate) fn into_hrana(stmt: Statement) -> Stmt {
+    let mut hrana_stmt = Stmt::new(stmt.sql, true);
+    for param in stmt.args {
+        hrana_stmt.bind(param);
+    }
+    hrana_stmt
+}
+
+/// Builds a pipeline request executing `stmts` as a batch and closing the stream
+pub(crate) fn batch_msg(
+    stmts: impl IntoIterator<Item = impl Into<Statement>>,
+) -> pipeline::ClientMsg {
+    let mut batch = Batch::new();
+    for stmt in stmts.into_iter() {
+        batch.step(None, into_hrana(stmt.into()));
+    }
+    pipeline::ClientMsg {
+        baton: None,
+        requests: vec![
+            pipeline::StreamRequest::Batch(pipeline::StreamBatchReq { batch }),
+            pipeline::StreamRequest::Close,
+        ],
+    }
+}
+
+/// Condition on the outcome of an earlier step of a batch, deciding whether a step runs.
+/// The `BatchCond` of `hrana_client_proto` is not serialized in the format servers expect.
+#[cfg(any(
+    feature = "reqwest_backend",
+    feature = "workers_backend",
+    feature = "spin_backend",
+    feature = "tower"
+))]
+#[derive(serde::Serialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum StepCond {
+    Ok { step: usize },
+    Not { cond: Box<StepCond> },
+}
+
+/// Builds a pipeline request executing `stmts` in a transaction started by `begin`,
+/// as a single batch closing the stream.
+///
+/// The steps are the `prefix` statements, which run unconditionally, then `begin`, `stmts`,
+/// `COMMIT` and `ROLLBACK`. Each statement only runs if the previous step succeeded,
+/// so the first failure skips the rest of the transaction, including its `COMMIT`,
+/// and the `ROLLBACK` runs instead.
+#[cfg(any(
+    feature = "reqwest_backend",
+    feature = "workers_backend",
+    feature = "spin_backend",
+    feature = "tower"
+))]
+pub(crate) fn transaction_batch_msg(
+    prefix: impl IntoIterator<Item = Statement>,
+    begin: Statement,
+    stmts: impl IntoIterator<Item = Statement>,
+) -> serde_json::Value {
+    let mut steps: Vec<serde_json::Value> = prefix
+        .into_iter()
+        .map(|stmt| serde_json::json!({ "condition": null, "stmt": into_hrana(stmt) }))
+        .collect();
+    steps.push(serde_json::json!({ "condition": null, "stmt": into_hrana(begin) }));
+    for stmt in stmts
+        .into_iter()
+        .chain(std::iter::once(Statement::new("COMMIT")))
+    {
+        let condition = StepCond::Ok {
+            step: steps.len() - 1,
+        };
+        steps.push(serde_json::json!({ "condition": condition, "stmt": into_hrana(stmt) }));
+    }
+    let commit_step = steps.len() - 1;
+    let condition = StepCond::Not {
+        cond: Box::new(StepCond::Ok { step: commit_step }),
+    };
+    steps.push(serde_json::json!({
+        "condition": condition,
+        "stmt": into_hrana(Statement::new("ROLLBACK")),
+    }));
+    serde_json::json!({
+        "baton": null,
+        "requests": [
+            { "type": "batch", "batch": { "steps": steps } },
+            { "type": "close" },
+        ],
+    })
+}
+
+/// Extracts the result of the batch from a response to [batch_msg]
+pub(crate) fn batch_result(mut response: pipeline::ServerMsg) -> Result<BatchResult> {
+    if response.results.is_empty() {
+        anyhow::bail!(
+            "Unexpected empty response from server: {:?}",
+            response.results
+        );
+    }
+    if response.results.len() > 2 {
+        // One with actual results, one closing the stream
+        anyhow::bail!(
+            "Unexpected multiple responses from server: {:?}",
+            response.results
+        );
+    }
+    match response.results.swap_remove(0) {
+        pipeline::Response::Ok(pipeline::StreamResponseOk {
+            response: pipeline::StreamResponse::Batch(batch_result),
+        }) => Ok(batch_result.result),
+        pipeline::Response::Ok(_) => {
+            anyhow::bail!("Unexpected response from server: {:?}", response.results)
+        }
+        pipeline::Response::Error(e) => {
+            anyhow::bail!("Error from server: {:?}", e)
+        }
+    }
+}
+
+/// Decodes a response of the server, applying `hook` to its JSON tree first if given.
+/// All responses of the HTTP backends are decoded here.
+///
+/// Decoding is tolerant, to keep working against newer servers: unknown fields are ignored,
+/// and the optional fields of statement results are filled in if missing.
+pub(crate) fn decode_server_msg(
+    body: &str,
+    hook: Option<&(dyn Fn(&mut serde_json::Value) + Send + Sync)>,
+) -> Result<pipeline::ServerMsg> {
+    if hook.is_none() {
+        // The common case, without the cost of building a tree
+        if let Ok(msg) = serde_json::from_str(body) {
+            return Ok(msg);
+        }
+    }
+    let mut tree: serde_json::Value = serde_json::from_str(body)?;
+    if let Some(hook) = hook {
+        hook(&mut tree);
+    }
+    fill_optional_fields(&mut tree);
+    // Some fields of the protocol can only be decoded from borrowed strings,
+    // so the tree needs to be serialized again instead of using `from_value()`.
+    Ok(serde_json::from_str(&tree.to_string())?)
+}
+
+/// Decodes one line of a newline-delimited JSON response, which is either the result
+/// of a statement or an object with an `error` field, applying `hook` to it first if given.
+#[cfg(any(
+    feature = "reqwest_backend",
+    feature = "workers_backend",
+    feature = "spin_backend",
+    feature = "tower"
+))]
+pub(crate) fn decode_result_line(
+    line: &str,
+    hook: Option<&(dyn Fn(&mut serde_json::Value) + Send + Sync)>,
+) -> Result<ResultSet> {
+    let mut tree: serde_json::Value = serde_json::from_str(line)?;
+    if let Some(hook) = hook {
+        hook(&mut tree);
+    }
+    if let Some(error) = tree.get("error") {
+        let message = error.get("message").and_then(|m| m.as_str());
+        anyhow::bail!(message.unwrap_or("Unknown error").to_string());
+    }
+    fill_optional_fields(&mut tree);
+    let result: StmtResult = serde_json::from_str(&tree.to_string())?;
+    Ok(ResultSet::from(result))
+}
+
+/// Extracts the SQLite result code of the first error in a response, which servers
+/// report by name as the `code` of the error, e.g. `SQLITE_CONSTRAINT_UNIQUE`.
+/// Errors of the steps of a batch are listed in its `step_errors`.
+#[cfg(any(
+    feature = "reqwest_backend",
+    feature = "workers_backend",
+    feature = "spin_backend",
+    feature = "tower"
+))]
+pub(crate) fn error_code(body: &str) -> Option<i32> {
+    fn find(tree: &serde_json::Value) -> Option<&serde_json::Value> {
+        match tree {
+            serde_json::Value::Object(object) => match object.get("error") {
+                Some(error) if error.get("code").is_some() => error.get("code"),
+                _ => match object.get("step_errors") {
+                    Some(serde_json::Value::Array(errors)) => errors
+                        .iter()
+                        .find_map(|error| error.get("code"))
+                        .or_else(|| object.values().find_map(find)),
+                    _ => object.values().find_map(find),
+                },
+            },
+            serde_json::Value::Array(array) => array.iter().find_map(find),
+            _ => None,
+        }
+    }
+    if !body.contains("\"code\"") {
+        return None;
+    }
+    let tree: serde_json::Value = serde_json::from_str(body).ok()?;
+    match find(&tree)? {
+        serde_json::Value::String(name) => crate::error::sqlite_code_from_name(name),
+        code => code.as_i64().and_then(|code| i32::try_from(code).ok()),
+    }
+}
+
+/// Extracts the `index` fields which some servers and proxies add to the results of the steps
+/// of a batch, to tell which statement each result is for. Empty if there are none.
+#[cfg(any(
+    feature = "reqwest_backend",
+    feature = "workers_backend",
+    feature = "spin_backend",
+    feature = "tower"
+))]
+pub(crate) fn step_indices(body: &str) -> Vec<Option<usize>> {
+    if !body.contains("\"index\"") {
+        return vec![];
+    }
+    let Ok(tree) = serde_json::from_str::<serde_json::Value>(body) else {
+        return vec![];
+    };
+    match &tree["results"][0]["response"]["result"]["step_results"] {
+        serde_json::Value::Array(steps) => steps.iter().map(result_index).collect(),
+        _ => vec![],
+    }
+}
+
+/// Extracts the `index` field of one line of a newline-delimited JSON response,
+/// see [step_indices]
+#[cfg(any(
+    feature = "reqwest_backend",
+    feature = "workers_backend",
+    feature = "spin_backend",
+    feature = "tower"
+))]
+pub(crate) fn line_index(line: &str) -> Option<usize> {
+    if !line.contains("\"index\"") {
+        return None;
+    }
+    result_index(&serde_json::from_str(line).ok()?)
+}
+
+#[cfg(any(
+    feature = "reqwest_backend",
+    feature = "workers_backend",
+    feature = "spin_backend",
+    feature = "tower"
+))]
+fn result_index(result: &serde_json::Value) -> Option<usize> {
+    result.get("index")?.as_u64()?.try_into().ok()
+}
+
+/// Checks that the result at each position is for the statement at the same position,
+/// as far as results are labeled with indices
+#[cfg(any(
+    feature = "reqwest_backend",
+    feature = "workers_backend",
+    feature = "spin_backend",
+    feature = "tower"
+))]
+pub(crate) fn check_order(indices: &[Option<usize>]) -> Result<()> {
+    for (position, index) in indices.iter().enumerate() {
+        match index {
+            Some(index) if *index != position => {
+                return Err(crate::Error::ResultsOutOfOrder {
+                    position,
+                    index: *index,
+                }
+                .into())
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Extracts the time spent executing each statement of a response, in order,
+/// reported by newer servers as `query_duration_ms`. Durations which are not
+/// valid are `None`.
+#[cfg(any(
+    feature = "reqwest_backend",
+    feature = "workers_backend",
+    feature = "spin_backend",
+    feature = "tower"
+))]
+pub(crate) fn server_durations(body: &str) -> Vec<Option<std::time::Duration>> {
+    fn find(tree: &serde_json::Value, durations: &mut Vec<Option<std::time::Duration>>) {
+        match tree {
+            serde_json::Value::Object(object) => match object.get("query_duration_ms") {
+                Some(ms) => durations.push(
+                    ms.as_f64()
+                        .and_then(|ms| std::time::Duration::try_from_secs_f64(ms / 1000.0).ok()),
+                ),
+                None => object.values().for_each(|value| find(value, durations)),
+            },
+            serde_json::Value::Array(array) => {
+                array.iter().for_each(|value| find(value, durations))
+            }
+            _ => {}
+        }
+    }
+    if !body.contains("query_duration_ms") {
+        return vec![];
+    }
+    let Ok(tree) = serde_json::from_str::<serde_json::Value>(body) else {
+        return vec![];
+    };
+    let mut durations = vec![];
+    find(&tree, &mut durations);
+    durations
+}
+
+/// Extracts the replication index of a response, reported by servers with replicas as
+/// `replication_index`, a number encoded as a string. The greatest one is returned
+/// if a response holds several, e.g. one per result.
+#[cfg(any(
+    feature = "reqwest_backend",
+    feature = "workers_backend",
+    feature = "spin_backend",
+    feature = "tower"
+))]
+pub(crate) fn replication_index(body: &str) -> Option<u64> {
+    fn find(tree: &serde_json::Value, max: &mut Option<u64>) {
+        match tree {
+            serde_json::Value::Object(object) => {
+                for (key, value) in object {
+                    if key != "replication_index" {
+                        find(value, max);
+                        continue;
+                    }
+                    let index = match value {
+                        serde_json::Value::String(index) => index.parse().ok(),
+                        index => index.as_u64(),
+                    };
+                    *max = (*max).max(index);
+                }
+            }
+            serde_json::Value::Array(array) => array.iter().for_each(|value| find(value, max)),
+            _ => {}
+        }
+    }
+    if !body.contains("replication_index") {
+        return None;
+    }
+    let tree: serde_json::Value = serde_json::from_str(body).ok()?;
+    let mut max = None;
+    find(&tree, &mut max);
+    max
+}
+
+/// Fills in the optional fields of all statement results found in `tree`,
+/// which the protocol types require to be present.
+fn fill_optional_fields(tree: &mut serde_json::Value) {
+    match tree {
+        serde_json::Value::Object(object) => {
+            let is_stmt_result = object.contains_key("cols") && object.contains_key("rows");
+            if is_stmt_result {
+                object
+                    .entry("last_insert_rowid")
+                    .or_insert(serde_json::Value::Null);
+                object
+                    .entry("affected_row_count")
+                    .or_insert(serde_json::Value::from(0));
+            }
+            object.values_mut().for_each(fill_optional_fields);
+        }
+        serde_json::Value::Array(array) => array.iter_mut().for_each(fill_optional_fields),
+        _ => (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::args;
+
+    #[test]
+    fn test_encode_request() {
+        let (body, count) = encode_request([
+            Statement::new("CREATE TABLE t(x)"),
+            Statement::with_args("INSERT INTO t VALUES (?)", args!(42)),
+            Statement::new("SELECT x FROM t"),
+        ])
+        .unwrap();
+        assert_eq!(count, 3);
+
+        let msg: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(msg["requests"][0]["type"], "batch");
+        assert_eq!(msg["requests"][1]["type"], "close");
+        let steps = msg["requests"][0]["batch"]["steps"].as_array().unwrap();
+        assert_eq!(steps.len(), 3);
+        assert_eq!(steps[1]["stmt"]["sql"], "INSERT INTO t VALUES (?)");
+        assert_eq!(steps[1]["stmt"]["args"][0]["type"], "integer");
+        assert_eq!(steps[1]["stmt"]["args"][0]["value"], "42");
+    }
+
+    #[test]
+    fn test_encode_bool_params() {
+        use crate::ser::bool_value;
+
+        let (body, _) = encode_request([Statement::with_args(
+            "INSERT INTO t VALUES (?, ?)",
+            &[bool_value(true), bool_value(false)],
+        )])
+        .unwrap();
+        let msg: serde_json::Value = serde_json::from_str(&body).unwrap();
+        let args = &msg["requests"][0]["batch"]["steps"][0]["stmt"]["args"];
+        assert_eq!(args[0]["type"], "integer");
+        assert_eq!(args[0]["value"], "1");
+        assert_eq!(args[1]["type"], "integer");
+        assert_eq!(args[1]["value"], "0");
+    }
+
+    const RESPONSE: &str = r#"{"baton":null,"base_url":null,"results":[
+        {"type":"ok","response":{"type":"batch","result":{
+            "step_results":[
+                {"cols":[],"rows":[],"affected_row_count":0,"last_insert_rowid":null},
+                {"cols":[],"rows":[],"affected_row_count":1,"last_insert_rowid":"1"},
+                {"cols":[{"name":"x"}],"rows":[[{"type":"integer","value":"42"}]],"affected_row_count":0,"last_insert_rowid":null}
+            ],
+            "step_errors":[null,null,null]
+        }}},
+        {"type":"ok","response":{"type":"close"}}
+    ]}"#;
+
+    #[test]
+    fn test_decode_response() {
+        let results = decode_response(RESPONSE, 3).unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[1].rows_affected, 1);
+        assert_eq!(results[1].last_insert_rowid, Some(1));
+        assert_eq!(results[2].columns, vec!["x".to_string()]);
+        assert!(matches!(
+            results[2].rows[0].values[0],
+            Value::Integer { value: 42 }
+        ));
+
+        assert!(decode_response(RESPONSE, 2).is_err());
+    }
+
+    #[test]
+    fn test_decode_unknown_fields() {
+        let response = r#"{"baton":null,"base_url":null,"server_version":"9.9","results":[
+            {"type":"ok","response":{"type":"batch","result":{
+                "step_results":[{
+                    "cols":[{"name":"x","decltype":"INTEGER"}],
+                    "rows":[[{"type":"integer","value":"1"}]],
+                    "affected_row_count":0,
+                    "last_insert_rowid":null,
+                    "rows_read":1,
+                    "rows_written":0
+                }],
+                "step_errors":[null],
+                "replication_index":"42"
+            }}},
+            {"type":"ok","response":{"type":"close"}}
+        ]}"#;
+        let results = decode_response(response, 1).unwrap();
+        assert_eq!(results[0].columns, vec!["x".to_string()]);
+        assert_eq!(results[0].rows.len(), 1);
+        assert_eq!(replication_index(response), Some(42));
+    }
+
+    #[cfg(any(
+        feature = "reqwest_backend",
+        feature = "workers_backend",
+        feature = "spin_backend",
+        feature = "tower"
+    ))]
+    #[test]
+    fn test_replication_index() {
+        assert_eq!(replication_index(RESPONSE), None);
+        assert_eq!(
+            replication_index(
+                r#"{"results":[{"replication_index":"7"},{"replication_index":12}]}"#
+            ),
+            Some(12)
+        );
+        assert_eq!(
+            replication_index(r#"{"results":[{"replication_index":null}]}"#),
+            None
+        );
+    }
+
+    #[cfg(any(
+        feature = "reqwest_backend",
+        feature = "workers_backend",
+        feature = "spin_backend",
+        feature = "tower"
+    ))]
+    #[test]
+    fn test_error_code() {
+        assert_eq!(error_code(RESPONSE), None);
+        assert_eq!(
+            error_code(
+                r#"{"results":[{"type":"error","error":{"message":"","code":"SQLITE_BUSY"}}]}"#
+            ),
+            Some(5)
+        );
+        assert_eq!(
+            error_code(
+                r#"{"result":{"step_results":[null,null],"step_errors":[null,{"message":"","code":"SQLITE_CONSTRAINT_UNIQUE"}]}}"#
+            ),
+            Some(2067)
+        );
+    }
+
+    #[cfg(any(
+        feature = "reqwest_backend",
+        feature = "workers_backend",
+        feature = "spin_backend",
+        feature = "tower"
+    ))]
+    #[test]
+    fn test_server_durations() {
+        assert_eq!(server_durations(RESPONSE), vec![]);
+        assert_eq!(
+            server_durations(r#"{"results":[{"query_duration_ms":12.5},{"query_duration_ms":2}]}"#),
+            vec![
+                Some(std::time::Duration::from_micros(12_500)),
+                Some(std::time::Duration::from_millis(2))
+            ]
+        );
+        for invalid in ["-1", "1e300"] {
+            let body = format!(r#"{{"results":[{{"query_duration_ms":{invalid}}}]}}"#);
+            assert_eq!(server_durations(&body), vec![None], "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_decode_missing_optional_fields() {
+        let response = r#"{"results":[
+            {"type":"ok","response":{"type":"batch","result":{
+                "step_results":[
+                    {"cols":[{}],"rows":[[{"type":"null"}]]},
+                    {"cols":[],"rows":[],"affected_row_count":3}
+                ],
+                "step_errors":[null,null]
+            }}},
+            {"type":"ok","response":{"type":"close"}}
+        ]}"#;
+        let results = decode_response(response, 2).unwrap();
+        assert_eq!(results[0].columns, vec![String::new()]);
+        assert_eq!(results[0].rows_affected, 0);
+        assert_eq!(results[0].last_insert_rowid, None);
+        assert_eq!(results[1].rows_affected, 3);
+        assert_eq!(results[1].last_insert_rowid, None);
+    }
+
+    #[test]
+    fn test_decode_response_errors() {
+        let step_error = r#"{"baton":null,"base_url":null,"results":[
+            {"type":"ok","response":{"type":"batch","result":{
+                "step_results":[null],
+                "step_errors":[{"message":"no such table: t"}]
+            }}},
+            {"type":"ok","response":{"type":"close"}}
+        ]}"#;
+        let err = decode_response(step_error, 1).unwrap_err();
+        assert_eq!(err.to_string(), "no such table: t");
+
+        let request_error = r#"{"baton":null,"base_url":null,"results":[
+            {"type":"error","error":{"message":"unauthorized"}}
+        ]}"#;
+        assert!(decode_response(request_error, 1).is_err());
+    }
+}
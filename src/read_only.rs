@@ -0,0 +1,121 @@
+//! `ReadOnlyClient` rejects statements which could modify the database, for untrusted queries.
+
+use anyhow::Result;
+
+use crate::statement::{classify, StatementKind};
+use crate::{Client, ResultSet, Statement};
+
+/// A [Client] wrapper which only lets [read](StatementKind::Read) statements through
+///
+/// Statements of any other kind, including pragmas and statements which cannot be
+/// classified, fail with [`Error::ReadOnlyViolation`](crate::Error::ReadOnlyViolation)
+/// without being sent. This is a safety layer on top of server-side permissions,
+/// not a replacement for them: reads can still be expensive, and SQL functions with
+/// side effects are not detected.
+///
+/// # Examples
+///
+/// ```
+/// # async fn f() -> anyhow::Result<()> {
+/// use libsql_client::read_only::ReadOnlyClient;
+///
+/// let db = ReadOnlyClient::new(libsql_client::Client::in_memory()?);
+/// db.execute("SELECT 1").await?;
+/// assert!(db.execute("DROP TABLE users").await.is_err());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct ReadOnlyClient {
+    inner: Client,
+}
+
+impl ReadOnlyClient {
+    /// Wraps `inner`, rejecting statements which are not reads
+    pub fn new(inner: Client) -> Self {
+        Self { inner }
+    }
+
+    /// Returns the wrapped client, which does not reject any statement
+    pub fn inner(&self) -> &Client {
+        &self.inner
+    }
+
+    /// Executes a single SQL statement if it is a read
+    ///
+    /// # Arguments
+    /// * `stmt` - SQL statement
+    pub async fn execute(&self, stmt: impl Into<Statement> + Send) -> Result<ResultSet> {
+        let stmt: Statement = stmt.into();
+        check(&stmt)?;
+        self.inner.execute(stmt).await
+    }
+
+    /// Executes a batch of SQL statements in a transaction, if all of them are reads.
+    /// Otherwise, none of them are sent.
+    ///
+    /// # Arguments
+    /// * `stmts` - SQL statements
+    pub async fn batch<I: IntoIterator<Item = impl Into<Statement> + Send> + Send>(
+        &self,
+        stmts: I,
+    ) -> Result<Vec<ResultSet>>
+    where
+        <I as IntoIterator>::IntoIter: Send,
+    {
+        let stmts: Vec<Statement> = stmts.into_iter().map(|s| s.into()).collect();
+        for stmt in &stmts {
+            check(stmt)?;
+        }
+        self.inner.batch(stmts).await
+    }
+}
+
+fn check(stmt: &Statement) -> Result<()> {
+    match classify(&stmt.sql) {
+        StatementKind::Read => Ok(()),
+        kind => Err(crate::Error::ReadOnlyViolation { kind }.into()),
+    }
+}
+
+#[cfg(all(test, feature = "local_backend"))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_read_only() {
+        let inner = Client::in_memory().unwrap();
+        inner.execute("CREATE TABLE t(x)").await.unwrap();
+        inner.execute("INSERT INTO t VALUES (1)").await.unwrap();
+        let db = ReadOnlyClient::new(inner);
+
+        let rs = db.execute("SELECT x FROM t").await.unwrap();
+        assert_eq!(rs.rows.len(), 1);
+
+        for (sql, expected) in [
+            ("INSERT INTO t VALUES (2)", StatementKind::Write),
+            ("DROP TABLE t", StatementKind::Ddl),
+            ("PRAGMA journal_mode = DELETE", StatementKind::Pragma),
+            ("SELECT 1; DROP TABLE t", StatementKind::Unknown),
+        ] {
+            let err = db.execute(sql).await.unwrap_err();
+            assert_eq!(
+                err.downcast_ref::<crate::Error>(),
+                Some(&crate::Error::ReadOnlyViolation { kind: expected }),
+                "{sql}"
+            );
+        }
+
+        let err = db
+            .batch(["SELECT x FROM t", "DELETE FROM t"])
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<crate::Error>(),
+            Some(crate::Error::ReadOnlyViolation { .. })
+        ));
+        // Nothing was modified
+        let rs = db.batch(["SELECT x FROM t"]).await.unwrap();
+        assert_eq!(rs[0].rows.len(), 1);
+    }
+}
@@ -1,45 +1,645 @@
 use anyhow::Result;
+use futures::StreamExt;
 
-use crate::proto::pipeline;
+use std::time::Duration;
 
+use crate::http::{HealthStatus, HttpRequest, ResponseBody};
+
+/// Content types of newline-delimited JSON responses, see [`HttpClient::send_streaming()`]
+const NDJSON_CONTENT_TYPES: [&str; 2] = ["application/x-ndjson", "application/jsonl"];
+
+/// How long idle connections are kept by default, same as in [reqwest]
+const DEFAULT_POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+
+/// HTTP client based on [reqwest]
+///
+/// Clones share the same [`reqwest::Client`], and so its connection pool.
+///
+/// With the `compression` feature, responses compressed with `gzip` or `brotli`
+/// are negotiated through `Accept-Encoding` and decompressed transparently.
 #[derive(Clone, Debug)]
 pub struct HttpClient {
     inner: reqwest::Client,
+    settings: Settings,
+}
+
+/// Settings of the [`reqwest::Client`], which is rebuilt whenever they change
+#[derive(Clone, Copy, Debug)]
+struct Settings {
+    version: HttpVersion,
+    accept_invalid_certs: bool,
+    connect_timeout: Option<Duration>,
+    tcp_nodelay: bool,
+    pool_idle_timeout: Option<Duration>,
+    pool_max_idle_per_host: usize,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            version: HttpVersion::Negotiate,
+            accept_invalid_certs: false,
+            connect_timeout: None,
+            tcp_nodelay: true,
+            pool_idle_timeout: Some(DEFAULT_POOL_IDLE_TIMEOUT),
+            pool_max_idle_per_host: usize::MAX,
+        }
+    }
+}
+
+/// HTTP versions the client is allowed to use
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HttpVersion {
+    /// HTTP/1.1 or HTTP/2, as negotiated with the server
+    #[default]
+    Negotiate,
+    /// HTTP/1.1 only, for proxies which mishandle HTTP/2
+    Http1Only,
+    /// HTTP/2 without negotiation, for servers known to support it
+    Http2PriorKnowledge,
 }
 
 impl HttpClient {
     pub fn new() -> Self {
+        Self::build(Settings::default())
+    }
+
+    fn build(settings: Settings) -> Self {
+        let builder = reqwest::Client::builder()
+            .danger_accept_invalid_certs(settings.accept_invalid_certs)
+            .tcp_nodelay(settings.tcp_nodelay)
+            .pool_idle_timeout(settings.pool_idle_timeout)
+            .pool_max_idle_per_host(settings.pool_max_idle_per_host);
+        let builder = match settings.connect_timeout {
+            Some(timeout) => builder.connect_timeout(timeout),
+            None => builder,
+        };
+        let builder = match settings.version {
+            HttpVersion::Negotiate => builder,
+            HttpVersion::Http1Only => builder.http1_only(),
+            HttpVersion::Http2PriorKnowledge => builder.http2_prior_knowledge(),
+        };
         Self {
-            inner: reqwest::Client::new(),
+            inner: builder.build().expect("failed to build the HTTP client"),
+            settings,
+        }
+    }
+
+    /// Forces HTTP/1.1 if `enabled`, or goes back to negotiating the version otherwise.
+    ///
+    /// Some proxies mishandle HTTP/2, which can cause requests to stall.
+    pub fn with_http1_only(self, enabled: bool) -> Self {
+        let version = match enabled {
+            true => HttpVersion::Http1Only,
+            false => HttpVersion::Negotiate,
+        };
+        Self::build(Settings {
+            version,
+            ..self.settings
+        })
+    }
+
+    /// Uses HTTP/2 right away, without negotiating it with the server first
+    pub fn with_http2_prior_knowledge(self) -> Self {
+        Self::build(Settings {
+            version: HttpVersion::Http2PriorKnowledge,
+            ..self.settings
+        })
+    }
+
+    /// Accepts any TLS certificate if `enabled`, including self-signed and expired ones.
+    ///
+    /// This makes connections vulnerable to man-in-the-middle attacks,
+    /// so only use it against development servers.
+    pub fn with_accept_invalid_certs(self, enabled: bool) -> Self {
+        Self::build(Settings {
+            accept_invalid_certs: enabled,
+            ..self.settings
+        })
+    }
+
+    /// Gives up on establishing a connection after `timeout`, including DNS resolution
+    /// and trying each of the resolved addresses.
+    ///
+    /// Unlike the timeout of [`http::Client::with_timeout()`](crate::http::Client::with_timeout),
+    /// this only covers connecting, so that unreachable hosts fail fast without cutting
+    /// long queries short. By default, connecting is only limited by the operating system.
+    pub fn with_connect_timeout(self, timeout: Duration) -> Self {
+        Self::build(Settings {
+            connect_timeout: Some(timeout),
+            ..self.settings
+        })
+    }
+
+    /// Disables Nagle's algorithm on connections if `enabled`, which is the default,
+    /// so that small requests are sent right away instead of being buffered
+    pub fn with_tcp_nodelay(self, enabled: bool) -> Self {
+        Self::build(Settings {
+            tcp_nodelay: enabled,
+            ..self.settings
+        })
+    }
+
+    /// Closes pooled connections which stayed idle for longer than `timeout`,
+    /// or keeps them until the server closes them if `None`. Defaults to 90 seconds.
+    ///
+    /// NATs and load balancers often drop idle connections silently, so that reusing them
+    /// fails with "connection reset". Set a timeout shorter than theirs to avoid it.
+    pub fn with_pool_idle_timeout(self, timeout: Option<Duration>) -> Self {
+        Self::build(Settings {
+            pool_idle_timeout: timeout,
+            ..self.settings
+        })
+    }
+
+    /// Keeps at most `max` idle connections per host in the pool, unlimited by default.
+    /// With `0`, connections are closed after each request instead of being reused.
+    pub fn with_pool_max_idle_per_host(self, max: usize) -> Self {
+        Self::build(Settings {
+            pool_max_idle_per_host: max,
+            ..self.settings
+        })
+    }
+
+    /// Whether this client accepts invalid TLS certificates
+    pub fn accepts_invalid_certs(&self) -> bool {
+        self.settings.accept_invalid_certs
+    }
+
+    /// The HTTP versions this client is allowed to use
+    pub fn version(&self) -> HttpVersion {
+        self.settings.version
+    }
+
+    /// The timeout for establishing connections, see [`HttpClient::with_connect_timeout()`]
+    pub fn connect_timeout(&self) -> Option<Duration> {
+        self.settings.connect_timeout
+    }
+
+    /// Whether Nagle's algorithm is disabled, see [`HttpClient::with_tcp_nodelay()`]
+    pub fn tcp_nodelay(&self) -> bool {
+        self.settings.tcp_nodelay
+    }
+
+    /// How long idle connections are kept, see [`HttpClient::with_pool_idle_timeout()`]
+    pub fn pool_idle_timeout(&self) -> Option<Duration> {
+        self.settings.pool_idle_timeout
+    }
+
+    /// How many idle connections are kept per host,
+    /// see [`HttpClient::with_pool_max_idle_per_host()`]
+    pub fn pool_max_idle_per_host(&self) -> usize {
+        self.settings.pool_max_idle_per_host
+    }
+
+    pub async fn send(&self, request: HttpRequest) -> Result<String> {
+        let limit = request.max_response_size;
+        let response = self.send_request(request, None).await?;
+        read_body(response, limit).await
+    }
+
+    /// Sends the request, accepting a newline-delimited JSON response. Such responses
+    /// are read line by line as they arrive, other responses are read in full.
+    pub async fn send_streaming(&self, request: HttpRequest) -> Result<ResponseBody> {
+        let limit = request.max_response_size;
+        let accept = "application/x-ndjson, application/json";
+        let response = self.send_request(request, Some(accept)).await?;
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split(';').next())
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase();
+        if NDJSON_CONTENT_TYPES.contains(&content_type.as_str()) {
+            Ok(ResponseBody::Lines(read_lines(response, limit)))
+        } else {
+            read_body(response, limit).await.map(ResponseBody::Full)
         }
     }
 
-    pub async fn send(
+    /// Sends a `GET` request to the health endpoint at `url`. Errors sending the request
+    /// mean the server is unreachable, and are reported as such rather than returned.
+    pub async fn health(&self, url: String, timeout: Option<Duration>) -> HealthStatus {
+        let mut builder = self.inner.get(url);
+        if let Some(timeout) = timeout {
+            builder = builder.timeout(timeout);
+        }
+        match builder.send().await {
+            Ok(response) => HealthStatus {
+                reachable: true,
+                ok: response.status().is_success(),
+                status: Some(response.status().as_u16()),
+            },
+            Err(e) => {
+                tracing::debug!("Health check failed: {e}");
+                HealthStatus {
+                    reachable: false,
+                    ok: false,
+                    status: None,
+                }
+            }
+        }
+    }
+
+    async fn send_request(
         &self,
-        url: String,
-        auth: String,
-        body: String,
-    ) -> Result<pipeline::ServerMsg> {
-        let response = self
+        request: HttpRequest,
+        accept: Option<&str>,
+    ) -> Result<reqwest::Response> {
+        let mut builder = self
             .inner
-            .post(url)
-            .body(body)
-            .header("Authorization", auth)
-            .send()
-            .await?;
+            .post(request.url)
+            .body(request.body)
+            .header("Authorization", request.auth)
+            .header("X-Request-Id", request.request_id);
+        if let Some(accept) = accept {
+            builder = builder.header(reqwest::header::ACCEPT, accept);
+        }
+        if let Some(timeout) = request.timeout {
+            builder = builder.timeout(timeout);
+        }
+        let response = builder.send().await.map_err(map_reqwest_error)?;
         if response.status() != reqwest::StatusCode::OK {
-            let status = response.status();
-            let txt = response.text().await.unwrap_or_default();
-            anyhow::bail!("{status}: {txt}");
+            let status = response.status().as_u16();
+            let body = read_body(response, request.max_response_size)
+                .await
+                .unwrap_or_default();
+            return Err(crate::Error::Http { status, body }.into());
         }
-        let resp: String = response.text().await?;
-        let response: pipeline::ServerMsg = serde_json::from_str(&resp)?;
         Ok(response)
     }
 }
 
+/// Splits the body of the response into lines as it arrives, skipping blank ones.
+/// The stream ends after the first error.
+fn read_lines(
+    response: reqwest::Response,
+    limit: Option<usize>,
+) -> futures::stream::BoxStream<'static, Result<String>> {
+    struct State {
+        response: Option<reqwest::Response>,
+        buffer: Vec<u8>,
+        read: usize,
+    }
+
+    let state = State {
+        response: Some(response),
+        buffer: Vec::new(),
+        read: 0,
+    };
+    futures::stream::unfold(state, move |mut state| async move {
+        loop {
+            if let Some(end) = state.buffer.iter().position(|b| *b == b'\n') {
+                let line: Vec<u8> = state.buffer.drain(..=end).collect();
+                if line.iter().all(u8::is_ascii_whitespace) {
+                    continue;
+                }
+                return Some((String::from_utf8(line).map_err(Into::into), state));
+            }
+            let Some(response) = state.response.as_mut() else {
+                // The last line may lack a trailing newline
+                if state.buffer.iter().all(u8::is_ascii_whitespace) {
+                    return None;
+                }
+                let line = std::mem::take(&mut state.buffer);
+                return Some((String::from_utf8(line).map_err(Into::into), state));
+            };
+            let error = match response.chunk().await {
+                Ok(Some(chunk)) => {
+                    state.read += chunk.len();
+                    match limit {
+                        Some(limit) if state.read > limit => {
+                            crate::Error::ResponseTooLarge { limit }.into()
+                        }
+                        _ => {
+                            state.buffer.extend_from_slice(&chunk);
+                            continue;
+                        }
+                    }
+                }
+                Ok(None) => {
+                    state.response = None;
+                    continue;
+                }
+                Err(e) => map_reqwest_error(e),
+            };
+            state.response = None;
+            state.buffer.clear();
+            return Some((Err(error), state));
+        }
+    })
+    .boxed()
+}
+
+/// Reads the body of the response, failing as soon as it grows over `limit`
+async fn read_body(mut response: reqwest::Response, limit: Option<usize>) -> Result<String> {
+    let Some(limit) = limit else {
+        return response.text().await.map_err(map_reqwest_error);
+    };
+    let too_large = || crate::Error::ResponseTooLarge { limit };
+    // Not known in advance for chunked or compressed responses
+    if response.content_length().unwrap_or_default() > limit as u64 {
+        return Err(too_large().into());
+    }
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await.map_err(map_reqwest_error)? {
+        if body.len() + chunk.len() > limit {
+            return Err(too_large().into());
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(String::from_utf8(body)?)
+}
+
+fn map_reqwest_error(e: reqwest::Error) -> anyhow::Error {
+    if e.is_timeout() {
+        crate::Error::Timeout.into()
+    } else {
+        e.into()
+    }
+}
+
 impl Default for HttpClient {
     fn default() -> Self {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::{Client, InnerClient};
+    use crate::mock_server::{MockResponse, MockServer};
+
+    #[test]
+    fn test_http_version() {
+        assert_eq!(HttpClient::new().version(), HttpVersion::Negotiate);
+        let client = HttpClient::new().with_http1_only(true);
+        assert_eq!(client.version(), HttpVersion::Http1Only);
+        assert_eq!(
+            client.with_http1_only(false).version(),
+            HttpVersion::Negotiate
+        );
+        assert_eq!(
+            HttpClient::new().with_http2_prior_knowledge().version(),
+            HttpVersion::Http2PriorKnowledge
+        );
+    }
+
+    #[tokio::test]
+    async fn test_connect_settings() {
+        let client = HttpClient::new();
+        assert_eq!(client.connect_timeout(), None);
+        assert!(client.tcp_nodelay());
+
+        let client = client
+            .with_http1_only(true)
+            .with_connect_timeout(Duration::from_millis(500))
+            .with_tcp_nodelay(false);
+        assert_eq!(client.connect_timeout(), Some(Duration::from_millis(500)));
+        assert!(!client.tcp_nodelay());
+        // Other settings are kept when the client is rebuilt
+        assert_eq!(client.version(), HttpVersion::Http1Only);
+        let client = client.with_accept_invalid_certs(true);
+        assert_eq!(client.connect_timeout(), Some(Duration::from_millis(500)));
+
+        let server = MockServer::start(|_| MockResponse::empty_execute()).await;
+        let client = Client::new(InnerClient::Reqwest(client), &server.url, "");
+        client.execute("SELECT 1").await.unwrap();
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_pool_settings() {
+        let client = HttpClient::new();
+        assert_eq!(client.pool_idle_timeout(), Some(Duration::from_secs(90)));
+        assert_eq!(client.pool_max_idle_per_host(), usize::MAX);
+
+        let client = client
+            .with_pool_idle_timeout(Some(Duration::from_secs(30)))
+            .with_pool_max_idle_per_host(0);
+        assert_eq!(client.pool_idle_timeout(), Some(Duration::from_secs(30)));
+        assert_eq!(client.pool_max_idle_per_host(), 0);
+        assert_eq!(
+            client
+                .clone()
+                .with_tcp_nodelay(false)
+                .pool_max_idle_per_host(),
+            0
+        );
+
+        // Without idle connections in the pool, each request opens a new connection
+        let server = MockServer::start(|_| MockResponse::empty_execute()).await;
+        let client = Client::new(InnerClient::Reqwest(client), &server.url, "");
+        client.execute("SELECT 1").await.unwrap();
+        client.execute("SELECT 2").await.unwrap();
+        assert_eq!(server.connections(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_http1_only() {
+        let server = MockServer::start(|_| MockResponse::empty_execute()).await;
+        let inner = InnerClient::Reqwest(HttpClient::new().with_http1_only(true));
+        let client = Client::new(inner, &server.url, "");
+        client.execute("SELECT 1").await.unwrap();
+        assert_eq!(server.requests().len(), 1);
+
+        // The mock server only speaks HTTP/1.1
+        let inner = InnerClient::Reqwest(HttpClient::new().with_http2_prior_knowledge());
+        let client = Client::new(inner, &server.url, "");
+        assert!(client.execute("SELECT 1").await.is_err());
+    }
+
+    #[cfg(feature = "compression")]
+    fn compress(encoding: &str, body: &[u8]) -> Vec<u8> {
+        use std::io::Write;
+
+        let mut compressed = Vec::new();
+        match encoding {
+            "br" => {
+                let mut writer = brotli::CompressorWriter::new(&mut compressed, 4096, 5, 22);
+                writer.write_all(body).unwrap();
+            }
+            "gzip" => {
+                let mut writer = flate2::write::GzEncoder::new(&mut compressed, Default::default());
+                writer.write_all(body).unwrap();
+                writer.finish().unwrap();
+            }
+            _ => unreachable!(),
+        }
+        compressed
+    }
+
+    #[tokio::test]
+    async fn test_batch_streaming() {
+        use futures::TryStreamExt;
+
+        let lines = [
+            r#"{"cols":[{"name":"n"}],"rows":[[{"type":"integer","value":"1"}]],"affected_row_count":0,"last_insert_rowid":null}"#,
+            "",
+            r#"{"error":{"message":"no such table: missing"}}"#,
+            r#"{"cols":[{"name":"n"}],"rows":[[{"type":"integer","value":"3"}]]}"#,
+        ];
+        let server = MockServer::start(move |_| MockResponse {
+            status: 200,
+            headers: vec![("Content-Type".into(), "application/x-ndjson".into())],
+            body: lines.join("\n").into_bytes(),
+            delay: None,
+        })
+        .await;
+        let client = Client::new(InnerClient::Reqwest(HttpClient::new()), &server.url, "");
+        let mut results = client
+            .batch_streaming(["SELECT 1 AS n", "SELECT * FROM missing", "SELECT 3 AS n"])
+            .await
+            .unwrap();
+        let first = results.try_next().await.unwrap().unwrap();
+        assert_eq!(first.rows[0].try_get::<i64>(0).unwrap(), 1);
+        let err = results.try_next().await.unwrap_err();
+        assert!(err.to_string().contains("no such table"), "{err}");
+        let last = results.try_next().await.unwrap().unwrap();
+        assert_eq!(last.rows[0].try_get::<i64>(0).unwrap(), 3);
+        assert!(results.try_next().await.unwrap().is_none());
+
+        let accepted = server.requests()[0].header("accept").unwrap().to_string();
+        assert!(accepted.contains("application/x-ndjson"), "{accepted}");
+    }
+
+    #[tokio::test]
+    async fn test_batch_streaming_regular_response() {
+        use futures::TryStreamExt;
+
+        let server = MockServer::start(|_| {
+            MockResponse::json(
+                r#"{"baton":null,"base_url":null,"results":[{"type":"ok","response":{"type":"batch","result":{"step_results":[{"cols":[{"name":"n"}],"rows":[[{"type":"integer","value":"1"}]],"affected_row_count":0,"last_insert_rowid":null},{"cols":[{"name":"n"}],"rows":[[{"type":"integer","value":"2"}]],"affected_row_count":0,"last_insert_rowid":null}],"step_errors":[null,null]}}},{"type":"ok","response":{"type":"close"}}]}"#,
+            )
+        })
+        .await;
+        let client = Client::new(InnerClient::Reqwest(HttpClient::new()), &server.url, "");
+        let results: Vec<_> = client
+            .batch_streaming(["SELECT 1 AS n", "SELECT 2 AS n"])
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        let values: Vec<i64> = results
+            .iter()
+            .map(|rs| rs.rows[0].try_get(0).unwrap())
+            .collect();
+        assert_eq!(values, [1, 2]);
+    }
+
+    #[tokio::test]
+    async fn test_batch_streaming_token_refresh() {
+        use futures::TryStreamExt;
+
+        let server = MockServer::start(|request| match request.header("authorization") {
+            Some("Bearer token-1") => MockResponse::status(401),
+            _ => MockResponse {
+                status: 200,
+                headers: vec![("Content-Type".into(), "application/x-ndjson".into())],
+                body: br#"{"cols":[{"name":"n"}],"rows":[[{"type":"integer","value":"1"}]]}"#
+                    .to_vec(),
+                delay: None,
+            },
+        })
+        .await;
+        let calls = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let provider: crate::http::TokenProvider = {
+            let calls = calls.clone();
+            std::sync::Arc::new(move || {
+                let n = calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst) + 1;
+                Box::pin(async move { Ok(format!("token-{n}")) })
+            })
+        };
+        let client = Client::new(InnerClient::Reqwest(HttpClient::new()), &server.url, "")
+            .with_token_provider(provider);
+        // The first token is rejected, so a new one is fetched and the request retried
+        let results: Vec<_> = client
+            .batch_streaming(["SELECT 1 AS n"])
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(results[0].rows[0].try_get::<i64>(0).unwrap(), 1);
+        assert_eq!(server.requests().len(), 2);
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_max_response_size() {
+        let result = format!(
+            r#"{{"cols":[{{"name":"s"}}],"rows":[[{{"type":"text","value":"{}"}}]],"affected_row_count":0,"last_insert_rowid":null}}"#,
+            "x".repeat(10_000)
+        );
+        let server = MockServer::start(move |_| MockResponse::execute(&result)).await;
+        let client = Client::new(InnerClient::Reqwest(HttpClient::new()), &server.url, "");
+        client.execute("SELECT 1").await.unwrap();
+
+        let limited = client.clone().with_max_response_size(1_000);
+        let err = limited.execute("SELECT 1").await.unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<crate::Error>(),
+            Some(&crate::Error::ResponseTooLarge { limit: 1_000 })
+        );
+        let generous = client.with_max_response_size(100_000);
+        generous.execute("SELECT 1").await.unwrap();
+    }
+
+    #[cfg(feature = "compression")]
+    #[tokio::test]
+    async fn test_max_response_size_decompressed() {
+        // The compressed body is tiny, but its size is only known after decompression
+        let result = format!(
+            r#"{{"cols":[{{"name":"s"}}],"rows":[[{{"type":"text","value":"{}"}}]],"affected_row_count":0,"last_insert_rowid":null}}"#,
+            "x".repeat(100_000)
+        );
+        let server = MockServer::start(move |_| {
+            let mut response = MockResponse::execute(&result);
+            response.body = compress("gzip", &response.body);
+            response
+                .headers
+                .push(("Content-Encoding".into(), "gzip".into()));
+            response
+        })
+        .await;
+        let client = Client::new(InnerClient::Reqwest(HttpClient::new()), &server.url, "")
+            .with_max_response_size(10_000);
+        let err = client.execute("SELECT 1").await.unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<crate::Error>(),
+            Some(&crate::Error::ResponseTooLarge { limit: 10_000 })
+        );
+    }
+
+    #[cfg(feature = "compression")]
+    #[tokio::test]
+    async fn test_compressed_responses() {
+        for encoding in ["br", "gzip"] {
+            let server = MockServer::start(move |_| {
+                let mut response = MockResponse::execute(
+                    r#"{"cols":[{"name":"n"}],"rows":[[{"type":"integer","value":"42"}]],"affected_row_count":0,"last_insert_rowid":null}"#,
+                );
+                response.body = compress(encoding, &response.body);
+                response
+                    .headers
+                    .push(("Content-Encoding".into(), encoding.into()));
+                response
+            })
+            .await;
+            let client = Client::new(InnerClient::Reqwest(HttpClient::new()), &server.url, "");
+            let rs = client.execute("SELECT 42 AS n").await.unwrap();
+            assert_eq!(rs.rows[0].try_get::<i64>(0).unwrap(), 42, "{encoding}");
+
+            let accepted = server.requests()[0]
+                .header("accept-encoding")
+                .unwrap()
+                .to_string();
+            assert!(accepted.contains("gzip"), "{accepted}");
+            assert!(accepted.contains("br"), "{accepted}");
+        }
+    }
+}
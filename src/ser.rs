@@ -0,0 +1,249 @@
+//! libsql serialization utilities.
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::Value;
+
+/// Serialize any type `T` that implements [`serde::Serialize`] into named values,
+/// one per field, e.g. to be inserted as a row.
+///
+/// # Types
+///
+/// `T` must serialize to a map, as structs do. Its values must be scalars:
+///
+/// - strings, serialized as text
+/// - integers within the range of `i64`, and booleans as `0` or `1`
+/// - floats
+/// - `None` and `()`, serialized as `NULL`
+///
+/// Nested structs and sequences, including `Vec<u8>`, are rejected.
+///
+/// # Example
+///
+/// ```
+/// use libsql_client::ser;
+///
+/// #[derive(serde::Serialize)]
+/// struct User {
+///     name: String,
+///     age: i64,
+/// }
+///
+/// let values = ser::to_named_values(&User { name: "alice".into(), age: 42 }).unwrap();
+/// assert_eq!(values.len(), 2);
+/// ```
+pub fn to_named_values<T: Serialize>(value: &T) -> Result<Vec<(String, Value)>> {
+    let serde_json::Value::Object(map) = serde_json::to_value(value)? else {
+        anyhow::bail!("Only structs and maps can be serialized into named values");
+    };
+    map.into_iter()
+        .map(|(name, value)| {
+            let value = to_value(value).map_err(|e| anyhow::anyhow!("field `{name}`: {e}"))?;
+            Ok((name, value))
+        })
+        .collect()
+}
+
+/// Converts a boolean into an integer [Value], `1` for `true` and `0` for `false`,
+/// following the SQLite convention since it has no boolean storage class
+///
+/// [Value] comes from the protocol crate, so it cannot implement `From<bool>` here:
+/// use this function to bind booleans as parameters.
+///
+/// # Example
+///
+/// ```
+/// use libsql_client::{ser, Statement};
+///
+/// let stmt = Statement::with_args(
+///     "UPDATE users SET active = ? WHERE id = ?",
+///     &[ser::bool_value(true), 42.into()],
+/// );
+/// ```
+pub fn bool_value(value: bool) -> Value {
+    Value::Integer {
+        value: value as i64,
+    }
+}
+
+/// Serializes any type `T` that implements [`serde::Serialize`] into JSON text,
+/// for binding it as a parameter to SQLite's JSON functions
+///
+/// The JSON is bound as text, which functions such as `json_extract()` accept as is.
+/// To read JSON text back, see [`Row::try_get_json()`](crate::Row::try_get_json).
+///
+/// # Example
+///
+/// ```
+/// use libsql_client::{ser, Statement};
+///
+/// #[derive(serde::Serialize)]
+/// struct Settings {
+///     theme: String,
+/// }
+///
+/// let settings = Settings { theme: "dark".into() };
+/// let stmt = Statement::with_args(
+///     "UPDATE users SET settings = json(?) WHERE id = ?",
+///     &[ser::json_value(&settings).unwrap(), 42.into()],
+/// );
+/// ```
+pub fn json_value<T: Serialize + ?Sized>(value: &T) -> Result<Value> {
+    Ok(Value::Text {
+        value: serde_json::to_string(value)?,
+    })
+}
+
+/// Converts a point in time into an integer [Value] of seconds since the Unix epoch,
+/// the format of SQLite's `unixepoch()` function
+///
+/// Seconds rather than milliseconds are used so that SQLite's date and time functions
+/// can read them with the `'unixepoch'` modifier. Fractions of seconds are rounded down,
+/// including for times before the epoch. To read timestamps back, get them as
+/// [`SystemTime`](std::time::SystemTime) with [`Row::try_get()`](crate::Row::try_get).
+///
+/// # Example
+///
+/// ```
+/// use libsql_client::{ser, Statement};
+///
+/// let stmt = Statement::with_args(
+///     "INSERT INTO events(created_at) VALUES (?)",
+///     &[ser::timestamp_value(std::time::SystemTime::now())],
+/// );
+/// ```
+pub fn timestamp_value(time: std::time::SystemTime) -> Value {
+    let secs = match time.duration_since(std::time::UNIX_EPOCH) {
+        Ok(since) => i64::try_from(since.as_secs()).unwrap_or(i64::MAX),
+        Err(e) => {
+            let before = e.duration();
+            let secs = before.as_secs() + u64::from(before.subsec_nanos() > 0);
+            i64::try_from(secs).map(|secs| -secs).unwrap_or(i64::MIN)
+        }
+    };
+    Value::Integer { value: secs }
+}
+
+/// Parses a string of hexadecimal digits into a blob [Value], e.g. for looking up rows
+/// by a blob key written as in logs or SQL `x'...'` literals
+///
+/// Digits may be in either case. Will return an error if the string has an odd length
+/// or contains anything other than hexadecimal digits.
+///
+/// # Example
+///
+/// ```
+/// use libsql_client::{ser, Value};
+///
+/// let key = ser::blob_from_hex("deadBEEF").unwrap();
+/// assert!(matches!(&key, Value::Blob { value } if value == &[0xde, 0xad, 0xbe, 0xef]));
+/// assert_eq!(ser::blob_to_hex(&key).as_deref(), Some("deadbeef"));
+/// assert!(ser::blob_from_hex("abc").is_err());
+/// ```
+pub fn blob_from_hex(hex: &str) -> Result<Value> {
+    if !hex.len().is_multiple_of(2) {
+        anyhow::bail!("Hex string has an odd number of digits: {hex}");
+    }
+    let value = hex
+        .as_bytes()
+        .chunks(2)
+        .map(|pair| {
+            std::str::from_utf8(pair)
+                .ok()
+                .filter(|pair| pair.chars().all(|c| c.is_ascii_hexdigit()))
+                .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                .ok_or_else(|| anyhow::anyhow!("Invalid hex digits in {hex}"))
+        })
+        .collect::<Result<_>>()?;
+    Ok(Value::Blob { value })
+}
+
+/// Formats a blob [Value] as lowercase hexadecimal digits, the inverse of [blob_from_hex()].
+/// Returns `None` for other types of values.
+///
+/// [Value] comes from the protocol crate, whose `Display` implementation
+/// formats blobs as base64, and cannot be changed here.
+pub fn blob_to_hex(value: &Value) -> Option<String> {
+    match value {
+        Value::Blob { value } => Some(value.iter().map(|b| format!("{b:02x}")).collect()),
+        _ => None,
+    }
+}
+
+fn to_value(value: serde_json::Value) -> Result<Value> {
+    Ok(match value {
+        serde_json::Value::Null => Value::Null,
+        serde_json::Value::Bool(b) => bool_value(b),
+        serde_json::Value::Number(n) => match (n.as_i64(), n.as_f64()) {
+            (Some(value), _) => Value::Integer { value },
+            (None, Some(value)) if !n.is_u64() => Value::Float { value },
+            _ => anyhow::bail!("{n} is out of the range of 64-bit signed integers"),
+        },
+        serde_json::Value::String(value) => Value::Text { value },
+        other => anyhow::bail!("cannot serialize {other} into a single value"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blob_hex() {
+        let Value::Blob { value } = blob_from_hex("00ffA07f").unwrap() else {
+            panic!("expected a blob");
+        };
+        assert_eq!(value, [0x00, 0xff, 0xa0, 0x7f]);
+        assert!(matches!(blob_from_hex("").unwrap(), Value::Blob { value } if value.is_empty()));
+
+        for invalid in ["0", "abc", "zz", "+1", "0x00", "é1"] {
+            assert!(blob_from_hex(invalid).is_err(), "{invalid}");
+        }
+
+        let blob = Value::Blob {
+            value: (0..=255).collect(),
+        };
+        let hex = blob_to_hex(&blob).unwrap();
+        assert_eq!(hex.len(), 512);
+        assert!(
+            matches!(blob_from_hex(&hex).unwrap(), Value::Blob { value } if value == (0..=255).collect::<Vec<u8>>())
+        );
+        assert_eq!(blob_to_hex(&Value::Text { value: "00".into() }), None);
+    }
+
+    #[test]
+    fn test_to_named_values() {
+        #[derive(Serialize)]
+        struct Foo {
+            text: String,
+            int: i64,
+            float: f64,
+            flag: bool,
+            missing: Option<i64>,
+        }
+
+        let values = to_named_values(&Foo {
+            text: "foo".into(),
+            int: 42,
+            float: 1.5,
+            flag: true,
+            missing: None,
+        })
+        .unwrap();
+        let values: std::collections::HashMap<_, _> = values.into_iter().collect();
+        assert!(matches!(&values["text"], Value::Text { value } if value == "foo"));
+        assert!(matches!(values["int"], Value::Integer { value: 42 }));
+        assert!(matches!(values["float"], Value::Float { value } if value == 1.5));
+        assert!(matches!(values["flag"], Value::Integer { value: 1 }));
+        assert!(matches!(values["missing"], Value::Null));
+
+        #[derive(Serialize)]
+        struct Nested {
+            list: Vec<i64>,
+        }
+        assert!(to_named_values(&Nested { list: vec![1] }).is_err());
+        assert!(to_named_values(&42).is_err());
+        assert!(to_named_values(&std::collections::HashMap::from([("big", u64::MAX)])).is_err());
+    }
+}
@@ -1,6 +1,6 @@
 use anyhow::Result;
 
-use crate::proto::pipeline;
+use crate::http::HttpRequest;
 
 #[derive(Clone, Debug)]
 pub struct HttpClient;
@@ -10,21 +10,33 @@ impl HttpClient {
         Self
     }
 
-    pub async fn send(
-        &self,
-        url: String,
-        auth: String,
-        body: String,
-    ) -> Result<pipeline::ServerMsg> {
+    /// Sends the request. Timeouts are not supported by this backend and are ignored.
+    /// The response size limit is checked once the whole body was read,
+    /// so it does not bound memory usage.
+    pub async fn send(&self, request: HttpRequest) -> Result<String> {
+        let HttpRequest {
+            url,
+            auth,
+            body,
+            request_id,
+            max_response_size,
+            ..
+        } = request;
         let req = http::Request::builder()
             .uri(&url)
             .header("Authorization", &auth)
+            .header("X-Request-Id", &request_id)
             .method("POST")
             .body(Some(bytes::Bytes::copy_from_slice(body.as_bytes())))?;
 
         let response: http::Response<String> = spin_sdk::http::send(req).await?;
-        let response: pipeline::ServerMsg = serde_json::from_str(&response.into_body())?;
-        Ok(response)
+        let body = response.into_body();
+        if let Some(limit) = max_response_size {
+            if body.len() > limit {
+                return Err(crate::Error::ResponseTooLarge { limit }.into());
+            }
+        }
+        Ok(body)
     }
 }
 
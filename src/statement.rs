@@ -1,20 +1,54 @@
 //! `Statement` represents an SQL statement,
 //! which can be later sent to a database.
 
+use anyhow::Result;
 use base64::prelude::BASE64_STANDARD_NO_PAD;
 use base64::Engine;
+use sqlite3_parser::dialect::TokenType;
+use sqlite3_parser::lexer::sql::Tokenizer;
+use sqlite3_parser::lexer::Scanner;
 
 use crate::Value;
 
 /// SQL statement, possibly with bound parameters
+///
+/// Statements are equal, and hash the same, if their SQL and parameters are equal,
+/// so they can be used as keys of maps. Labels are not compared. Floats are compared
+/// by their bit pattern, so `NaN` equals itself, but `0.0` and `-0.0` differ,
+/// as do integer and float parameters with the same numeric value.
+///
+/// Statements can be serialized, e.g. to JSON for audit logs, and deserialized later
+/// to be executed again. The SQL, parameters and label are kept as they are. Parameters
+/// are tagged with their type like in the Hrana protocol, so integers are stored as strings
+/// to keep their precision and blobs are stored in base64. NaN and infinite floats, which
+/// JSON cannot represent, are stored as the strings `NaN`, `Infinity` and `-Infinity`.
+///
+/// ```
+/// let stmt = libsql_client::Statement::with_args("SELECT ?", &[42]);
+/// let json = serde_json::to_string(&stmt).unwrap();
+/// assert_eq!(json, r#"{"sql":"SELECT ?","args":[{"type":"integer","value":"42"}]}"#);
+/// let restored: libsql_client::Statement = serde_json::from_str(&json).unwrap();
+/// assert_eq!(restored, stmt);
+/// ```
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
 pub struct Statement {
     pub(crate) sql: String,
+    #[serde(default, with = "stored_args")]
     pub(crate) args: Vec<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) label: Option<String>,
 }
 
 impl Statement {
     /// Creates a new simple statement without bound parameters
     ///
+    /// Surrounding whitespace and a single trailing semicolon are removed from the SQL,
+    /// so `"SELECT 1;"` and `"SELECT 1"` are equivalent. Postgres-style `$1`, `$2`, ...
+    /// placeholders are rewritten to `?1`, `?2`, ..., so that they refer to parameters
+    /// by position, like in Postgres. For SQLite, they would be named parameters instead,
+    /// numbered in order of appearance. To send the SQL exactly as given,
+    /// use [`Statement::verbatim()`].
+    ///
     /// # Examples
     ///
     /// ```
@@ -22,32 +56,610 @@ impl Statement {
     /// ```
     pub fn new(q: impl Into<String>) -> Statement {
         Self {
-            sql: q.into(),
+            sql: normalize_sql(q.into()),
             args: vec![],
+            label: None,
         }
     }
 
     /// Creates a statement with bound parameters
     ///
+    /// The SQL is normalized the same way as in [`Statement::new()`]. Parameters are bound
+    /// by position: `?` takes the next one, while `?NNN` and `$NNN` take the NNN-th one.
+    ///
     /// # Examples
     ///
     /// ```
     /// let stmt = libsql_client::Statement::with_args("UPDATE t SET x = ? WHERE key = ?", &[3, 8]);
+    /// let stmt = libsql_client::Statement::with_args("UPDATE t SET x = $2 WHERE key = $1", &[8, 3]);
+    /// assert_eq!(stmt.sql(), "UPDATE t SET x = ?2 WHERE key = ?1");
     /// ```
     pub fn with_args(q: impl Into<String>, params: &[impl Into<Value> + Clone]) -> Statement {
         Self {
-            sql: q.into(),
+            sql: normalize_sql(q.into()),
             args: params.iter().map(|p| p.clone().into()).collect(),
+            label: None,
+        }
+    }
+
+    /// Creates a new simple statement, keeping the SQL exactly as given
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let stmt = libsql_client::Statement::verbatim("SELECT 1;\n");
+    /// ```
+    pub fn verbatim(q: impl Into<String>) -> Statement {
+        Self {
+            sql: q.into(),
+            args: vec![],
+            label: None,
+        }
+    }
+
+    /// Attaches a label to this statement, to tell which statement failed.
+    ///
+    /// The label is not sent to the server. It is included in errors returned for
+    /// this statement and recorded in the tracing span of its execution.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let stmt = libsql_client::Statement::new("SELECT * FROM users").with_label("list users");
+    /// assert_eq!(stmt.label(), Some("list users"));
+    /// ```
+    pub fn with_label(mut self, label: impl Into<String>) -> Statement {
+        self.label = Some(label.into());
+        self
+    }
+
+    /// Returns the label attached with [`Statement::with_label()`], if any
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    /// Returns the SQL of this statement, after normalization
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let stmt = libsql_client::Statement::with_args("SELECT * FROM t WHERE id = ?;", &[1]);
+    /// assert_eq!(stmt.sql(), "SELECT * FROM t WHERE id = ?");
+    /// ```
+    pub fn sql(&self) -> &str {
+        &self.sql
+    }
+
+    /// Returns the parameters bound to this statement
+    pub fn args(&self) -> &[Value] {
+        &self.args
+    }
+
+    /// Creates a `DELETE` statement for rows of `table` matching `where_clause`
+    ///
+    /// The clause is inserted verbatim after `WHERE`, so values should be passed
+    /// as `?` placeholders and bound through `params`. Table names are not escaped.
+    /// Returns an error if the clause is empty or if the number of placeholders
+    /// does not match the number of parameters.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let stmt = libsql_client::Statement::delete("users", "id = ?", &[42]).unwrap();
+    /// ```
+    pub fn delete(
+        table: &str,
+        where_clause: &str,
+        params: &[impl Into<Value> + Clone],
+    ) -> Result<Statement> {
+        check_where_clause(where_clause, params.len())?;
+        Ok(Statement::with_args(
+            format!("DELETE FROM {table} WHERE {where_clause}"),
+            params,
+        ))
+    }
+
+    /// Creates an `INSERT ... ON CONFLICT DO NOTHING` statement, which inserts a row into `table`
+    /// unless it would violate a uniqueness constraint, e.g. because it already exists.
+    /// Unlike `INSERT OR IGNORE`, violations of other constraints, like `NOT NULL` or `CHECK`,
+    /// still fail the statement.
+    ///
+    /// Values are bound as positional parameters. Table and column names are not escaped.
+    /// Returns an error if there are no columns or if the numbers of columns and values differ.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use libsql_client::{Statement, Value};
+    /// let stmt = Statement::insert_or_ignore(
+    ///     "users",
+    ///     &["email", "name"],
+    ///     &[Value::from("a@b.c"), Value::from("Alice")],
+    /// ).unwrap();
+    /// assert_eq!(
+    ///     stmt.sql(),
+    ///     "INSERT INTO users (email, name) VALUES (?, ?) ON CONFLICT DO NOTHING"
+    /// );
+    /// ```
+    pub fn insert_or_ignore(
+        table: &str,
+        columns: &[&str],
+        values: &[impl Into<Value> + Clone],
+    ) -> Result<Statement> {
+        if columns.is_empty() {
+            anyhow::bail!("INSERT into `{table}` needs at least one column");
         }
+        if columns.len() != values.len() {
+            anyhow::bail!(
+                "INSERT into `{table}` has {} columns, but {} values were given",
+                columns.len(),
+                values.len()
+            );
+        }
+        let placeholders = vec!["?"; columns.len()].join(", ");
+        Ok(Statement::with_args(
+            format!(
+                "INSERT INTO {table} ({}) VALUES ({placeholders}) ON CONFLICT DO NOTHING",
+                columns.join(", ")
+            ),
+            values,
+        ))
+    }
+
+    /// Creates a statement returning a single row with `1` if `table` has rows matching
+    /// `where_clause`, and `0` otherwise. See [`Client::exists()`](crate::Client::exists).
+    ///
+    /// The clause follows the same rules as in [`Statement::delete()`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let stmt = libsql_client::Statement::exists("users", "email = ?", &["a@b.c"]).unwrap();
+    /// assert_eq!(stmt.sql(), "SELECT EXISTS(SELECT 1 FROM users WHERE email = ?)");
+    /// ```
+    pub fn exists(
+        table: &str,
+        where_clause: &str,
+        params: &[impl Into<Value> + Clone],
+    ) -> Result<Statement> {
+        check_where_clause(where_clause, params.len())?;
+        Ok(Statement::with_args(
+            format!("SELECT EXISTS(SELECT 1 FROM {table} WHERE {where_clause})"),
+            params,
+        ))
+    }
+
+    /// Creates a statement counting the rows of `table`, or only those matching `where_clause`
+    /// if given. See [`Client::count()`](crate::Client::count).
+    ///
+    /// The clause follows the same rules as in [`Statement::delete()`]. Without a clause,
+    /// there are no placeholders, so `params` must be empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let stmt = libsql_client::Statement::count("users", Some("age > ?"), &[18]).unwrap();
+    /// assert_eq!(stmt.sql(), "SELECT COUNT(*) FROM users WHERE age > ?");
+    /// ```
+    pub fn count(
+        table: &str,
+        where_clause: Option<&str>,
+        params: &[impl Into<Value> + Clone],
+    ) -> Result<Statement> {
+        let sql = match where_clause {
+            Some(where_clause) => {
+                check_where_clause(where_clause, params.len())?;
+                format!("SELECT COUNT(*) FROM {table} WHERE {where_clause}")
+            }
+            None if params.is_empty() => format!("SELECT COUNT(*) FROM {table}"),
+            None => anyhow::bail!(
+                "Counting all rows of `{table}` expects no parameters, but {} were given",
+                params.len()
+            ),
+        };
+        Ok(Statement::with_args(sql, params))
+    }
+
+    /// Creates an `UPDATE` statement setting columns of `table` for rows matching `where_clause`
+    ///
+    /// Each `(column, value)` pair in `set` becomes a `column = ?` assignment, with values bound
+    /// before `where_params`. The clause follows the same rules as in [`Statement::delete()`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use libsql_client::{Statement, Value};
+    /// let stmt = Statement::update(
+    ///     "users",
+    ///     &[("name", Value::from("John")), ("age", Value::from(42))],
+    ///     "id = ?",
+    ///     &[7],
+    /// ).unwrap();
+    /// ```
+    pub fn update(
+        table: &str,
+        set: &[(&str, Value)],
+        where_clause: &str,
+        where_params: &[impl Into<Value> + Clone],
+    ) -> Result<Statement> {
+        if set.is_empty() {
+            anyhow::bail!("UPDATE of `{table}` needs at least one column to set");
+        }
+        check_where_clause(where_clause, where_params.len())?;
+        let assignments: Vec<String> = set.iter().map(|(col, _)| format!("{col} = ?")).collect();
+        let args = set
+            .iter()
+            .map(|(_, value)| value.clone())
+            .chain(where_params.iter().map(|p| p.clone().into()))
+            .collect();
+        Ok(Statement {
+            sql: format!(
+                "UPDATE {table} SET {} WHERE {where_clause}",
+                assignments.join(", ")
+            ),
+            args,
+            label: None,
+        })
+    }
+
+    /// Creates a statement with bound parameters, some of which may be arrays,
+    /// e.g. for `IN` lists
+    ///
+    /// SQLite needs one placeholder per value, so the SQL is rewritten: the `?` placeholder
+    /// of each [`Param::Array`] is replaced with as many comma-separated `?` as the array has
+    /// elements, and its elements are bound in its place. `WHERE id IN (?)` thus becomes
+    /// `WHERE id IN (?, ?, ?)` for a 3-element array, and `WHERE id IN ()`, which SQLite
+    /// accepts and which matches nothing, for an empty one. Placeholders in literals and
+    /// comments are left alone.
+    ///
+    /// Since the rewriting shifts positions, only plain `?` placeholders are supported.
+    /// Returns an error for numbered or named placeholders, and if the number of placeholders
+    /// does not match the number of parameters.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsql_client::{Param, Statement};
+    ///
+    /// let stmt = Statement::with_array_args(
+    ///     "SELECT * FROM users WHERE id IN (?) AND active = ?",
+    ///     &[Param::array([1, 2, 3]), Param::from(1)],
+    /// )
+    /// .unwrap();
+    /// assert_eq!(stmt.sql(), "SELECT * FROM users WHERE id IN (?, ?, ?) AND active = ?");
+    /// assert_eq!(stmt.args().len(), 4);
+    /// ```
+    pub fn with_array_args(q: impl Into<String>, params: &[Param]) -> Result<Statement> {
+        let sql = normalize_sql(q.into());
+        let input = sql.as_bytes();
+        let mut scanner = Scanner::new(Tokenizer::new());
+        let mut placeholders = Vec::new();
+        while let (start, Some((token, token_type)), end) = scanner.scan(input)? {
+            if token_type != TokenType::TK_VARIABLE {
+                continue;
+            }
+            if !token.is_empty() {
+                anyhow::bail!(
+                    "Only `?` placeholders can be used with array parameters, found `{}`",
+                    &sql[start..end]
+                );
+            }
+            placeholders.push(start);
+        }
+        if placeholders.len() != params.len() {
+            anyhow::bail!(
+                "Statement has {} placeholders, but {} parameters were given",
+                placeholders.len(),
+                params.len()
+            );
+        }
+
+        let mut rewritten = String::with_capacity(sql.len());
+        let mut args = Vec::with_capacity(params.len());
+        let mut copied = 0;
+        for (start, param) in placeholders.into_iter().zip(params) {
+            rewritten.push_str(&sql[copied..start]);
+            match param {
+                Param::Value(value) => {
+                    rewritten.push('?');
+                    args.push(value.clone());
+                }
+                Param::Array(values) => {
+                    rewritten.push_str(&vec!["?"; values.len()].join(", "));
+                    args.extend(values.iter().cloned());
+                }
+            }
+            copied = start + 1;
+        }
+        rewritten.push_str(&sql[copied..]);
+        Ok(Statement {
+            sql: rewritten,
+            args,
+            label: None,
+        })
+    }
+}
+
+/// A parameter of [`Statement::with_array_args()`]
+#[derive(Clone, Debug)]
+pub enum Param {
+    /// A single value, bound to its placeholder as is
+    Value(Value),
+    /// A list of values, expanded into one placeholder per value
+    Array(Vec<Value>),
+}
+
+impl Param {
+    /// Creates an array parameter from `values`
+    pub fn array<T: Into<Value>>(values: impl IntoIterator<Item = T>) -> Param {
+        Param::Array(values.into_iter().map(Into::into).collect())
     }
 }
 
+impl<T: Into<Value>> From<T> for Param {
+    fn from(value: T) -> Param {
+        Param::Value(value.into())
+    }
+}
+
+/// Adds the label of the failed statement, if any, to `error`, keeping the original error
+/// in the message
+pub(crate) fn label_error(error: anyhow::Error, label: Option<&str>) -> anyhow::Error {
+    match label {
+        Some(label) => error.context(format!("Statement `{label}` failed")),
+        None => error,
+    }
+}
+
+fn check_where_clause(where_clause: &str, params_len: usize) -> Result<()> {
+    if where_clause.trim().is_empty() {
+        anyhow::bail!("Refusing to build a statement with an empty WHERE clause");
+    }
+    let expected = parameter_count(where_clause)?;
+    if expected != params_len {
+        anyhow::bail!(
+            "WHERE clause `{where_clause}` expects {expected} parameters, but {params_len} were given"
+        );
+    }
+    Ok(())
+}
+
+/// Kind of an SQL statement, as determined by [classify()]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum StatementKind {
+    /// `SELECT`, `VALUES` and `EXPLAIN`
+    Read,
+    /// `INSERT`, `REPLACE`, `UPDATE` and `DELETE`, as well as `ANALYZE`,
+    /// `REINDEX` and `VACUUM`, which modify the database file
+    Write,
+    /// `CREATE`, `DROP` and `ALTER`
+    Ddl,
+    /// `PRAGMA`, which can either read or change settings
+    Pragma,
+    /// `BEGIN`, `COMMIT`, `END`, `ROLLBACK`, `SAVEPOINT` and `RELEASE`
+    Transaction,
+    /// Anything else, including empty and unparseable SQL
+    Unknown,
+}
+
+/// Classifies an SQL statement by its leading keyword, ignoring whitespace and comments
+///
+/// Statements starting with `WITH` are classified by the statement following
+/// their common table expressions, so `WITH ... SELECT` is a read and `WITH ... INSERT` is a write.
+/// SQL holding several statements is [Unknown](StatementKind::Unknown), since backends
+/// differ in whether they run the statements after the first one. Note that reads may still
+/// have side effects, e.g. by calling user-defined functions.
+///
+/// # Examples
+///
+/// ```
+/// use libsql_client::statement::{classify, StatementKind};
+///
+/// assert_eq!(classify("SELECT * FROM users"), StatementKind::Read);
+/// assert_eq!(classify("-- log\nINSERT INTO log VALUES (1)"), StatementKind::Write);
+/// assert_eq!(
+///     classify("WITH t AS (SELECT 1) DELETE FROM users WHERE id IN t"),
+///     StatementKind::Write
+/// );
+/// ```
+pub fn classify(sql: &str) -> StatementKind {
+    if has_several_statements(sql) {
+        return StatementKind::Unknown;
+    }
+    let input = sql.as_bytes();
+    let mut scanner = Scanner::new(Tokenizer::new());
+    let mut in_with = false;
+    let mut depth = 0usize;
+    while let Ok((_, Some((_, token_type)), _)) = scanner.scan(input) {
+        if in_with {
+            // Skip over the common table expressions, looking for the main statement
+            match token_type {
+                TokenType::TK_LP => depth += 1,
+                TokenType::TK_RP => depth = depth.saturating_sub(1),
+                TokenType::TK_SEMI => return StatementKind::Unknown,
+                _ if depth == 0 => match token_type {
+                    TokenType::TK_SELECT | TokenType::TK_VALUES => return StatementKind::Read,
+                    TokenType::TK_INSERT
+                    | TokenType::TK_REPLACE
+                    | TokenType::TK_UPDATE
+                    | TokenType::TK_DELETE => return StatementKind::Write,
+                    _ => {}
+                },
+                _ => {}
+            }
+            continue;
+        }
+        return match token_type {
+            TokenType::TK_WITH => {
+                in_with = true;
+                continue;
+            }
+            TokenType::TK_SELECT | TokenType::TK_VALUES | TokenType::TK_EXPLAIN => {
+                StatementKind::Read
+            }
+            TokenType::TK_INSERT
+            | TokenType::TK_REPLACE
+            | TokenType::TK_UPDATE
+            | TokenType::TK_DELETE
+            | TokenType::TK_ANALYZE
+            | TokenType::TK_REINDEX
+            | TokenType::TK_VACUUM => StatementKind::Write,
+            TokenType::TK_CREATE | TokenType::TK_DROP | TokenType::TK_ALTER => StatementKind::Ddl,
+            TokenType::TK_PRAGMA => StatementKind::Pragma,
+            TokenType::TK_BEGIN
+            | TokenType::TK_COMMIT
+            | TokenType::TK_END
+            | TokenType::TK_ROLLBACK
+            | TokenType::TK_SAVEPOINT
+            | TokenType::TK_RELEASE => StatementKind::Transaction,
+            _ => StatementKind::Unknown,
+        };
+    }
+    StatementKind::Unknown
+}
+
+/// Checks whether any token follows a semicolon ending a statement, i.e. one which is
+/// neither in parentheses nor in the body of a trigger
+fn has_several_statements(sql: &str) -> bool {
+    let input = sql.as_bytes();
+    let mut scanner = Scanner::new(Tokenizer::new());
+    let mut depth = 0usize;
+    let mut in_trigger = false;
+    let mut in_trigger_body = false;
+    let mut ended = false;
+    while let Ok((_, Some((_, token_type)), _)) = scanner.scan(input) {
+        if ended {
+            return true;
+        }
+        match token_type {
+            TokenType::TK_LP => depth += 1,
+            TokenType::TK_RP => depth = depth.saturating_sub(1),
+            TokenType::TK_TRIGGER => in_trigger = true,
+            TokenType::TK_BEGIN if in_trigger => in_trigger_body = true,
+            TokenType::TK_END if in_trigger_body => {
+                in_trigger = false;
+                in_trigger_body = false;
+            }
+            TokenType::TK_SEMI if depth == 0 && !in_trigger_body => ended = true,
+            _ => {}
+        }
+    }
+    false
+}
+
+/// Trims surrounding whitespace and strips a single trailing semicolon,
+/// as long as it is a separate token and not a part of a literal or a comment.
+/// `$NNN` placeholders are then rewritten to `?NNN`, see [`Statement::new()`].
+fn normalize_sql(sql: String) -> String {
+    rewrite_dollar_params(trim_sql(sql))
+}
+
+fn trim_sql(sql: String) -> String {
+    let trimmed = sql.trim();
+    let input = trimmed.as_bytes();
+    let mut scanner = Scanner::new(Tokenizer::new());
+    let mut last = None;
+    loop {
+        match scanner.scan(input) {
+            Ok((start, Some((_, token_type)), end)) => last = Some((start, token_type, end)),
+            Ok((_, None, _)) => break,
+            // Leave invalid SQL alone and let the server report the error
+            Err(_) => return trimmed.to_string(),
+        }
+    }
+    match last {
+        Some((start, TokenType::TK_SEMI, end)) if end == input.len() => {
+            trimmed[..start].trim_end().to_string()
+        }
+        _ => trimmed.to_string(),
+    }
+}
+
+/// Rewrites Postgres-style `$NNN` placeholders to `?NNN`, leaving literals and comments alone
+fn rewrite_dollar_params(sql: String) -> String {
+    let input = sql.as_bytes();
+    let mut scanner = Scanner::new(Tokenizer::new());
+    let mut placeholders = Vec::new();
+    loop {
+        match scanner.scan(input) {
+            Ok((start, Some((token, TokenType::TK_VARIABLE)), _)) if is_dollar_index(token) => {
+                placeholders.push(start)
+            }
+            Ok((_, Some(_), _)) => {}
+            Ok((_, None, _)) => break,
+            // Leave invalid SQL alone and let the server report the error
+            Err(_) => return sql,
+        }
+    }
+    if placeholders.is_empty() {
+        return sql;
+    }
+    let mut rewritten = sql.into_bytes();
+    for start in placeholders {
+        rewritten[start] = b'?';
+    }
+    // Only an ASCII character was replaced with another one
+    String::from_utf8(rewritten).unwrap()
+}
+
+/// Returns true for variable tokens of the form `$NNN`
+fn is_dollar_index(token: &[u8]) -> bool {
+    match token.split_first() {
+        Some((b'$', digits)) => !digits.is_empty() && digits.iter().all(u8::is_ascii_digit),
+        _ => false,
+    }
+}
+
+/// Returns the number of parameters SQLite expects to be bound for `sql`,
+/// following the rules of `sqlite3_bind_parameter_count()`:
+/// `?` takes the next free index, `?NNN` takes index NNN,
+/// and each distinct named parameter takes the next free index.
+/// `$NNN` is counted like `?NNN`, since [Statement] rewrites it to that form.
+pub(crate) fn parameter_count(sql: &str) -> Result<usize> {
+    Ok(parameters(sql)?.0)
+}
+
+/// Returns the named parameters of `sql` along with their indices, in order,
+/// as assigned by [parameter_count()]
+pub(crate) fn named_parameters(sql: &str) -> Result<Vec<(String, usize)>> {
+    Ok(parameters(sql)?.1)
+}
+
+fn parameters(sql: &str) -> Result<(usize, Vec<(String, usize)>)> {
+    let input = sql.as_bytes();
+    let mut scanner = Scanner::new(Tokenizer::new());
+    let mut count = 0;
+    let mut names: Vec<(String, usize)> = Vec::new();
+    while let (_, Some((token, token_type)), _) = scanner.scan(input)? {
+        if token_type != TokenType::TK_VARIABLE {
+            continue;
+        }
+        match token.first() {
+            // `?` is stripped from positional parameters by the tokenizer
+            None => count += 1,
+            Some(b'0'..=b'9') => {
+                let index: usize = std::str::from_utf8(token)?.parse()?;
+                count = count.max(index);
+            }
+            Some(b'$') if is_dollar_index(token) => {
+                let index: usize = std::str::from_utf8(&token[1..])?.parse()?;
+                count = count.max(index);
+            }
+            Some(_) => {
+                let name = std::str::from_utf8(token)?;
+                if !names.iter().any(|(n, _)| n == name) {
+                    count += 1;
+                    names.push((name.to_string(), count));
+                }
+            }
+        }
+    }
+    Ok((count, names))
+}
+
 impl From<String> for Statement {
     fn from(q: String) -> Statement {
-        Statement {
-            sql: q,
-            args: vec![],
-        }
+        Statement::new(q)
     }
 }
 
@@ -63,6 +675,166 @@ impl From<&&str> for Statement {
     }
 }
 
+impl AsRef<str> for Statement {
+    fn as_ref(&self) -> &str {
+        &self.sql
+    }
+}
+
+/// Extracts the SQL of a statement, dropping its parameters and label
+impl From<Statement> for String {
+    fn from(stmt: Statement) -> String {
+        stmt.sql
+    }
+}
+
+impl PartialEq for Statement {
+    fn eq(&self, other: &Self) -> bool {
+        self.sql == other.sql
+            && self.args.len() == other.args.len()
+            && self
+                .args
+                .iter()
+                .zip(&other.args)
+                .all(|(a, b)| value_eq(a, b))
+    }
+}
+
+impl Eq for Statement {}
+
+impl std::hash::Hash for Statement {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.sql.hash(state);
+        self.args.len().hash(state);
+        for arg in &self.args {
+            std::mem::discriminant(arg).hash(state);
+            match arg {
+                Value::Null => {}
+                Value::Integer { value } => value.hash(state),
+                Value::Float { value } => value.to_bits().hash(state),
+                Value::Text { value } => value.hash(state),
+                Value::Blob { value } => value.hash(state),
+            }
+        }
+    }
+}
+
+/// Serialization of the parameters of a [Statement], in the format of the Hrana protocol.
+///
+/// [Value] implements it too, but can only be deserialized from borrowed strings,
+/// which rules out deserializing from readers and from `serde_json::Value`.
+mod stored_args {
+    use super::*;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(tag = "type", rename_all = "snake_case")]
+    enum StoredValue {
+        Null,
+        Integer { value: String },
+        Float { value: StoredFloat },
+        Text { value: String },
+        Blob { base64: String },
+    }
+
+    /// JSON has no representation for NaN and infinities, so they are stored as strings
+    #[derive(Serialize, Deserialize)]
+    #[serde(untagged)]
+    enum StoredFloat {
+        Finite(f64),
+        NonFinite(String),
+    }
+
+    impl From<f64> for StoredFloat {
+        fn from(value: f64) -> Self {
+            if value.is_finite() {
+                StoredFloat::Finite(value)
+            } else if value.is_nan() {
+                StoredFloat::NonFinite("NaN".to_string())
+            } else if value > 0. {
+                StoredFloat::NonFinite("Infinity".to_string())
+            } else {
+                StoredFloat::NonFinite("-Infinity".to_string())
+            }
+        }
+    }
+
+    impl TryFrom<StoredFloat> for f64 {
+        type Error = String;
+
+        fn try_from(stored: StoredFloat) -> Result<Self, Self::Error> {
+            match stored {
+                StoredFloat::Finite(value) => Ok(value),
+                StoredFloat::NonFinite(value) => match value.as_str() {
+                    "NaN" => Ok(f64::NAN),
+                    "Infinity" => Ok(f64::INFINITY),
+                    "-Infinity" => Ok(f64::NEG_INFINITY),
+                    _ => Err(format!("invalid float `{value}`")),
+                },
+            }
+        }
+    }
+
+    pub fn serialize<S: Serializer>(args: &[Value], ser: S) -> Result<S::Ok, S::Error> {
+        let stored: Vec<StoredValue> = args
+            .iter()
+            .map(|arg| match arg {
+                Value::Null => StoredValue::Null,
+                Value::Integer { value } => StoredValue::Integer {
+                    value: value.to_string(),
+                },
+                Value::Float { value } => StoredValue::Float {
+                    value: (*value).into(),
+                },
+                Value::Text { value } => StoredValue::Text {
+                    value: value.clone(),
+                },
+                Value::Blob { value } => StoredValue::Blob {
+                    base64: BASE64_STANDARD_NO_PAD.encode(value),
+                },
+            })
+            .collect();
+        stored.serialize(ser)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(de: D) -> Result<Vec<Value>, D::Error> {
+        use serde::de::Error;
+
+        Vec::<StoredValue>::deserialize(de)?
+            .into_iter()
+            .map(|stored| {
+                Ok(match stored {
+                    StoredValue::Null => Value::Null,
+                    StoredValue::Integer { value } => Value::Integer {
+                        value: value.parse().map_err(D::Error::custom)?,
+                    },
+                    StoredValue::Float { value } => Value::Float {
+                        value: value.try_into().map_err(D::Error::custom)?,
+                    },
+                    StoredValue::Text { value } => Value::Text { value },
+                    StoredValue::Blob { base64 } => Value::Blob {
+                        value: BASE64_STANDARD_NO_PAD
+                            .decode(base64.trim_end_matches('='))
+                            .map_err(D::Error::custom)?,
+                    },
+                })
+            })
+            .collect()
+    }
+}
+
+/// Compares values consistently with hashing them, see [Statement]
+fn value_eq(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Null, Value::Null) => true,
+        (Value::Integer { value: a }, Value::Integer { value: b }) => a == b,
+        (Value::Float { value: a }, Value::Float { value: b }) => a.to_bits() == b.to_bits(),
+        (Value::Text { value: a }, Value::Text { value: b }) => a == b,
+        (Value::Blob { value: a }, Value::Blob { value: b }) => a == b,
+        _ => false,
+    }
+}
+
 impl std::fmt::Display for Statement {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let params: Vec<String> = self
@@ -84,3 +856,305 @@ impl std::fmt::Display for Statement {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_with_array_args() {
+        let stmt = Statement::with_array_args(
+            "SELECT * FROM users WHERE id IN (?) AND name != '?'",
+            &[Param::array([1, 2, 3])],
+        )
+        .unwrap();
+        assert_eq!(
+            stmt.sql(),
+            "SELECT * FROM users WHERE id IN (?, ?, ?) AND name != '?'"
+        );
+        let args: Vec<i64> = stmt
+            .args()
+            .iter()
+            .map(|v| match v {
+                Value::Integer { value } => *value,
+                other => panic!("unexpected {other:?}"),
+            })
+            .collect();
+        assert_eq!(args, [1, 2, 3]);
+
+        let stmt = Statement::with_array_args(
+            "DELETE FROM t WHERE a = ? AND b IN (?) AND c = ?",
+            &[
+                Param::from("x"),
+                Param::array(Vec::<i64>::new()),
+                Param::from(2.5),
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            stmt.sql(),
+            "DELETE FROM t WHERE a = ? AND b IN () AND c = ?"
+        );
+        assert_eq!(stmt.args().len(), 2);
+
+        assert!(Statement::with_array_args("SELECT ?", &[]).is_err());
+        assert!(Statement::with_array_args("SELECT ?1", &[Param::from(1)]).is_err());
+        assert!(Statement::with_array_args("SELECT :id", &[Param::from(1)]).is_err());
+    }
+
+    #[test]
+    fn test_normalize_trailing_semicolon() {
+        assert_eq!(Statement::new("SELECT 1;").sql, "SELECT 1");
+        assert_eq!(Statement::from("SELECT 1 ;").sql, "SELECT 1");
+        assert_eq!(Statement::new("SELECT 1;;").sql, "SELECT 1;");
+        assert_eq!(Statement::new("BEGIN; SELECT 1;").sql, "BEGIN; SELECT 1");
+        assert_eq!(Statement::new("SELECT ';'").sql, "SELECT ';'");
+        assert_eq!(Statement::new("SELECT 1 -- done;").sql, "SELECT 1 -- done;");
+        assert_eq!(Statement::verbatim("SELECT 1;").sql, "SELECT 1;");
+    }
+
+    #[test]
+    fn test_normalize_whitespace() {
+        assert_eq!(Statement::new("  \n\tSELECT 1\n ").sql, "SELECT 1");
+        assert_eq!(
+            Statement::with_args("\nSELECT '  a  ',\n  ?  ", &[1]).sql,
+            "SELECT '  a  ',\n  ?"
+        );
+        assert_eq!(Statement::verbatim(" SELECT 1 ").sql, " SELECT 1 ");
+    }
+
+    #[test]
+    fn test_parameter_count() {
+        assert_eq!(parameter_count("SELECT 1").unwrap(), 0);
+        assert_eq!(parameter_count("a = ? AND b = ?").unwrap(), 2);
+        assert_eq!(parameter_count("a = '?' AND b = ? -- ?").unwrap(), 1);
+        assert_eq!(parameter_count("a = ?3 AND b = ?1").unwrap(), 3);
+        assert_eq!(parameter_count("a = :x AND b = :x AND c = ?").unwrap(), 2);
+        assert_eq!(parameter_count("a = $2 AND b = $1 AND c = $2").unwrap(), 2);
+        assert_eq!(parameter_count("a = $3").unwrap(), 3);
+        assert_eq!(parameter_count("a = $x AND b = $x").unwrap(), 1);
+    }
+
+    #[test]
+    fn test_dollar_params() {
+        let stmt = Statement::with_args("SELECT $2, $1, $2 FROM t WHERE x = '$1' -- $1", &[1, 2]);
+        assert_eq!(stmt.sql(), "SELECT ?2, ?1, ?2 FROM t WHERE x = '$1' -- $1");
+        // Named parameters are kept
+        assert_eq!(Statement::new("SELECT $name").sql(), "SELECT $name");
+        assert_eq!(Statement::verbatim("SELECT $1").sql(), "SELECT $1");
+    }
+
+    #[test]
+    fn test_update() {
+        let stmt = Statement::update(
+            "users",
+            &[("name", Value::from("John")), ("age", Value::from(42))],
+            "id = ? AND active = ?",
+            &[7, 1],
+        )
+        .unwrap();
+        assert_eq!(
+            stmt.sql,
+            "UPDATE users SET name = ?, age = ? WHERE id = ? AND active = ?"
+        );
+        assert_eq!(
+            stmt.to_string(),
+            r#"{"sql": "UPDATE users SET name = ?, age = ? WHERE id = ? AND active = ?", "args": ["John","42","7","1"]}"#
+        );
+    }
+
+    #[test]
+    fn test_update_validation() {
+        let no_columns = Statement::update("users", &[], "id = ?", &[7]);
+        assert!(no_columns.is_err());
+        let missing_param =
+            Statement::update("users", &[("age", Value::from(1))], "id = ?", &[] as &[i64]);
+        assert!(missing_param.is_err());
+    }
+
+    #[test]
+    fn test_insert_or_ignore() {
+        let stmt = Statement::insert_or_ignore("users", &["id", "name"], &["7", "John"]).unwrap();
+        assert_eq!(
+            stmt.to_string(),
+            r#"{"sql": "INSERT INTO users (id, name) VALUES (?, ?) ON CONFLICT DO NOTHING", "args": ["7","John"]}"#
+        );
+        assert!(Statement::insert_or_ignore("users", &[], &[] as &[i64]).is_err());
+        assert!(Statement::insert_or_ignore("users", &["id", "name"], &[7]).is_err());
+    }
+
+    #[test]
+    fn test_delete() {
+        let stmt = Statement::delete("users", "id = ? OR name = ?", &["7", "John"]).unwrap();
+        assert_eq!(stmt.sql, "DELETE FROM users WHERE id = ? OR name = ?");
+        assert_eq!(
+            stmt.to_string(),
+            r#"{"sql": "DELETE FROM users WHERE id = ? OR name = ?", "args": ["7","John"]}"#
+        );
+        assert!(Statement::delete("users", "", &[] as &[i64]).is_err());
+        assert!(Statement::delete("users", "id = ?", &[1, 2]).is_err());
+    }
+
+    #[test]
+    fn test_read_sql() {
+        let stmt = Statement::with_args("SELECT * FROM t WHERE id = ? AND name = ?;", &["1", "x"])
+            .with_label("lookup");
+        let copy = stmt.clone();
+        assert_eq!(copy.sql(), "SELECT * FROM t WHERE id = ? AND name = ?");
+        assert_eq!(copy.to_string(), stmt.to_string());
+        assert_eq!(copy.args().len(), 2);
+        assert_eq!(copy.label(), Some("lookup"));
+
+        let as_ref: &str = stmt.as_ref();
+        assert_eq!(as_ref, stmt.sql());
+        let sql: String = stmt.into();
+        assert_eq!(sql, "SELECT * FROM t WHERE id = ? AND name = ?");
+    }
+
+    #[test]
+    fn test_classify() {
+        use StatementKind::*;
+        let cases = [
+            ("SELECT * FROM t", Read),
+            ("  values (1), (2)", Read),
+            ("EXPLAIN QUERY PLAN SELECT 1", Read),
+            ("/* hint */ -- comment\n select 1", Read),
+            ("INSERT INTO t VALUES (1)", Write),
+            ("REPLACE INTO t VALUES (1)", Write),
+            ("UPDATE t SET x = 1", Write),
+            ("DELETE FROM t", Write),
+            ("VACUUM", Write),
+            ("CREATE TABLE t(x)", Ddl),
+            ("DROP INDEX i", Ddl),
+            ("ALTER TABLE t ADD COLUMN y", Ddl),
+            ("PRAGMA table_info(t)", Pragma),
+            ("BEGIN IMMEDIATE", Transaction),
+            ("COMMIT", Transaction),
+            ("END", Transaction),
+            ("ROLLBACK TO sp", Transaction),
+            ("SAVEPOINT sp", Transaction),
+            ("RELEASE sp", Transaction),
+            ("WITH t AS (SELECT 1) SELECT * FROM t", Read),
+            (
+                "WITH RECURSIVE c(n) AS (VALUES(1) UNION ALL SELECT n + 1 FROM c WHERE n < 5) SELECT n FROM c",
+                Read,
+            ),
+            (
+                "WITH t(x) AS MATERIALIZED (SELECT 1) INSERT INTO u SELECT x FROM t",
+                Write,
+            ),
+            (
+                "WITH a AS (SELECT 1), b AS (SELECT 2) UPDATE t SET x = 1",
+                Write,
+            ),
+            ("SELECT 1;", Read),
+            ("SELECT 1; -- done", Read),
+            ("SELECT ';'; ", Read),
+            (
+                "CREATE TRIGGER t AFTER INSERT ON u BEGIN DELETE FROM v; END;",
+                Ddl,
+            ),
+            ("SELECT 1; DROP TABLE u", Unknown),
+            ("SELECT 1;;", Unknown),
+            (
+                "CREATE TRIGGER t AFTER INSERT ON u BEGIN DELETE FROM v; END; DROP TABLE u",
+                Unknown,
+            ),
+            ("ATTACH 'db' AS other", Unknown),
+            ("", Unknown),
+            ("-- only a comment", Unknown),
+            ("WITH t AS (SELECT 1)", Unknown),
+            ("\u{1}", Unknown),
+        ];
+        for (sql, expected) in cases {
+            assert_eq!(classify(sql), expected, "{sql}");
+        }
+    }
+
+    #[test]
+    fn test_eq_and_hash() {
+        fn hash(stmt: &Statement) -> u64 {
+            use std::hash::{BuildHasher, BuildHasherDefault};
+            BuildHasherDefault::<std::collections::hash_map::DefaultHasher>::default()
+                .hash_one(stmt)
+        }
+        let stmt = |args: &[Value]| Statement::with_args("SELECT ?, ?", args);
+
+        let a = stmt(&[1.into(), vec![1u8, 2].into()]);
+        let b = stmt(&[1.into(), vec![1u8, 2].into()]);
+        assert_eq!(a, b);
+        assert_eq!(hash(&a), hash(&b));
+        // Labels are not compared
+        assert_eq!(a, b.clone().with_label("b"));
+
+        for other in [
+            stmt(&[2.into(), vec![1u8, 2].into()]),
+            stmt(&[1.into(), vec![1u8, 3].into()]),
+            stmt(&[1.0.into(), vec![1u8, 2].into()]),
+            stmt(&[1.into(), "\x01\x02".into()]),
+            stmt(&[1.into()]),
+            Statement::with_args("SELECT ?,?", &[Value::from(1), vec![1u8, 2].into()]),
+        ] {
+            assert_ne!(a, other, "{other}");
+            assert_ne!(hash(&a), hash(&other), "{other}");
+        }
+
+        assert_eq!(stmt(&[f64::NAN.into()]), stmt(&[f64::NAN.into()]));
+        assert_ne!(stmt(&[0.0.into()]), stmt(&[(-0.0).into()]));
+
+        let mut counts = std::collections::HashMap::new();
+        *counts.entry(a).or_insert(0) += 1;
+        *counts.entry(b).or_insert(0) += 1;
+        assert_eq!(counts.len(), 1);
+    }
+
+    #[test]
+    fn test_serde_round_trip() {
+        let stmt = Statement::with_args(
+            "INSERT INTO t VALUES (?, ?, ?, ?, ?)",
+            &[
+                Value::Integer { value: i64::MAX },
+                Value::Float { value: 1.5 },
+                Value::Text {
+                    value: "text".into(),
+                },
+                Value::Blob {
+                    value: vec![0xde, 0xad, 0xbe, 0xef],
+                },
+                Value::Null,
+            ],
+        )
+        .with_label("audit");
+        let json = serde_json::to_value(&stmt).unwrap();
+        assert_eq!(json["sql"], "INSERT INTO t VALUES (?, ?, ?, ?, ?)");
+        assert_eq!(json["label"], "audit");
+        assert_eq!(json["args"][0]["value"], i64::MAX.to_string());
+        assert_eq!(json["args"][3]["base64"], "3q2+7w");
+
+        let restored: Statement = serde_json::from_value(json).unwrap();
+        assert_eq!(restored, stmt);
+        assert_eq!(restored.label(), Some("audit"));
+
+        let restored: Statement = serde_json::from_str(r#"{"sql":"SELECT 1"}"#).unwrap();
+        assert_eq!(restored, Statement::new("SELECT 1"));
+        let invalid = r#"{"sql":"SELECT ?","args":[{"type":"integer","value":"x"}]}"#;
+        assert!(serde_json::from_str::<Statement>(invalid).is_err());
+        let invalid = r#"{"sql":"SELECT ?","args":[{"type":"float","value":"x"}]}"#;
+        assert!(serde_json::from_str::<Statement>(invalid).is_err());
+    }
+
+    #[test]
+    fn test_serde_non_finite_floats() {
+        let stmt = Statement::with_args(
+            "SELECT ?, ?, ?",
+            &[f64::NAN, f64::INFINITY, f64::NEG_INFINITY],
+        );
+        let json = serde_json::to_string(&stmt).unwrap();
+        assert_eq!(
+            json,
+            r#"{"sql":"SELECT ?, ?, ?","args":[{"type":"float","value":"NaN"},{"type":"float","value":"Infinity"},{"type":"float","value":"-Infinity"}]}"#
+        );
+        let restored: Statement = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, stmt);
+    }
+}
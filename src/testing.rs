@@ -0,0 +1,118 @@
+//! Helpers for asserting on query results in tests.
+//!
+//! Enabled with the `testing` feature.
+
+use crate::{ResultSet, Value};
+
+/// Asserts that `rs` has exactly the columns `expected_columns` and the rows `expected_rows`.
+///
+/// On mismatch, panics with a line-by-line diff of the columns and rows,
+/// where expected lines are prefixed with `-` and actual ones with `+`.
+/// Cells are rendered as SQL literals, e.g. `NULL`, `42`, `'text'` or `x'0102'`.
+///
+/// # Examples
+///
+/// ```
+/// use libsql_client::testing::assert_result_eq;
+/// use libsql_client::Value;
+///
+/// let db = libsql_client::SyncClient::in_memory().unwrap();
+/// let rs = db.execute("SELECT 1 AS id, 'one' AS name").unwrap();
+/// assert_result_eq(&rs, &["id", "name"], &[&[Value::from(1), Value::from("one")]]);
+/// ```
+#[track_caller]
+pub fn assert_result_eq(rs: &ResultSet, expected_columns: &[&str], expected_rows: &[&[Value]]) {
+    let mut diff = String::new();
+    let mut matches = true;
+    let mut compare = |expected: String, actual: Option<String>| match actual {
+        Some(actual) if actual == expected => diff.push_str(&format!("  {expected}\n")),
+        actual => {
+            matches = false;
+            diff.push_str(&format!("- {expected}\n"));
+            if let Some(actual) = actual {
+                diff.push_str(&format!("+ {actual}\n"));
+            }
+        }
+    };
+
+    compare(
+        format!("columns: {expected_columns:?}"),
+        Some(format!("columns: {:?}", rs.column_names())),
+    );
+    for (i, expected) in expected_rows.iter().enumerate() {
+        compare(
+            render_row(expected),
+            rs.rows.get(i).map(|row| render_row(&row.values)),
+        );
+    }
+    for row in rs.rows.iter().skip(expected_rows.len()) {
+        matches = false;
+        diff.push_str(&format!("+ {}\n", render_row(&row.values)));
+    }
+
+    if !matches {
+        panic!("result set does not match (-expected +actual):\n{diff}");
+    }
+}
+
+fn render_row(values: &[Value]) -> String {
+    let cells: Vec<String> = values.iter().map(render_value).collect();
+    format!("({})", cells.join(", "))
+}
+
+fn render_value(value: &Value) -> String {
+    match value {
+        Value::Null => "NULL".to_string(),
+        Value::Integer { value } => value.to_string(),
+        Value::Float { value } => format!("{value:?}"),
+        Value::Text { value } => format!("'{}'", value.replace('\'', "''")),
+        Value::Blob { .. } => format!("x'{}'", crate::ser::blob_to_hex(value).unwrap_or_default()),
+    }
+}
+
+#[cfg(all(test, feature = "local_backend"))]
+mod tests {
+    use super::*;
+    use crate::SyncClient;
+
+    fn result_set() -> ResultSet {
+        let db = SyncClient::in_memory().unwrap();
+        db.execute("SELECT 1 AS id, 'one' AS name UNION ALL SELECT 2, NULL")
+            .unwrap()
+    }
+
+    #[test]
+    fn test_assert_result_eq() {
+        assert_result_eq(
+            &result_set(),
+            &["id", "name"],
+            &[
+                &[Value::from(1), Value::from("one")],
+                &[Value::from(2), Value::Null],
+            ],
+        );
+    }
+
+    #[test]
+    fn test_assert_result_eq_diff() {
+        let rs = result_set();
+        let payload = std::panic::catch_unwind(|| {
+            assert_result_eq(
+                &rs,
+                &["id", "name"],
+                &[&[Value::from(1), Value::from("uno")]],
+            )
+        })
+        .unwrap_err();
+        assert_eq!(
+            payload.downcast_ref::<String>().unwrap(),
+            "\
+result set does not match (-expected +actual):
+  columns: [\"id\", \"name\"]
+- (1, 'uno')
++ (1, 'one')
++ (2, NULL)
+"
+        );
+    }
+}
@@ -0,0 +1,190 @@
+//! An HTTP backend sending requests through a [`tower::Service`],
+//! so that existing middleware stacks (auth refresh, rate limiting, tracing) can be reused.
+
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use tower::util::BoxCloneService;
+use tower::{BoxError, Service, ServiceExt};
+
+use crate::http::HttpRequest;
+
+type BoxedService = BoxCloneService<http::Request<String>, http::Response<String>, BoxError>;
+
+#[derive(Clone)]
+pub struct HttpClient {
+    // Services are not required to be `Sync`, so they are cloned out of the mutex for each request
+    service: Arc<Mutex<BoxedService>>,
+}
+
+impl std::fmt::Debug for HttpClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("tower::HttpClient").finish_non_exhaustive()
+    }
+}
+
+impl HttpClient {
+    /// Wraps `service`, which receives one `POST` request per round-trip to the server
+    pub fn new<S>(service: S) -> Self
+    where
+        S: Service<http::Request<String>, Response = http::Response<String>>
+            + Clone
+            + Send
+            + 'static,
+        S::Error: Into<BoxError>,
+        S::Future: Send + 'static,
+    {
+        let service = BoxCloneService::new(service.map_err(Into::into));
+        Self {
+            service: Arc::new(Mutex::new(service)),
+        }
+    }
+
+    /// Sends the request. Timeouts are not supported by this backend and are ignored,
+    /// they can be enforced by a middleware instead. The response size limit is checked
+    /// once the service returned the whole body, so it does not bound memory usage.
+    pub async fn send(&self, request: HttpRequest) -> Result<String> {
+        let HttpRequest {
+            url,
+            auth,
+            body,
+            request_id,
+            extensions,
+            max_response_size,
+            ..
+        } = request;
+        let mut req = http::Request::builder()
+            .uri(&url)
+            .method("POST")
+            .header("Authorization", &auth)
+            .header("X-Request-Id", &request_id)
+            .body(body)?;
+        if !extensions.is_empty() {
+            req.extensions_mut().insert(extensions);
+        }
+
+        let service = self.service.lock().unwrap().clone();
+        let response = service.oneshot(req).await.map_err(|e| anyhow::anyhow!(e))?;
+        if response.status() != http::StatusCode::OK {
+            let status = response.status().as_u16();
+            return Err(crate::Error::Http {
+                status,
+                body: response.into_body(),
+            }
+            .into());
+        }
+        let body = response.into_body();
+        if let Some(limit) = max_response_size {
+            if body.len() > limit {
+                return Err(crate::Error::ResponseTooLarge { limit }.into());
+            }
+        }
+        Ok(body)
+    }
+}
+
+impl crate::http::Client {
+    /// Creates a database client sending its requests through `service`
+    ///
+    /// # Arguments
+    /// * `service` - service sending HTTP requests, e.g. a stack of middleware over an HTTP client
+    /// * `url` - URL of the database endpoint
+    /// * `token` - auth token
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f() {
+    /// let service = tower::service_fn(|req: http::Request<String>| async move {
+    ///     // Send `req` with any HTTP client
+    ///     # let _ = req;
+    ///     # Ok::<_, std::convert::Infallible>(http::Response::new(String::new()))
+    /// });
+    /// let db = libsql_client::http::Client::from_service(service, "https://example.com/", "token");
+    /// # }
+    /// ```
+    pub fn from_service<S>(service: S, url: impl Into<String>, token: impl Into<String>) -> Self
+    where
+        S: Service<http::Request<String>, Response = http::Response<String>>
+            + Clone
+            + Send
+            + 'static,
+        S::Error: Into<BoxError>,
+        S::Future: Send + 'static,
+    {
+        let inner = crate::http::InnerClient::Tower(HttpClient::new(service));
+        Self::new(inner, url, token)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_from_service() {
+        let received: Arc<Mutex<Vec<http::Request<String>>>> = Default::default();
+        let sink = received.clone();
+        let service = tower::service_fn(move |req: http::Request<String>| {
+            sink.lock().unwrap().push(req);
+            async {
+                let body = r#"{"baton":null,"base_url":null,"results":[{"type":"ok","response":{"type":"batch","result":{"step_results":[{"cols":[{"name":"n"}],"rows":[[{"type":"integer","value":"1"}]],"affected_row_count":0,"last_insert_rowid":null},{"cols":[{"name":"n"}],"rows":[[{"type":"integer","value":"2"}]],"affected_row_count":0,"last_insert_rowid":null}],"step_errors":[null,null]}}},{"type":"ok","response":{"type":"close"}}]}"#;
+                Ok::<_, std::convert::Infallible>(http::Response::new(body.to_string()))
+            }
+        });
+        let client = crate::http::Client::from_service(service, "https://example.com/", "token");
+        let result = client.raw_batch(["SELECT 1", "SELECT 2"]).await.unwrap();
+        assert_eq!(result.step_results.len(), 2);
+
+        let err = client
+            .with_max_response_size(100)
+            .raw_batch(["SELECT 1", "SELECT 2"])
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<crate::Error>(),
+            Some(&crate::Error::ResponseTooLarge { limit: 100 })
+        );
+
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 2);
+        assert_eq!(received[0].method(), http::Method::POST);
+        assert_eq!(received[0].uri(), "https://example.com/v2/pipeline");
+        assert_eq!(received[0].headers()["authorization"], "Bearer token");
+        assert!(received[0].body().contains("SELECT 2"));
+    }
+
+    #[tokio::test]
+    async fn test_batch_with_extensions() {
+        #[derive(Debug, PartialEq)]
+        struct TenantId(u64);
+
+        let received: Arc<Mutex<Vec<Option<u64>>>> = Default::default();
+        let sink = received.clone();
+        let service = tower::service_fn(move |req: http::Request<String>| {
+            let tenant = req
+                .extensions()
+                .get::<crate::http::Extensions>()
+                .and_then(|ext| ext.get::<TenantId>())
+                .map(|tenant| tenant.0);
+            sink.lock().unwrap().push(tenant);
+            assert!(!req.body().contains("TenantId"));
+            async {
+                let body = r#"{"baton":null,"base_url":null,"results":[{"type":"ok","response":{"type":"batch","result":{"step_results":[{"cols":[],"rows":[],"affected_row_count":0,"last_insert_rowid":null},{"cols":[{"name":"n"}],"rows":[[{"type":"integer","value":"1"}]],"affected_row_count":0,"last_insert_rowid":null},{"cols":[],"rows":[],"affected_row_count":0,"last_insert_rowid":null}],"step_errors":[null,null,null]}}},{"type":"ok","response":{"type":"close"}}]}"#;
+                Ok::<_, std::convert::Infallible>(http::Response::new(body.to_string()))
+            }
+        });
+        let db = crate::Client::Http(crate::http::Client::from_service(
+            service,
+            "https://example.com/",
+            "token",
+        ));
+
+        let mut ext = crate::http::Extensions::new();
+        ext.insert(TenantId(7));
+        db.batch_with_extensions(["SELECT 1"], ext).await.unwrap();
+        // Extensions only apply to the call they were passed to
+        db.batch(["SELECT 1"]).await.unwrap();
+        assert_eq!(*received.lock().unwrap(), [Some(7), None]);
+    }
+}
@@ -3,6 +3,34 @@
 use crate::{Client, ResultSet, Statement, SyncClient};
 use anyhow::Result;
 
+/// Locking behavior of a transaction, selecting the `BEGIN` statement which starts it,
+/// see [`Client::batch_transaction_with()`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TxMode {
+    /// `BEGIN`: locks are only taken when the database is first read or written.
+    /// Best for transactions which mostly read, but a transaction which reads before writing
+    /// may fail with `SQLITE_BUSY` when upgrading its lock if another one wrote in between.
+    #[default]
+    Deferred,
+    /// `BEGIN IMMEDIATE`: the write lock is taken upfront, so other writers wait or fail
+    /// when the transaction starts rather than midway. Best for transactions which write.
+    Immediate,
+    /// `BEGIN EXCLUSIVE`: like [`TxMode::Immediate`], but also keeps readers out in
+    /// journaling modes other than WAL. Rarely needed, e.g. for consistent maintenance tasks.
+    Exclusive,
+}
+
+impl TxMode {
+    /// Returns the statement starting a transaction in this mode
+    pub(crate) fn begin(self) -> Statement {
+        Statement::from(match self {
+            TxMode::Deferred => "BEGIN",
+            TxMode::Immediate => "BEGIN IMMEDIATE",
+            TxMode::Exclusive => "BEGIN EXCLUSIVE",
+        })
+    }
+}
+
 pub struct Transaction<'a> {
     pub(crate) id: u64,
     pub(crate) client: &'a Client,
@@ -10,9 +38,15 @@ pub struct Transaction<'a> {
 
 impl<'a> Transaction<'a> {
     pub async fn new(client: &'a Client, id: u64) -> Result<Transaction<'a>> {
-        client
-            .execute_in_transaction(id, Statement::from("BEGIN"))
-            .await?;
+        Self::with_mode(client, id, TxMode::Deferred).await
+    }
+
+    pub(crate) async fn with_mode(
+        client: &'a Client,
+        id: u64,
+        mode: TxMode,
+    ) -> Result<Transaction<'a>> {
+        client.execute_in_transaction(id, mode.begin()).await?;
         Ok(Self { id, client })
     }
 
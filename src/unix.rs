@@ -0,0 +1,158 @@
+//! An HTTP backend talking to a co-located sqld over a Unix domain socket.
+//!
+//! Only available on Unix platforms, such as Linux and macOS.
+
+use std::path::PathBuf;
+
+use anyhow::Result;
+
+use crate::http::HttpRequest;
+
+#[derive(Clone, Debug)]
+pub struct HttpClient {
+    socket_path: PathBuf,
+}
+
+impl HttpClient {
+    pub fn new(socket_path: impl Into<PathBuf>) -> Self {
+        Self {
+            socket_path: socket_path.into(),
+        }
+    }
+
+    /// The path of the socket requests are sent to
+    pub fn socket_path(&self) -> &std::path::Path {
+        &self.socket_path
+    }
+
+    /// Sends the request over a new connection to the socket.
+    /// Connections are not pooled, since connecting to a local socket is cheap.
+    pub async fn send(&self, request: HttpRequest) -> Result<String> {
+        match request.timeout {
+            Some(timeout) => tokio::time::timeout(timeout, self.send_inner(request))
+                .await
+                .map_err(|_| crate::Error::Timeout)?,
+            None => self.send_inner(request).await,
+        }
+    }
+
+    async fn send_inner(&self, request: HttpRequest) -> Result<String> {
+        // Only the path is meaningful, the host is a placeholder required by HTTP/1.1
+        let url = url::Url::parse(&request.url)?;
+        let path = match url.query() {
+            Some(query) => format!("{}?{query}", url.path()),
+            None => url.path().to_string(),
+        };
+        let req = hyper::Request::builder()
+            .method("POST")
+            .uri(path)
+            .header("Host", "localhost")
+            .header("Authorization", request.auth)
+            .header("X-Request-Id", request.request_id)
+            .body(hyper::Body::from(request.body))?;
+
+        let stream = tokio::net::UnixStream::connect(&self.socket_path).await?;
+        let (mut sender, connection) = hyper::client::conn::handshake(stream).await?;
+        tokio::spawn(async move {
+            // Errors are reported through the response instead
+            let _ = connection.await;
+        });
+        let response = sender.send_request(req).await?;
+        let status = response.status().as_u16();
+        let body = read_body(response.into_body(), request.max_response_size).await?;
+        if status != 200 {
+            return Err(crate::Error::Http { status, body }.into());
+        }
+        Ok(body)
+    }
+}
+
+/// Reads the body of the response, failing as soon as it grows over `limit`
+async fn read_body(mut body: hyper::Body, limit: Option<usize>) -> Result<String> {
+    use hyper::body::HttpBody;
+
+    let Some(limit) = limit else {
+        let body = hyper::body::to_bytes(body).await?;
+        return Ok(String::from_utf8(body.to_vec())?);
+    };
+    let mut bytes = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk?;
+        if bytes.len() + chunk.len() > limit {
+            return Err(crate::Error::ResponseTooLarge { limit }.into());
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(String::from_utf8(bytes)?)
+}
+
+impl crate::http::Client {
+    /// Creates a database client talking to sqld over the Unix domain socket at `socket_path`,
+    /// avoiding the overhead of TCP for co-located servers.
+    ///
+    /// Only available on Unix platforms, such as Linux and macOS.
+    ///
+    /// # Arguments
+    /// * `socket_path` - path of the socket sqld listens on
+    /// * `token` - auth token
+    pub fn connect_unix(socket_path: impl Into<PathBuf>, token: impl Into<String>) -> Self {
+        let inner = crate::http::InnerClient::Unix(HttpClient::new(socket_path));
+        Self::new(inner, "http://localhost/", token)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mock_server::{MockResponse, MockServer};
+
+    #[tokio::test]
+    async fn test_connect_unix() {
+        let path = std::env::temp_dir().join(format!(
+            "libsql-client-test-{}-{}.sock",
+            std::process::id(),
+            rand::random::<u32>()
+        ));
+        let server = MockServer::start_unix(&path, |_| {
+            MockResponse::execute(
+                r#"{"cols":[{"name":"n"}],"rows":[[{"type":"integer","value":"1"}]],"affected_row_count":0,"last_insert_rowid":null}"#,
+            )
+        })
+        .await;
+        let client = crate::http::Client::connect_unix(&path, "token");
+        let rs = client.execute("SELECT 1 AS n").await.unwrap();
+        assert_eq!(rs.rows[0].try_get::<i64>(0).unwrap(), 1);
+
+        let requests = server.requests();
+        assert_eq!(requests[0].method, "POST");
+        assert_eq!(requests[0].path, "/v2/pipeline");
+        assert_eq!(requests[0].header("authorization"), Some("Bearer token"));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_unix_socket_cancellation() {
+        use std::time::Duration;
+
+        let path = std::env::temp_dir().join(format!(
+            "libsql-client-test-{}-{}.sock",
+            std::process::id(),
+            rand::random::<u32>()
+        ));
+        let server = MockServer::start_unix(&path, |_| {
+            MockResponse::empty_execute().with_delay(Duration::from_secs(5))
+        })
+        .await;
+        let client = crate::http::Client::connect_unix(&path, "token");
+        let dropped = tokio::time::timeout(Duration::from_millis(100), client.execute("SELECT 1"));
+        assert!(dropped.await.is_err());
+        assert_eq!(server.requests().len(), 1);
+        for _ in 0..100 {
+            if server.aborted() == 1 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(server.aborted(), 1);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
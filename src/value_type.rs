@@ -0,0 +1,51 @@
+//! `ValueType` is the storage class of a [Value], as returned by the server.
+//!
+//! See <https://www.sqlite.org/datatype3.html#storage_classes_and_datatypes> for details.
+
+use crate::Value;
+
+/// Storage class of a single cell
+///
+/// Unlike the [Affinity](crate::Affinity) of a column, which comes from its declared type and
+/// is unknown for expressions like `AVG(x)`, the storage class is always known from the value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ValueType {
+    Null,
+    Integer,
+    Real,
+    Text,
+    Blob,
+}
+
+impl ValueType {
+    /// Returns the storage class of `value`
+    pub fn of(value: &Value) -> ValueType {
+        match value {
+            Value::Null => ValueType::Null,
+            Value::Integer { .. } => ValueType::Integer,
+            Value::Float { .. } => ValueType::Real,
+            Value::Text { .. } => ValueType::Text,
+            Value::Blob { .. } => ValueType::Blob,
+        }
+    }
+}
+
+/// Adds [`runtime_type()`](RuntimeType::runtime_type) to [Value], which is defined in another crate
+pub trait RuntimeType {
+    /// Returns the storage class of this value, see [ValueType]
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsql_client::{RuntimeType, Value, ValueType};
+    ///
+    /// assert_eq!(Value::from(1.5).runtime_type(), ValueType::Real);
+    /// ```
+    fn runtime_type(&self) -> ValueType;
+}
+
+impl RuntimeType for Value {
+    fn runtime_type(&self) -> ValueType {
+        ValueType::of(self)
+    }
+}
@@ -1,7 +1,7 @@
 use anyhow::Result;
 use worker::*;
 
-use crate::proto::pipeline;
+use crate::http::HttpRequest;
 
 #[derive(Clone, Debug)]
 pub struct HttpClient;
@@ -11,14 +11,23 @@ impl HttpClient {
         Self
     }
 
-    pub async fn send(
-        &self,
-        url: String,
-        auth: String,
-        body: String,
-    ) -> Result<pipeline::ServerMsg> {
+    /// Sends the request. Timeouts and response size limits are not supported
+    /// by this backend and are ignored: the body is read through the Fetch API
+    /// of the runtime, which does not allow bounding it.
+    ///
+    /// Any status other than 200 is returned as [`Error::Http`](crate::Error::Http) right away,
+    /// without retrying the request on another endpoint.
+    pub async fn send(&self, request: HttpRequest) -> Result<String> {
+        let HttpRequest {
+            url,
+            auth,
+            body,
+            request_id,
+            ..
+        } = request;
         let mut headers = Headers::new();
         headers.append("Authorization", &auth).ok();
+        headers.append("X-Request-Id", &request_id).ok();
 
         let request_init = RequestInit {
             body: Some(wasm_bindgen::JsValue::from_str(&body)),
@@ -34,12 +43,12 @@ impl HttpClient {
             .await
             .map_err(|e| anyhow::anyhow!("{e}"))?;
         if response.status_code() != 200 {
-            anyhow::bail!("Status {}", response.status_code());
+            let status = response.status_code();
+            let body = response.text().await.unwrap_or_default();
+            return Err(crate::Error::Http { status, body }.into());
         }
 
-        let resp: String = response.text().await.map_err(|e| anyhow::anyhow!("{e}"))?;
-        let response: pipeline::ServerMsg = serde_json::from_str(&resp)?;
-        Ok(response)
+        response.text().await.map_err(|e| anyhow::anyhow!("{e}"))
     }
 }
 